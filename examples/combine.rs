@@ -21,30 +21,25 @@ fn combine(
     uid: uuid::Uuid,
     bi: num_bigint::BigInt,
 ) -> (
-    (
-        bool,
-        Vec<u8>,
-        chrono::NaiveDate,
-        bigdecimal::BigDecimal,
-        f64,
-        CqlDuration,
-        f32,
-        i32,
-        i64,
-    ),
-    (
-        String,
-        Timestamp,
-        std::net::IpAddr,
-        i16,
-        i8,
-        Time,
-        uuid::Uuid,
-        num_bigint::BigInt,
-    ),
+    bool,
+    Vec<u8>,
+    chrono::NaiveDate,
+    bigdecimal::BigDecimal,
+    f64,
+    CqlDuration,
+    f32,
+    i32,
+    i64,
+    String,
+    Timestamp,
+    std::net::IpAddr,
+    i16,
+    i8,
+    Time,
+    uuid::Uuid,
+    num_bigint::BigInt,
 ) {
     (
-        (b, blob, date, bd, dbl, cqldur, flt, int32, int64),
-        (s, tstamp, ip, int16, int8, tim, uid, bi),
+        b, blob, date, bd, dbl, cqldur, flt, int32, int64, s, tstamp, ip, int16, int8, tim, uid, bi,
     )
 }
@@ -0,0 +1,7 @@
+use scylla_udf::{export_udf, ListView};
+
+// Counts the strings that contain the given word, without decoding all of them at once.
+#[export_udf]
+fn count_containing(strings: ListView<'_, String>, word: String) -> i32 {
+    strings.iter().filter(|s| s.contains(&word)).count() as i32
+}
@@ -0,0 +1,6 @@
+use scylla_udf::{export_udf, CqlValue};
+
+#[export_udf(types(arg = "map<text, frozen<list<int>>>", return = "text"))]
+fn describe(arg: Option<CqlValue>) -> CqlValue {
+    CqlValue::Text(format!("{:?}", arg))
+}
@@ -0,0 +1,173 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+// A token of a CQL type definition, e.g. `map<text, frozen<list<int>>>`
+#[derive(Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '<' => tokens.push(Token::Open),
+            '>' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            c if c.is_whitespace() => {}
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c.to_ascii_lowercase());
+                    chars.next();
+                }
+                tokens.push(Token::Name(name));
+                continue;
+            }
+            other => return Err(format!("unexpected character `{}`", other)),
+        }
+        chars.next();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    path: &'a TokenStream2,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if *token == expected => Ok(()),
+            _ => Err(format!("expected {}", what)),
+        }
+    }
+
+    fn parse_params(&mut self, name: &str) -> Result<Vec<TokenStream2>, String> {
+        self.expect(Token::Open, &format!("`<` after `{}`", name))?;
+        let mut params = vec![self.parse_type()?];
+        loop {
+            match self.next() {
+                Some(Token::Comma) => params.push(self.parse_type()?),
+                Some(Token::Close) => return Ok(params),
+                _ => return Err(format!("expected `,` or `>` in `{}` parameters", name)),
+            }
+        }
+    }
+
+    fn parse_exact_params(
+        &mut self,
+        name: &str,
+        count: usize,
+    ) -> Result<Vec<TokenStream2>, String> {
+        let params = self.parse_params(name)?;
+        if params.len() != count {
+            return Err(format!(
+                "`{}` takes {} type parameter(s), {} given",
+                name,
+                count,
+                params.len()
+            ));
+        }
+        Ok(params)
+    }
+
+    fn parse_type(&mut self) -> Result<TokenStream2, String> {
+        let path = self.path;
+        let name = match self.next() {
+            Some(Token::Name(name)) => name.as_str(),
+            _ => return Err("expected a type name".to_string()),
+        };
+        let native = match name {
+            "ascii" => quote! { Ascii },
+            "bigint" => quote! { BigInt },
+            "blob" => quote! { Blob },
+            "boolean" => quote! { Boolean },
+            "counter" => quote! { Counter },
+            "date" => quote! { Date },
+            "decimal" => quote! { Decimal },
+            "double" => quote! { Double },
+            "duration" => quote! { Duration },
+            "float" => quote! { Float },
+            "inet" => quote! { Inet },
+            "int" => quote! { Int },
+            "smallint" => quote! { SmallInt },
+            "text" | "varchar" => quote! { Text },
+            "time" => quote! { Time },
+            "timestamp" => quote! { Timestamp },
+            "timeuuid" => quote! { Timeuuid },
+            "tinyint" => quote! { TinyInt },
+            "uuid" => quote! { Uuid },
+            "varint" => quote! { Varint },
+            "frozen" => {
+                let mut params = self.parse_exact_params(name, 1)?;
+                return Ok(params.remove(0));
+            }
+            "list" | "set" => {
+                let params = self.parse_exact_params(name, 1)?;
+                let variant = if name == "list" {
+                    quote! { List }
+                } else {
+                    quote! { Set }
+                };
+                return Ok(quote! {
+                    ::#path::ColumnType::#variant(::#path::Box::new(#(#params)*))
+                });
+            }
+            "map" => {
+                let params = self.parse_exact_params(name, 2)?;
+                let (key, value) = (&params[0], &params[1]);
+                return Ok(quote! {
+                    ::#path::ColumnType::Map(
+                        ::#path::Box::new(#key),
+                        ::#path::Box::new(#value),
+                    )
+                });
+            }
+            "tuple" => {
+                let params = self.parse_params(name)?;
+                return Ok(quote! {
+                    ::#path::ColumnType::Tuple(::#path::vec![#(#params),*])
+                });
+            }
+            other => {
+                return Err(format!(
+                    "unknown CQL type `{}` (user-defined types are not supported, use #[scylla_udf::export_udt] instead)",
+                    other
+                ))
+            }
+        };
+        Ok(quote! { ::#path::ColumnType::#native })
+    }
+}
+
+// Parses a CQL type definition, e.g. `map<text, frozen<list<int>>>`, and returns an expression
+// constructing the corresponding ColumnType.
+pub(crate) fn parse_column_type(input: &str, path: &TokenStream2) -> Result<TokenStream2, String> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        path,
+    };
+    let column_type = parser.parse_type()?;
+    if parser.pos != tokens.len() {
+        return Err("unexpected tokens after the type definition".to_string());
+    }
+    Ok(column_type)
+}
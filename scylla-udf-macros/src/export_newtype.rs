@@ -53,10 +53,10 @@ fn impl_wasm_convertible(nst: &NewtypeStruct, path: &TokenStream2) -> TokenStrea
     quote! {
         impl #impl_generics ::#path::WasmConvertible for #struct_name #ty_generics #where_clause {
             type WasmType = <#struct_type as ::#path::WasmConvertible>::WasmType;
-            fn from_wasm(arg: Self::WasmType) -> Self {
-                #struct_name(<#struct_type as ::#path::WasmConvertible>::from_wasm(arg))
+            fn from_wasm(arg: Self::WasmType) -> ::core::result::Result<Self, ::#path::ConversionError> {
+                <#struct_type as ::#path::WasmConvertible>::from_wasm(arg).map(#struct_name)
             }
-            fn to_wasm(&self) -> Self::WasmType {
+            fn to_wasm(&self) -> ::core::result::Result<Self::WasmType, ::#path::ConversionError> {
                 <#struct_type as ::#path::WasmConvertible>::to_wasm(&self.0)
             }
         }
@@ -76,29 +76,60 @@ fn impl_to_col_type(nst: &NewtypeStruct, path: &TokenStream2) -> TokenStream2 {
     }
 }
 
-fn impl_value(nst: &NewtypeStruct, path: &TokenStream2) -> TokenStream2 {
+fn impl_serialize_cql(nst: &NewtypeStruct, path: &TokenStream2) -> TokenStream2 {
     let struct_name = &nst.struct_name;
     let struct_type = &nst.field_type;
     let (impl_generics, ty_generics, where_clause) = nst.generics.split_for_impl();
 
     quote! {
-        impl #impl_generics ::#path::Value for #struct_name #ty_generics #where_clause {
-            fn serialize(&self, buf: &mut ::std::vec::Vec<::core::primitive::u8>) -> ::std::result::Result<(), ::#path::ValueTooBig> {
-                <#struct_type as ::#path::Value>::serialize(&self.0, buf)
+        impl #impl_generics ::#path::SerializeCql for #struct_name #ty_generics #where_clause {
+            fn serialize_cql(&self, buf: &mut ::#path::Vec<::core::primitive::u8>) -> ::core::result::Result<(), ::#path::SerializeCqlError> {
+                <#struct_type as ::#path::SerializeCql>::serialize_cql(&self.0, buf)
+            }
+
+            fn size_hint(&self) -> ::core::primitive::usize {
+                <#struct_type as ::#path::SerializeCql>::size_hint(&self.0)
             }
         }
     }
 }
 
-fn impl_from_cql_val(nst: &NewtypeStruct, path: &TokenStream2) -> TokenStream2 {
+fn impl_deserialize_cql(nst: &NewtypeStruct, path: &TokenStream2) -> TokenStream2 {
     let struct_name = &nst.struct_name;
     let struct_type = &nst.field_type;
     let (impl_generics, ty_generics, where_clause) = nst.generics.split_for_impl();
 
     quote! {
-        impl #impl_generics ::#path::FromCqlVal<::#path::CqlValue> for #struct_name #ty_generics #where_clause {
-            fn from_cql(val: ::#path::CqlValue) -> ::std::result::Result<Self, ::#path::FromCqlValError> {
-                <#struct_type as ::#path::FromCqlVal<::#path::CqlValue>>::from_cql(val).map(|v| #struct_name(v))
+        impl #impl_generics ::#path::DeserializeCql for #struct_name #ty_generics #where_clause {
+            fn deserialize_cql(val: ::core::option::Option<::#path::CqlValue>) -> ::core::result::Result<Self, ::#path::FromCqlValError> {
+                <#struct_type as ::#path::DeserializeCql>::deserialize_cql(val).map(|v| #struct_name(v))
+            }
+
+            fn deserialize_cql_bytes(
+                typ: &::#path::ColumnType,
+                bytes: ::core::option::Option<&[::core::primitive::u8]>,
+            ) -> ::core::result::Result<Self, ::#path::FromCqlValError> {
+                <#struct_type as ::#path::DeserializeCql>::deserialize_cql_bytes(typ, bytes).map(|v| #struct_name(v))
+            }
+        }
+    }
+}
+
+fn impl_cql_ord(nst: &NewtypeStruct, path: &TokenStream2) -> TokenStream2 {
+    let struct_name = &nst.struct_name;
+    let struct_type = &nst.field_type;
+    // Like for UDTs, the impl only applies if the inner type implements CqlOrd
+    let mut generics = nst.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(for<'__cql_ord> #struct_type: ::#path::CqlOrd));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::#path::CqlOrd for #struct_name #ty_generics #where_clause {
+            fn cql_cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                <#struct_type as ::#path::CqlOrd>::cql_cmp(&self.0, &other.0)
             }
         }
     }
@@ -114,14 +145,16 @@ pub(crate) fn export_newtype(attrs: TokenStream, item: TokenStream) -> TokenStre
     };
     let wasm_convertible = impl_wasm_convertible(&newtype_struct, &path);
     let to_col_type = impl_to_col_type(&newtype_struct, &path);
-    let value = impl_value(&newtype_struct, &path);
-    let from_cql_val = impl_from_cql_val(&newtype_struct, &path);
+    let serialize_cql = impl_serialize_cql(&newtype_struct, &path);
+    let deserialize_cql = impl_deserialize_cql(&newtype_struct, &path);
+    let cql_ord = impl_cql_ord(&newtype_struct, &path);
     quote! {
         #st
         #wasm_convertible
         #to_col_type
-        #value
-        #from_cql_val
+        #serialize_cql
+        #deserialize_cql
+        #cql_ord
     }
     .into()
 }
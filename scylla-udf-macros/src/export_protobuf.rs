@@ -0,0 +1,90 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+fn impl_wasm_convertible(st: &syn::ItemStruct, path: &TokenStream2) -> TokenStream2 {
+    let struct_name = &st.ident;
+    let (impl_generics, ty_generics, where_clause) = st.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::#path::WasmConvertible for #struct_name #ty_generics #where_clause {
+            type WasmType = ::#path::WasmPtr;
+            fn from_wasm(arg: Self::WasmType) -> ::core::result::Result<Self, ::#path::ConversionError> {
+                <Self as ::#path::FromWasmPtr>::from_wasmptr(arg)
+            }
+            fn to_wasm(&self) -> ::core::result::Result<Self::WasmType, ::#path::ConversionError> {
+                <Self as ::#path::ToWasmPtr>::to_wasmptr(self)
+            }
+        }
+    }
+}
+
+fn impl_to_col_type(st: &syn::ItemStruct, path: &TokenStream2) -> TokenStream2 {
+    let struct_name = &st.ident;
+    let (impl_generics, ty_generics, where_clause) = st.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::#path::ToColumnType for #struct_name #ty_generics #where_clause {
+            fn to_column_type() -> ::#path::ColumnType {
+                ::#path::ColumnType::Blob
+            }
+        }
+    }
+}
+
+fn impl_serialize_cql(st: &syn::ItemStruct, path: &TokenStream2) -> TokenStream2 {
+    let struct_name = &st.ident;
+    let (impl_generics, ty_generics, where_clause) = st.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::#path::SerializeCql for #struct_name #ty_generics #where_clause {
+            fn serialize_cql(&self, buf: &mut ::#path::Vec<::core::primitive::u8>) -> ::core::result::Result<(), ::#path::SerializeCqlError> {
+                let bytes = <Self as ::#path::prost::Message>::encode_to_vec(self);
+                <::#path::Vec<::core::primitive::u8> as ::#path::SerializeCql>::serialize_cql(&bytes, buf)
+            }
+
+            fn size_hint(&self) -> ::core::primitive::usize {
+                4 + <Self as ::#path::prost::Message>::encoded_len(self)
+            }
+        }
+    }
+}
+
+// Fails with FromCqlValError::BadVal if the blob isn't a valid encoding of the message
+fn impl_deserialize_cql(st: &syn::ItemStruct, path: &TokenStream2) -> TokenStream2 {
+    let struct_name = &st.ident;
+    let (impl_generics, ty_generics, where_clause) = st.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::#path::DeserializeCql for #struct_name #ty_generics #where_clause {
+            fn deserialize_cql(val: ::core::option::Option<::#path::CqlValue>) -> ::core::result::Result<Self, ::#path::FromCqlValError> {
+                let bytes = <::#path::Vec<::core::primitive::u8> as ::#path::DeserializeCql>::deserialize_cql(val)?;
+                <Self as ::#path::prost::Message>::decode(bytes.as_slice())
+                    .map_err(|_| ::#path::FromCqlValError::BadVal)
+            }
+
+            fn deserialize_cql_bytes(
+                _typ: &::#path::ColumnType,
+                bytes: ::core::option::Option<&[::core::primitive::u8]>,
+            ) -> ::core::result::Result<Self, ::#path::FromCqlValError> {
+                let bytes = bytes.ok_or(::#path::FromCqlValError::ValIsNull)?;
+                <Self as ::#path::prost::Message>::decode(bytes)
+                    .map_err(|_| ::#path::FromCqlValError::BadVal)
+            }
+        }
+    }
+}
+
+pub(crate) fn export_protobuf(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    let st = syn::parse_macro_input!(item as syn::ItemStruct);
+    let atrs = syn::parse_macro_input!(attrs as syn::AttributeArgs);
+    let path = crate::path::get_path(&atrs).expect("Couldn't get path to the scylla_udf crate");
+    let wasm_convertible = impl_wasm_convertible(&st, &path);
+    let to_col_type = impl_to_col_type(&st, &path);
+    let serialize_cql = impl_serialize_cql(&st, &path);
+    let deserialize_cql = impl_deserialize_cql(&st, &path);
+    quote! {
+        #st
+        #wasm_convertible
+        #to_col_type
+        #serialize_cql
+        #deserialize_cql
+    }
+    .into()
+}
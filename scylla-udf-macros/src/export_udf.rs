@@ -1,22 +1,137 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
+use std::collections::HashMap;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, FnArg, ItemFn};
+use syn::{parse_macro_input, AttributeArgs, FnArg, ItemFn, Lit, Meta, MetaList, NestedMeta, Pat};
+
+// Key of the `types(...)` attribute entry describing the return type of the function
+const RETURN_KEY: &str = "return";
+
+// CQL types given explicitly in the `types(...)` attribute, by argument name or RETURN_KEY.
+// Each type is stored as an expression constructing the corresponding ColumnType.
+type ExplicitTypes = HashMap<String, (syn::LitStr, TokenStream2)>;
+
+// Separates the `types(...)` attributes from the rest of the attribute arguments
+fn split_types_attributes(atrs: AttributeArgs) -> (AttributeArgs, Vec<MetaList>) {
+    let mut remaining = AttributeArgs::new();
+    let mut types_lists = Vec::new();
+    for attr in atrs {
+        match attr {
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("types") => {
+                types_lists.push(list)
+            }
+            other => remaining.push(other),
+        }
+    }
+    (remaining, types_lists)
+}
+
+// Separates the `max_result_size = N` attribute from the rest of the attribute arguments
+fn split_max_result_size(
+    atrs: AttributeArgs,
+) -> Result<(AttributeArgs, Option<usize>), syn::Error> {
+    let mut remaining = AttributeArgs::new();
+    let mut max_result_size = None;
+    for attr in atrs {
+        match attr {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max_result_size") => {
+                let lit = match &nv.lit {
+                    Lit::Int(lit) => lit,
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "the `max_result_size` attribute should be an integer literal",
+                        ))
+                    }
+                };
+                // a CQL value can't be bigger than i32::MAX bytes
+                let value = lit.base10_parse::<i32>().map_err(|_| {
+                    syn::Error::new_spanned(
+                        lit,
+                        format!(
+                            "the `max_result_size` attribute can be at most {}",
+                            i32::MAX
+                        ),
+                    )
+                })?;
+                if max_result_size.replace(value as usize).is_some() {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "the `max_result_size` attribute was set multiple times",
+                    ));
+                }
+            }
+            other => remaining.push(other),
+        }
+    }
+    Ok((remaining, max_result_size))
+}
+
+fn parse_explicit_types(
+    types_lists: &[MetaList],
+    path: &TokenStream2,
+) -> Result<ExplicitTypes, syn::Error> {
+    let mut types = ExplicitTypes::new();
+    for nested in types_lists.iter().flat_map(|list| list.nested.iter()) {
+        let (name, lit) = match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) => match (nv.path.get_ident(), &nv.lit) {
+                (Some(ident), Lit::Str(lit)) => (ident.to_string(), lit),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        nv,
+                        "expected `name = \"cql type\"` in the `types` attribute",
+                    ))
+                }
+            },
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected `name = \"cql type\"` in the `types` attribute",
+                ))
+            }
+        };
+        let column_type = crate::cql_type::parse_column_type(&lit.value(), path)
+            .map_err(|e| syn::Error::new_spanned(lit, format!("invalid CQL type: {}", e)))?;
+        if types.insert(name, (lit.clone(), column_type)).is_some() {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "the type was set multiple times in the `types` attribute",
+            ));
+        }
+    }
+    Ok(types)
+}
 
 fn get_parameters_and_arguments(
     item: &ItemFn,
     path: &TokenStream2,
+    explicit_types: &mut ExplicitTypes,
 ) -> Result<(Vec<TokenStream2>, Vec<TokenStream2>), TokenStream2> {
     let inputs = &item.sig.inputs;
+    let fun_name_string = item.sig.ident.to_string();
     let mut parameters = Vec::with_capacity(inputs.len());
     let mut arguments = Vec::with_capacity(inputs.len());
     for (idx, i) in inputs.iter().enumerate() {
         if let FnArg::Typed(pat) = i {
             let ident = format_ident!("arg_{}", idx);
             let typ = &pat.ty;
-            parameters.push(quote! { #ident: <#typ as ::#path::WasmConvertible>::WasmType });
-            arguments.push(quote! { <#typ as ::#path::WasmConvertible>::from_wasm(#ident) });
+            let explicit_type = match &*pat.pat {
+                Pat::Ident(pat_ident) => explicit_types.remove(&pat_ident.ident.to_string()),
+                _ => None,
+            };
+            let conversion = if let Some((_, column_type)) = explicit_type {
+                parameters.push(quote! { #ident: ::#path::WasmPtr });
+                quote! {
+                    <#typ as ::#path::WasmConvertibleWithType>::from_wasm_with_type(#ident, &#column_type)
+                }
+            } else {
+                parameters.push(quote! { #ident: <#typ as ::#path::WasmConvertible>::WasmType });
+                quote! { <#typ as ::#path::WasmConvertible>::from_wasm(#ident) }
+            };
+            arguments.push(quote! {
+                #conversion.map_err(|e| e.in_udf(#fun_name_string, ::#path::ValuePosition::Argument(#idx)))?
+            });
         } else {
             return Err(syn::Error::new(
                 i.span(),
@@ -32,13 +147,41 @@ fn get_output_type_and_block(
     item: &ItemFn,
     arguments: &[TokenStream2],
     path: &TokenStream2,
+    explicit_types: &mut ExplicitTypes,
+    max_result_size: Option<usize>,
 ) -> Result<(TokenStream2, TokenStream2), TokenStream2> {
     let fun_name = item.sig.ident.clone();
+    let fun_name_string = fun_name.to_string();
     if let syn::ReturnType::Type(_, typ) = &item.sig.output {
+        let (output_type, conversion) = match explicit_types.remove(RETURN_KEY) {
+            Some((_, column_type)) => (
+                quote! { ::#path::WasmPtr },
+                quote! {
+                    <#typ as ::#path::WasmConvertibleWithType>::to_wasm_with_type(&#fun_name(#(#arguments),*), &#column_type)
+                },
+            ),
+            None => (
+                quote! { <#typ as ::#path::WasmConvertible>::WasmType },
+                quote! { <#typ as ::#path::WasmConvertible>::to_wasm(&#fun_name(#(#arguments),*)) },
+            ),
+        };
+        let max_result_size = max_result_size.map(|max_size| {
+            quote! { let _max_result_size = ::#path::MaxResultSize::set(#max_size); }
+        });
+        // The conversions are done in a closure, so that their errors can be propagated with `?`
+        // and reported in one place
         Ok((
-            quote! { -> <#typ as ::#path::WasmConvertible>::WasmType },
+            quote! { -> #output_type },
             quote! { {
-                <#typ as ::#path::WasmConvertible>::to_wasm(&#fun_name(#(#arguments),*))
+                let _call_scope = ::#path::CallScope::enter();
+                #max_result_size
+                let result = (move || -> ::core::result::Result<#output_type, ::#path::ConversionError> {
+                    #conversion.map_err(|e| e.in_udf(#fun_name_string, ::#path::ValuePosition::ReturnValue))
+                })();
+                match result {
+                    ::core::result::Result::Ok(result) => result,
+                    ::core::result::Result::Err(error) => ::#path::conversion_failed(error),
+                }
             } },
         ))
     } else {
@@ -87,22 +230,57 @@ fn get_exported_fun(
 /// }
 /// #[export_name = "foo"]
 /// extern "C" fn _scylla_internal_foo(arg1: u32, arg2: WasmPtr) -> u32 {
-///     foo(from_wasm(arg1), from_wasm(arg2)).to_wasm()
+///     foo(from_wasm(arg1)?, from_wasm(arg2)?).to_wasm()?
 /// }
 /// ```
+/// where a failed conversion is reported by `conversion_failed`, with the name of the function
+/// and the position of the value.
+///
+/// The call is made inside of a `CallScope`, so that the memory allocated during it can be
+/// released at once by the `Arena` allocator of `scylla-udf`.
+///
+/// Arguments and the return value can also be given an explicit CQL type using the `types` attribute,
+/// e.g. `#[scylla_udf::export_udf(types(arg2 = "list<int>", return = "text"))]`. Such values are
+/// converted using the `WasmConvertibleWithType` trait instead of `WasmConvertible`.
+///
+/// The `max_result_size = N` attribute limits the size of the returned value to N bytes
+/// while the value is converted.
 pub(crate) fn export_udf(attrs: TokenStream, input: TokenStream) -> TokenStream {
     let item = parse_macro_input!(input as ItemFn);
     let atrs = syn::parse_macro_input!(attrs as syn::AttributeArgs);
+    let (atrs, types_lists) = split_types_attributes(atrs);
+    let (atrs, max_result_size) = match split_max_result_size(atrs) {
+        Ok(split) => split,
+        Err(e) => return e.to_compile_error().into(),
+    };
     let path = crate::path::get_path(&atrs).expect("Couldn't get path to the scylla_udf crate");
-    let (parameters, arguments) = match get_parameters_and_arguments(&item, &path) {
-        Ok(pa) => pa,
-        Err(e) => return e.into(),
+    let mut explicit_types = match parse_explicit_types(&types_lists, &path) {
+        Ok(types) => types,
+        Err(e) => return e.to_compile_error().into(),
     };
-    let (output_type_token, exported_block) =
-        match get_output_type_and_block(&item, &arguments, &path) {
-            Ok(oe) => oe,
+    let (parameters, arguments) =
+        match get_parameters_and_arguments(&item, &path, &mut explicit_types) {
+            Ok(pa) => pa,
             Err(e) => return e.into(),
         };
+    let (output_type_token, exported_block) = match get_output_type_and_block(
+        &item,
+        &arguments,
+        &path,
+        &mut explicit_types,
+        max_result_size,
+    ) {
+        Ok(oe) => oe,
+        Err(e) => return e.into(),
+    };
+    if let Some((lit, _)) = explicit_types.values().next() {
+        return syn::Error::new_spanned(
+            lit,
+            "the `types` attribute refers to a name that is not an argument of the function",
+        )
+        .to_compile_error()
+        .into();
+    }
     let exported_fun = get_exported_fun(&item, &parameters, output_type_token, exported_block);
     quote! {
         #item
@@ -2,7 +2,7 @@ use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
-use syn::Fields;
+use syn::{Fields, FieldsNamed};
 
 pub fn impl_wasm_convertible(st: &syn::ItemStruct, path: &TokenStream2) -> TokenStream2 {
     let struct_name = &st.ident;
@@ -10,29 +10,34 @@ pub fn impl_wasm_convertible(st: &syn::ItemStruct, path: &TokenStream2) -> Token
     quote! {
         impl #impl_generics ::#path::WasmConvertible for #struct_name #ty_generics #where_clause {
             type WasmType = ::#path::WasmPtr;
-            fn from_wasm(arg: Self::WasmType) -> Self {
+            fn from_wasm(arg: Self::WasmType) -> ::core::result::Result<Self, ::#path::ConversionError> {
                 <Self as ::#path::FromWasmPtr>::from_wasmptr(arg)
             }
-            fn to_wasm(&self) -> Self::WasmType {
+            fn to_wasm(&self) -> ::core::result::Result<Self::WasmType, ::#path::ConversionError> {
                 <Self as ::#path::ToWasmPtr>::to_wasmptr(self)
             }
         }
     }
 }
 
-pub fn impl_to_col_type(st: &syn::ItemStruct, path: &TokenStream2) -> TokenStream2 {
+fn get_named_fields(st: &syn::ItemStruct) -> Result<&FieldsNamed, TokenStream2> {
+    match &st.fields {
+        Fields::Named(named_fields) => Ok(named_fields),
+        _ => Err(syn::Error::new_spanned(
+            st,
+            "#[scylla_udf::export_udt] works only for structs with named fields.",
+        )
+        .to_compile_error()),
+    }
+}
+
+pub fn impl_to_col_type(
+    st: &syn::ItemStruct,
+    struct_fields: &FieldsNamed,
+    path: &TokenStream2,
+) -> TokenStream2 {
     let struct_name = &st.ident;
     let struct_name_string = struct_name.to_string();
-    let struct_fields = match &st.fields {
-        Fields::Named(named_fields) => named_fields,
-        _ => {
-            return syn::Error::new_spanned(
-                st,
-                "#[scylla_udf::export_udt] works only for structs with named fields.",
-            )
-            .to_compile_error()
-        }
-    };
     let (impl_generics, ty_generics, where_clause) = st.generics.split_for_impl();
     let fields_column_types = struct_fields.named.iter().map(|field| {
         // we matched with Fields::Named above, so we can unwrap
@@ -45,33 +50,206 @@ pub fn impl_to_col_type(st: &syn::ItemStruct, path: &TokenStream2) -> TokenStrea
     quote! {
         impl #impl_generics ::#path::ToColumnType for #struct_name #ty_generics #where_clause {
             fn to_column_type() -> ::#path::ColumnType {
-                use ::std::string::ToString;
+                use ::#path::ToString;
                 ::#path::ColumnType::UserDefinedType {
                     type_name: #struct_name_string.to_string(),
                     keyspace: "".to_string(),
-                    field_types: ::std::vec![#(#fields_column_types)*],
+                    field_types: ::#path::vec![#(#fields_column_types)*],
                 }
             }
         }
     }
 }
 
+pub fn impl_serialize_cql(
+    st: &syn::ItemStruct,
+    struct_fields: &FieldsNamed,
+    path: &TokenStream2,
+) -> TokenStream2 {
+    let struct_name = &st.ident;
+    let (impl_generics, ty_generics, where_clause) = st.generics.split_for_impl();
+    // A UDT value is serialized like a tuple of its fields, in the order of the struct definition
+    let serialize_fields = struct_fields.named.iter().map(|field| {
+        let field_name = &field.ident;
+        let field_type = &field.ty;
+        quote_spanned! {field.span() =>
+            <#field_type as ::#path::SerializeCql>::serialize_cql(&self.#field_name, buf)?;
+        }
+    });
+    let field_size_hints = struct_fields.named.iter().map(|field| {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_type = &field.ty;
+        quote_spanned! {field.span() =>
+            + <#field_type as ::#path::SerializeCql>::size_hint(&self.#field_name)
+        }
+    });
+    quote! {
+        impl #impl_generics ::#path::SerializeCql for #struct_name #ty_generics #where_clause {
+            fn serialize_cql(&self, buf: &mut ::#path::Vec<::core::primitive::u8>) -> ::core::result::Result<(), ::#path::SerializeCqlError> {
+                ::#path::serialize_with_size(buf, |buf| {
+                    #(#serialize_fields)*
+                    ::core::result::Result::Ok(())
+                })
+            }
+
+            fn size_hint(&self) -> ::core::primitive::usize {
+                4 #(#field_size_hints)*
+            }
+        }
+    }
+}
+
+pub fn impl_deserialize_cql(
+    st: &syn::ItemStruct,
+    struct_fields: &FieldsNamed,
+    path: &TokenStream2,
+) -> TokenStream2 {
+    let struct_name = &st.ident;
+    let (impl_generics, ty_generics, where_clause) = st.generics.split_for_impl();
+    // The received fields are in the same order as the struct fields, but the value
+    // may lack trailing fields (e.g. if they were added to the UDT later), which are then null
+    let deserialize_fields = struct_fields.named.iter().map(|field| {
+        // we matched with Fields::Named above, so we can unwrap
+        let field_name = field.ident.as_ref().unwrap();
+        let field_name_string = field_name.to_string();
+        let field_type = &field.ty;
+        quote_spanned! {field.span() =>
+            #field_name: <#field_type as ::#path::DeserializeCql>::deserialize_cql(
+                match fields_iter.peek() {
+                    ::core::option::Option::Some((name, _)) if name == #field_name_string => {
+                        fields_iter.next().and_then(|(_, value)| value)
+                    }
+                    _ => ::core::option::Option::None,
+                }
+            )?,
+        }
+    });
+    let deserialize_fields_bytes = struct_fields.named.iter().map(|field| {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_name_string = field_name.to_string();
+        let field_type = &field.ty;
+        quote_spanned! {field.span() =>
+            #field_name: match field_types.peek() {
+                ::core::option::Option::Some((name, typ)) if name == #field_name_string => {
+                    let value = ::#path::read_field(&mut buf)?;
+                    field_types.next();
+                    <#field_type as ::#path::DeserializeCql>::deserialize_cql_bytes(typ, value)?
+                }
+                _ => <#field_type as ::#path::DeserializeCql>::deserialize_cql(::core::option::Option::None)?,
+            },
+        }
+    });
+    quote! {
+        impl #impl_generics ::#path::DeserializeCql for #struct_name #ty_generics #where_clause {
+            fn deserialize_cql(val: ::core::option::Option<::#path::CqlValue>) -> ::core::result::Result<Self, ::#path::FromCqlValError> {
+                use ::core::iter::{IntoIterator, Iterator};
+                let mut fields_iter = match val {
+                    ::core::option::Option::Some(::#path::CqlValue::UserDefinedType { fields, .. }) => {
+                        fields.into_iter().peekable()
+                    }
+                    ::core::option::Option::None => {
+                        return ::core::result::Result::Err(::#path::FromCqlValError::ValIsNull)
+                    }
+                    _ => return ::core::result::Result::Err(::#path::FromCqlValError::BadCqlType),
+                };
+                let result = #struct_name {
+                    #(#deserialize_fields)*
+                };
+                if fields_iter.next().is_some() {
+                    return ::core::result::Result::Err(::#path::FromCqlValError::BadCqlType);
+                }
+                ::core::result::Result::Ok(result)
+            }
+
+            fn deserialize_cql_bytes(
+                typ: &::#path::ColumnType,
+                bytes: ::core::option::Option<&[::core::primitive::u8]>,
+            ) -> ::core::result::Result<Self, ::#path::FromCqlValError> {
+                use ::core::iter::Iterator;
+                let mut field_types = match typ {
+                    ::#path::ColumnType::UserDefinedType { field_types, .. } => field_types.iter().peekable(),
+                    _ => return ::core::result::Result::Err(::#path::FromCqlValError::BadCqlType),
+                };
+                let mut buf = match bytes {
+                    ::core::option::Option::Some(bytes) if !bytes.is_empty() => bytes,
+                    ::core::option::Option::Some(_) => {
+                        return ::core::result::Result::Err(::#path::FromCqlValError::BadCqlType)
+                    }
+                    ::core::option::Option::None => {
+                        return ::core::result::Result::Err(::#path::FromCqlValError::ValIsNull)
+                    }
+                };
+                let result = #struct_name {
+                    #(#deserialize_fields_bytes)*
+                };
+                if field_types.next().is_some() || !buf.is_empty() {
+                    return ::core::result::Result::Err(::#path::FromCqlValError::BadCqlType);
+                }
+                ::core::result::Result::Ok(result)
+            }
+        }
+    }
+}
+
+pub fn impl_cql_ord(
+    st: &syn::ItemStruct,
+    struct_fields: &FieldsNamed,
+    path: &TokenStream2,
+) -> TokenStream2 {
+    let struct_name = &st.ident;
+    let mut generics = st.generics.clone();
+    let where_clause = generics.make_where_clause();
+    // The impl only applies if all fields implement CqlOrd, so that structs with other fields can
+    // still be used as UDTs. The bounds are higher-ranked, so that unsatisfied bounds on concrete
+    // types disable the impl instead of failing to compile.
+    for field in struct_fields.named.iter() {
+        let field_type = &field.ty;
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(for<'__cql_ord> #field_type: ::#path::CqlOrd));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // UDT values are compared field by field, in the order of the struct definition
+    let compare_fields = struct_fields.named.iter().map(|field| {
+        let field_name = &field.ident;
+        let field_type = &field.ty;
+        quote_spanned! {field.span() =>
+            match <#field_type as ::#path::CqlOrd>::cql_cmp(&self.#field_name, &other.#field_name) {
+                ::core::cmp::Ordering::Equal => {}
+                ordering => return ordering,
+            }
+        }
+    });
+    quote! {
+        impl #impl_generics ::#path::CqlOrd for #struct_name #ty_generics #where_clause {
+            fn cql_cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                #(#compare_fields)*
+                ::core::cmp::Ordering::Equal
+            }
+        }
+    }
+}
+
 pub(crate) fn export_udt(attrs: TokenStream, item: TokenStream) -> TokenStream {
     let st = syn::parse_macro_input!(item as syn::ItemStruct);
     let atrs = syn::parse_macro_input!(attrs as syn::AttributeArgs);
-    let path_no_internal = crate::path::get_path_no_internal(&atrs)
-        .expect("Couldn't get path to the scylla_udf crate");
-    let path_string = path_no_internal.to_string();
-    let path = crate::path::append_internal(&path_no_internal);
+    let path = crate::path::get_path(&atrs).expect("Couldn't get path to the scylla_udf crate");
+    let struct_fields = match get_named_fields(&st) {
+        Ok(fields) => fields,
+        Err(e) => return e.into(),
+    };
     let wasm_convertible = impl_wasm_convertible(&st, &path);
-    let to_col_type = impl_to_col_type(&st, &path);
+    let to_col_type = impl_to_col_type(&st, struct_fields, &path);
+    let serialize_cql = impl_serialize_cql(&st, struct_fields, &path);
+    let deserialize_cql = impl_deserialize_cql(&st, struct_fields, &path);
+    let cql_ord = impl_cql_ord(&st, struct_fields, &path);
     quote! {
-        #[derive(::#path::FromUserType)]
-        #[derive(::#path::IntoUserType)]
-        #[scylla_crate = #path_string]
         #st
         #wasm_convertible
         #to_col_type
+        #serialize_cql
+        #deserialize_cql
+        #cql_ord
     }
     .into()
 }
@@ -1,6 +1,8 @@
 use proc_macro::TokenStream;
 
+mod cql_type;
 mod export_newtype;
+mod export_protobuf;
 mod export_udf;
 mod export_udt;
 
@@ -19,4 +21,9 @@ pub fn export_newtype(attrs: TokenStream, item: TokenStream) -> TokenStream {
     export_newtype::export_newtype(attrs, item)
 }
 
+#[proc_macro_attribute]
+pub fn export_protobuf(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    export_protobuf::export_protobuf(attrs, item)
+}
+
 pub(crate) mod path;
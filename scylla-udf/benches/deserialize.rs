@@ -0,0 +1,71 @@
+//! Compares deserializing UDF arguments directly from their serialized bytes, as `#[export_udf]` does,
+//! with deserializing them to a `CqlValue` first, for argument types of the collection-heavy examples.
+//!
+//! Run with `cargo bench --bench deserialize`, or with a Wasm runtime as described in the README
+//! to measure the performance inside of a UDF.
+
+use scylla_udf::codec::decode_value;
+use scylla_udf::mapping::{DeserializeCql, SerializeCql, ToColumnType};
+use std::collections::{BTreeMap, BTreeSet};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 2000;
+
+fn serialize<T: SerializeCql>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.serialize_cql(&mut buf).unwrap();
+    buf.split_off(4)
+}
+
+fn measure(f: impl Fn()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn bench<T: DeserializeCql + SerializeCql + ToColumnType>(name: &str, value: T) {
+    let typ = T::to_column_type();
+    let bytes = serialize(&value);
+    let via_cql_value = measure(|| {
+        let val = decode_value(&typ, &bytes).unwrap();
+        black_box(T::deserialize_cql(Some(val)).unwrap());
+    });
+    let direct = measure(|| {
+        black_box(T::deserialize_cql_bytes(&typ, Some(&bytes)).unwrap());
+    });
+    println!(
+        "{:<10} {:>6} bytes  via CqlValue: {:>10.2?}  direct: {:>10.2?}  ({:.1}x)",
+        name,
+        bytes.len(),
+        via_cql_value,
+        direct,
+        via_cql_value.as_secs_f64() / direct.as_secs_f64()
+    );
+}
+
+fn words(count: usize) -> impl Iterator<Item = String> {
+    (0..count).map(|i| format!("word{}", i))
+}
+
+fn main() {
+    // commas(strings: list<text>)
+    bench("commas", words(1000).collect::<Vec<_>>());
+    // keys(map: map<text, text>)
+    bench(
+        "keys",
+        words(1000)
+            .map(|word| (word.clone(), word))
+            .collect::<BTreeMap<_, _>>(),
+    );
+    // topn_row(acc_tup: tuple<int, set<text>>)
+    bench("topn", (100_i32, words(100).collect::<BTreeSet<_>>()));
+    bench(
+        "nested",
+        (0..100)
+            .map(|i| (0..100).map(|j| i * j).collect::<Vec<i64>>())
+            .collect::<Vec<_>>(),
+    );
+}
@@ -1,25 +1,117 @@
-extern "C" {
-    fn malloc(size: u32) -> *mut u8;
-    fn free(ptr: *mut u8);
+use alloc::alloc::{alloc, dealloc, Layout};
+
+// Buffers shared with Scylla are allocated with the Rust allocator and preceded by a header with
+// their size, which is needed to free them. The header has the same format as the size prefix
+// of serialized values, so a serialized value can be passed to Scylla without copying it.
+// The buffers are allocated outside of the arena (with the `arena` feature), because they outlive calls.
+pub(crate) const HEADER_SIZE: usize = 4;
+
+// The layout of a buffer of the given size, including the header
+pub(crate) fn buffer_layout(size: u32) -> Option<Layout> {
+    let total_size = (size as usize).checked_add(HEADER_SIZE)?;
+    Layout::from_size_align(total_size, 1).ok()
+}
+
+// Allocates a buffer of the given size with its header, and returns the pointer past the header,
+// or null if the allocation fails
+pub(crate) fn alloc_buffer(size: u32) -> *mut u8 {
+    let layout = match buffer_layout(size) {
+        Some(layout) => layout,
+        None => return core::ptr::null_mut(),
+    };
+    // SAFETY: the layout is not empty, because it includes the header
+    let ptr = crate::arena::outside_arena(|| unsafe { alloc(layout) });
+    if ptr.is_null() {
+        return ptr;
+    }
+    // SAFETY: the buffer is at least HEADER_SIZE bytes long
+    unsafe {
+        ptr.copy_from_nonoverlapping(size.to_be_bytes().as_ptr(), HEADER_SIZE);
+        ptr.add(HEADER_SIZE)
+    }
 }
 
+/// # Safety
+/// - the pointer must be null or returned by `alloc_buffer` and not freed yet
+pub(crate) unsafe fn free_buffer(ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+    let ptr = ptr.sub(HEADER_SIZE);
+    let mut size = [0; HEADER_SIZE];
+    size.as_mut_ptr().copy_from_nonoverlapping(ptr, HEADER_SIZE);
+    // the layout was valid when the buffer was allocated
+    dealloc(ptr, buffer_layout(u32::from_be_bytes(size)).unwrap())
+}
+
+// The functions used by Scylla to pass buffers to the UDF are only exported on wasm32, where
+// pointers fit in the 32-bit values of the ABI
+
 /// # Safety
 /// - caller must ensure that the size is valid, if the allocation fails
 /// - the caller must not dereference the returned pointer
+#[cfg(target_arch = "wasm32")]
 #[no_mangle]
 #[doc(hidden)]
 pub(crate) unsafe extern "C" fn _scylla_malloc(size: u32) -> u32 {
-    malloc(size) as u32
+    alloc_buffer(size) as u32
 }
 
 /// # Safety
 /// - caller must ensure that the pointer is valid
+#[cfg(target_arch = "wasm32")]
 #[no_mangle]
 #[doc(hidden)]
 pub(crate) unsafe extern "C" fn _scylla_free(ptr: u32) {
-    free(ptr as *mut u8)
+    free_buffer(ptr as *mut u8)
 }
 
+#[cfg(target_arch = "wasm32")]
 #[no_mangle]
 #[doc(hidden)]
 static _scylla_abi: u32 = 2;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malloc_and_free() {
+        for size in [0, 1, 7, 1 << 20] {
+            // SAFETY: the buffer is only accessed within its size, and freed once
+            unsafe {
+                let ptr = alloc_buffer(size);
+                assert!(!ptr.is_null());
+                let mut header = [0; HEADER_SIZE];
+                header
+                    .as_mut_ptr()
+                    .copy_from_nonoverlapping(ptr.sub(HEADER_SIZE), HEADER_SIZE);
+                assert_eq!(u32::from_be_bytes(header), size);
+                ptr.write_bytes(0xab, size as usize);
+                free_buffer(ptr);
+            }
+        }
+        // SAFETY: freeing a null pointer does nothing
+        unsafe { free_buffer(core::ptr::null_mut()) };
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[test]
+    fn exported_malloc_and_free() {
+        // SAFETY: the buffer is only accessed within its size, and freed once
+        unsafe {
+            let ptr = _scylla_malloc(3);
+            assert_ne!(ptr, 0);
+            (ptr as *mut u8).write_bytes(0xab, 3);
+            _scylla_free(ptr);
+            _scylla_free(0);
+        }
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn oversized_malloc() {
+        // The header doesn't fit in the 32-bit address space
+        assert!(alloc_buffer(u32::MAX - 1).is_null());
+    }
+}
@@ -0,0 +1,343 @@
+// With the `arena` feature, the `Arena` allocator can be installed as the global allocator. Memory allocated
+// during a call of a UDF is then taken from an arena, which is reset when the call returns. Buffers passed
+// between Scylla and the UDF are allocated outside of the arena. The arena is only used on wasm32 targets
+// without threads, where it doesn't need synchronization.
+#[cfg(all(
+    feature = "arena",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+mod imp {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::{Cell, UnsafeCell};
+    use std::mem;
+    use std::ptr;
+
+    // Memory is taken from the system allocator in chunks of whole Wasm pages, aligned to the page size,
+    // which are kept after the arena is reset and reused by the following calls
+    const PAGE_SIZE: usize = 64 * 1024;
+    // The number of pages in the 32-bit address space
+    const PAGES: usize = 1 << 16;
+
+    struct ChunkHeader {
+        next: *mut ChunkHeader,
+        // including the header
+        size: usize,
+    }
+
+    const HEADER_SIZE: usize = mem::size_of::<ChunkHeader>();
+
+    pub(crate) struct ArenaAllocator {
+        // a bit for each page, set if the page belongs to a chunk, so that `contains` doesn't
+        // have to look through the chunks
+        pages: UnsafeCell<[u64; PAGES / 64]>,
+        first: Cell<*mut ChunkHeader>,
+        current: Cell<*mut ChunkHeader>,
+        // offset of the free space in the current chunk
+        offset: Cell<usize>,
+        // number of entered call scopes
+        calls: Cell<u32>,
+        // number of nested `outside_arena` calls
+        suspended: Cell<u32>,
+    }
+
+    // SAFETY: the arena is only used on targets without threads
+    unsafe impl Sync for ArenaAllocator {}
+
+    pub(crate) static ARENA: ArenaAllocator = ArenaAllocator::new();
+
+    fn align_up(addr: usize, align: usize) -> Option<usize> {
+        Some(addr.checked_add(align - 1)? & !(align - 1))
+    }
+
+    impl ArenaAllocator {
+        const fn new() -> ArenaAllocator {
+            ArenaAllocator {
+                pages: UnsafeCell::new([0; PAGES / 64]),
+                first: Cell::new(ptr::null_mut()),
+                current: Cell::new(ptr::null_mut()),
+                offset: Cell::new(HEADER_SIZE),
+                calls: Cell::new(0),
+                suspended: Cell::new(0),
+            }
+        }
+
+        fn is_active(&self) -> bool {
+            self.calls.get() > 0 && self.suspended.get() == 0
+        }
+
+        fn contains(&self, ptr: *mut u8) -> bool {
+            let page = ptr as usize / PAGE_SIZE;
+            // SAFETY: the arena is only used on targets without threads, and no reference to
+            // the bitmap is kept
+            let pages = unsafe { &*self.pages.get() };
+            pages[page / 64] & (1 << (page % 64)) != 0
+        }
+
+        fn add_pages(&self, chunk: *mut ChunkHeader, size: usize) {
+            let first = chunk as usize / PAGE_SIZE;
+            // SAFETY: like in `contains`
+            let pages = unsafe { &mut *self.pages.get() };
+            for page in first..first + size / PAGE_SIZE {
+                pages[page / 64] |= 1 << (page % 64);
+            }
+        }
+
+        // Allocates from the current chunk, moving to the next ones if it's full
+        unsafe fn bump(&self, layout: Layout) -> *mut u8 {
+            let mut chunk = self.current.get();
+            while !chunk.is_null() {
+                let start = chunk as usize + self.offset.get();
+                if let Some(end) = align_up(start, layout.align())
+                    .and_then(|aligned| aligned.checked_add(layout.size()))
+                {
+                    if end <= chunk as usize + (*chunk).size {
+                        self.offset.set(end - chunk as usize);
+                        return (end - layout.size()) as *mut u8;
+                    }
+                }
+                if (*chunk).next.is_null() {
+                    break;
+                }
+                chunk = (*chunk).next;
+                self.current.set(chunk);
+                self.offset.set(HEADER_SIZE);
+            }
+            self.add_chunk(chunk, layout)
+        }
+
+        // Appends a chunk big enough for the allocation after the last one, and allocates from it
+        unsafe fn add_chunk(&self, last: *mut ChunkHeader, layout: Layout) -> *mut u8 {
+            let size = match layout
+                .size()
+                .checked_add(layout.align() + HEADER_SIZE)
+                .and_then(|size| align_up(size, PAGE_SIZE))
+            {
+                Some(size) => size,
+                None => return ptr::null_mut(),
+            };
+            let chunk_layout = match Layout::from_size_align(size, PAGE_SIZE) {
+                Ok(chunk_layout) => chunk_layout,
+                Err(_) => return ptr::null_mut(),
+            };
+            let chunk = System.alloc(chunk_layout) as *mut ChunkHeader;
+            if chunk.is_null() {
+                return ptr::null_mut();
+            }
+            chunk.write(ChunkHeader {
+                next: ptr::null_mut(),
+                size,
+            });
+            self.add_pages(chunk, size);
+            if last.is_null() {
+                self.first.set(chunk);
+            } else {
+                (*last).next = chunk;
+            }
+            self.current.set(chunk);
+            self.offset.set(HEADER_SIZE);
+            self.bump(layout)
+        }
+
+        fn reset(&self) {
+            self.current.set(self.first.get());
+            self.offset.set(HEADER_SIZE);
+        }
+    }
+
+    // Memory allocated outside of the arena is managed by the system allocator, and memory in the
+    // arena is only freed when the arena is reset. Memory allocated by the system allocator is
+    // never moved to the arena, because it may outlive the call.
+    unsafe impl GlobalAlloc for ArenaAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if self.is_active() {
+                self.bump(layout)
+            } else {
+                System.alloc(layout)
+            }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            if !self.contains(ptr) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if !self.contains(ptr) {
+                return System.realloc(ptr, layout, new_size);
+            }
+            let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+            let new_ptr = self.alloc(new_layout);
+            if !new_ptr.is_null() {
+                ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            }
+            new_ptr
+        }
+    }
+
+    pub(crate) fn enter_call() {
+        ARENA.calls.set(ARENA.calls.get() + 1);
+    }
+
+    pub(crate) fn exit_call() {
+        ARENA.calls.set(ARENA.calls.get() - 1);
+        if ARENA.calls.get() == 0 {
+            ARENA.reset();
+        }
+    }
+
+    pub(crate) fn outside_arena<R>(f: impl FnOnce() -> R) -> R {
+        struct Resume;
+        impl Drop for Resume {
+            fn drop(&mut self) {
+                ARENA.suspended.set(ARENA.suspended.get() - 1);
+            }
+        }
+        ARENA.suspended.set(ARENA.suspended.get() + 1);
+        let _resume = Resume;
+        f()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn in_arena(ptr: *const u8) -> bool {
+        ARENA.contains(ptr as *mut u8)
+    }
+}
+
+#[cfg(not(all(
+    feature = "arena",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+)))]
+mod imp {
+    // On other targets the allocations are passed to the system allocator
+    #[cfg(feature = "arena")]
+    pub(crate) static ARENA: std::alloc::System = std::alloc::System;
+
+    pub(crate) fn enter_call() {}
+
+    pub(crate) fn exit_call() {}
+
+    pub(crate) fn outside_arena<R>(f: impl FnOnce() -> R) -> R {
+        f()
+    }
+}
+
+pub(crate) use imp::outside_arena;
+#[cfg(feature = "arena")]
+use std::alloc::{GlobalAlloc, Layout};
+
+/// A global allocator that takes the memory allocated during a call of a UDF from an arena,
+/// and resets the arena when the call returns, instead of freeing each allocation separately.
+///
+/// The arena is only used on wasm32 targets without threads. On other targets, allocations
+/// are passed to the system allocator. Installing it requires acknowledging that memory
+/// allocated during a call doesn't outlive it:
+/// ```
+/// #[global_allocator]
+/// // SAFETY: the UDFs don't keep values allocated during a call after it returns
+/// static ALLOCATOR: scylla_udf::Arena = unsafe { scylla_udf::Arena::new() };
+/// ```
+#[cfg(feature = "arena")]
+pub struct Arena(());
+
+#[cfg(feature = "arena")]
+impl Arena {
+    /// Creates the allocator, which should be installed with `#[global_allocator]`.
+    ///
+    /// # Safety
+    ///
+    /// Values allocated during a call of a UDF must not be used after the call returns, e.g. by
+    /// keeping them in a `static` or a `thread_local!`, including lazily initialized buffers such
+    /// as the one used by `println!`. Their memory is reused by the following calls.
+    pub const unsafe fn new() -> Arena {
+        Arena(())
+    }
+}
+
+#[cfg(feature = "arena")]
+unsafe impl GlobalAlloc for Arena {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        imp::ARENA.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        imp::ARENA.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        imp::ARENA.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Marks a call of an exported UDF. With the `arena` feature, memory allocated while a `CallScope`
+/// exists is taken from the arena, which is reset when the outermost `CallScope` is dropped.
+#[doc(hidden)]
+pub struct CallScope(());
+
+impl CallScope {
+    pub fn enter() -> CallScope {
+        imp::enter_call();
+        CallScope(())
+    }
+}
+
+impl Drop for CallScope {
+    fn drop(&mut self) {
+        imp::exit_call();
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "arena",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+mod tests {
+    use super::imp::in_arena;
+    use super::*;
+    use crate::to_wasmptr::ToWasmPtr;
+
+    #[global_allocator]
+    // SAFETY: the tests don't use values allocated in a call scope after it ends
+    static ALLOCATOR: Arena = unsafe { Arena::new() };
+
+    #[test]
+    fn allocations_in_call_scope() {
+        let before = Box::new([1_u8; 10]);
+        let (in_scope, result) = {
+            let _scope = CallScope::enter();
+            let value = vec![String::from("abc"); 100];
+            (value.as_ptr() as usize, value.to_wasmptr().unwrap())
+        };
+        assert!(!in_arena(before.as_ptr()));
+        assert!(in_arena(in_scope as *const u8));
+        // the result is allocated outside of the arena, so it's still valid after the scope ends
+        let _scope = CallScope::enter();
+        let overwrite = vec![String::from("xyz"); 100];
+        assert!(in_arena(overwrite.as_ptr() as *const u8));
+        assert_eq!(
+            <Vec<String> as crate::from_wasmptr::FromWasmPtr>::from_wasmptr(result).unwrap(),
+            vec![String::from("abc"); 100]
+        );
+    }
+
+    #[test]
+    fn system_allocations_stay_outside() {
+        let mut outside = Vec::<u64>::with_capacity(1);
+        let _scope = CallScope::enter();
+        // growing a vector allocated before the call doesn't move it into the arena
+        outside.extend(0..1000);
+        assert!(!in_arena(outside.as_ptr() as *const u8));
+        // allocations bigger than a chunk get a chunk of their own
+        let big = vec![7_u8; 1 << 20];
+        assert!(in_arena(big.as_ptr()));
+        assert!(big.iter().all(|&b| b == 7));
+        let mut grown = Vec::<u32>::new();
+        grown.extend(0..10_000);
+        assert!(in_arena(grown.as_ptr() as *const u8));
+        assert_eq!(grown.iter().sum::<u32>(), 49_995_000);
+    }
+}
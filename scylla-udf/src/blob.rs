@@ -0,0 +1,135 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use std::error::Error;
+
+/// A binary encoding of Rust values, used by [`Blob`].
+///
+/// The `msgpack`, `cbor` and `bincode` features of `scylla-udf` provide the [`MessagePack`],
+/// [`Cbor`] and [`Bincode`] codecs. Other encodings can be used by implementing this trait.
+pub trait BlobCodec {
+    /// Appends the encoded value to the buffer.
+    fn encode<T: serde::Serialize>(value: &T, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error>>;
+    fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>>;
+}
+
+/// A value stored in a `blob` column, encoded with the codec `C`.
+///
+/// `Blob<T, C>` can be used as an argument or return type of a UDF for any `T` that implements
+/// `serde::Serialize` and `serde::de::DeserializeOwned`. Arguments are decoded and return values
+/// are encoded using `C`. If an argument can't be decoded to `T`, calling the UDF fails with a
+/// conversion error.
+/// ```
+/// use scylla_udf::{export_udf, Blob, MessagePack};
+/// use std::collections::HashMap;
+///
+/// #[export_udf]
+/// fn reading(payload: Blob<HashMap<String, f64>, MessagePack>, sensor: String) -> Option<f64> {
+///     payload.get(&sensor).copied()
+/// }
+/// ```
+pub struct Blob<T, C>(pub T, PhantomData<fn() -> C>);
+
+impl<T, C> Blob<T, C> {
+    pub const fn new(value: T) -> Blob<T, C> {
+        Blob(value, PhantomData)
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+// The traits are implemented manually, so that they don't require the codec to implement them
+impl<T: Clone, C> Clone for Blob<T, C> {
+    fn clone(&self) -> Self {
+        Blob::new(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug, C> fmt::Debug for Blob<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Blob").field(&self.0).finish()
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for Blob<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq, C> Eq for Blob<T, C> {}
+
+impl<T: Default, C> Default for Blob<T, C> {
+    fn default() -> Self {
+        Blob::new(T::default())
+    }
+}
+
+impl<T, C> From<T> for Blob<T, C> {
+    fn from(value: T) -> Blob<T, C> {
+        Blob::new(value)
+    }
+}
+
+impl<T, C> Deref for Blob<T, C> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T, C> DerefMut for Blob<T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// The MessagePack codec. Structs are encoded as maps with the field names as keys.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug)]
+pub enum MessagePack {}
+
+#[cfg(feature = "msgpack")]
+impl BlobCodec for MessagePack {
+    fn encode<T: serde::Serialize>(value: &T, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+        Ok(rmp_serde::encode::write_named(buf, value)?)
+    }
+    fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+/// The CBOR codec.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug)]
+pub enum Cbor {}
+
+#[cfg(feature = "cbor")]
+impl BlobCodec for Cbor {
+    fn encode<T: serde::Serialize>(value: &T, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+        Ok(ciborium::ser::into_writer(value, buf)?)
+    }
+    fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(ciborium::de::from_reader(bytes)?)
+    }
+}
+
+/// The bincode codec, with the default options of `bincode::serialize`.
+#[cfg(feature = "bincode")]
+#[derive(Clone, Copy, Debug)]
+pub enum Bincode {}
+
+#[cfg(feature = "bincode")]
+impl BlobCodec for Bincode {
+    fn encode<T: serde::Serialize>(value: &T, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+        Ok(bincode::serialize_into(buf, value)?)
+    }
+    fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
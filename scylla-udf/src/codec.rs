@@ -0,0 +1,617 @@
+//! Decoding and encoding of CQL values in the format used by the CQL protocol, in which Scylla passes
+//! serialized arguments to UDFs and expects their return values.
+//!
+//! Values of the Rust types mapped to CQL types are decoded and encoded directly by their
+//! [`DeserializeCql`] and [`SerializeCql`]
+//! implementations. The functions in this module handle values of any type as a [`CqlValue`].
+//!
+//! ```
+//! use scylla_udf::codec::{decode_value, encode_value};
+//! use scylla_udf::{ColumnType, CqlValue};
+//!
+//! let typ = ColumnType::List(Box::new(ColumnType::Int));
+//! let value = CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)]);
+//! let mut buf = Vec::new();
+//! encode_value(&value, &mut buf).unwrap();
+//! // the serialized value is preceded by its size
+//! assert_eq!(decode_value(&typ, &buf[4..]), Ok(value));
+//! ```
+
+use crate::cql_value::{ColumnType, CqlDuration, CqlValue, Time, Timestamp};
+use crate::deserialize_cql::{read_field, read_value, DeserializeCql};
+use crate::serialize_cql::{serialize_sorted, serialize_with_size, SerializeCql};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+
+/// The reason why a CQL value couldn't be converted to a Rust type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FromCqlValError {
+    /// The value is of a CQL type that can't be converted to the Rust type.
+    BadCqlType,
+    /// The value is null, and the Rust type can't represent it.
+    ValIsNull,
+    /// The value is malformed, or it can't be represented by the Rust type.
+    BadVal,
+    /// The value can't be parsed as the Rust type, e.g. a `text` value that isn't valid JSON,
+    /// for the given reason.
+    Parse(String),
+}
+
+impl fmt::Display for FromCqlValError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FromCqlValError::BadCqlType => "Bad CQL type",
+            FromCqlValError::ValIsNull => "Value is null",
+            FromCqlValError::BadVal => "Bad Value",
+            FromCqlValError::Parse(reason) => return write!(f, "Parse error: {}", reason),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FromCqlValError {}
+
+/// A value can't be serialized, because it's bigger than the maximum size of a CQL value (2GiB),
+/// or it can't be represented in the CQL format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ValueTooBig;
+
+impl fmt::Display for ValueTooBig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Value too big to be serialized - max 2GiB allowed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValueTooBig {}
+
+/// The error returned by [`SerializeCql`] when a value can't be serialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerializeCqlError {
+    /// The value is too big, or it can't be represented in the CQL format, see [`ValueTooBig`].
+    ValueTooBig,
+    /// The value can't be encoded, e.g. a `Json` value that can't be represented in JSON,
+    /// for the given reason.
+    Encode(String),
+    /// The value is returned from a UDF, and its serialized size reached `size` bytes, exceeding the
+    /// maximum result size of the UDF.
+    ResultTooBig { size: usize, max_size: usize },
+}
+
+impl From<ValueTooBig> for SerializeCqlError {
+    fn from(_: ValueTooBig) -> SerializeCqlError {
+        SerializeCqlError::ValueTooBig
+    }
+}
+
+impl fmt::Display for SerializeCqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeCqlError::ValueTooBig => ValueTooBig.fmt(f),
+            SerializeCqlError::Encode(reason) => write!(f, "Encode error: {}", reason),
+            SerializeCqlError::ResultTooBig { size, max_size } => write!(
+                f,
+                "value of at least {} bytes exceeds the maximum result size of {} bytes",
+                size, max_size
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SerializeCqlError {}
+
+fn decode<T: DeserializeCql>(typ: &ColumnType, bytes: &[u8]) -> Result<T, FromCqlValError> {
+    T::deserialize_cql_bytes(typ, Some(bytes))
+}
+
+// Elements of collections can't be null
+fn decode_element(typ: &ColumnType, buf: &mut &[u8]) -> Result<CqlValue, FromCqlValError> {
+    let bytes = read_value(buf)?.ok_or(FromCqlValError::BadVal)?;
+    decode_value(typ, bytes)
+}
+
+fn decode_count(buf: &mut &[u8]) -> Result<usize, FromCqlValError> {
+    crate::cql_ord::read_count(buf).ok_or(FromCqlValError::BadVal)
+}
+
+/// Decodes a serialized value of the given type, not preceded by its size.
+///
+/// Values of all types except `ascii`, `text` and `blob` can be empty, and are decoded to `CqlValue::Empty`.
+/// Fails with `FromCqlValError::BadVal` if the value is malformed, and with `FromCqlValError::BadCqlType`
+/// for custom types, and for `inet` values without the `std` feature.
+pub fn decode_value(typ: &ColumnType, bytes: &[u8]) -> Result<CqlValue, FromCqlValError> {
+    if bytes.is_empty() && !matches!(typ, ColumnType::Ascii | ColumnType::Text | ColumnType::Blob) {
+        return Ok(CqlValue::Empty);
+    }
+    Ok(match typ {
+        ColumnType::Custom(_) => return Err(FromCqlValError::BadCqlType),
+        ColumnType::Ascii => {
+            let text: String = decode(typ, bytes)?;
+            if !text.is_ascii() {
+                return Err(FromCqlValError::BadVal);
+            }
+            CqlValue::Ascii(text)
+        }
+        ColumnType::Text => CqlValue::Text(decode(typ, bytes)?),
+        ColumnType::Blob => CqlValue::Blob(decode(typ, bytes)?),
+        ColumnType::Boolean => CqlValue::Boolean(decode(typ, bytes)?),
+        ColumnType::Counter => CqlValue::Counter(decode(typ, bytes)?),
+        ColumnType::Date => CqlValue::Date(u32::from_be_bytes(
+            bytes.try_into().map_err(|_| FromCqlValError::BadVal)?,
+        )),
+        ColumnType::Decimal => CqlValue::Decimal(decode(typ, bytes)?),
+        ColumnType::Double => CqlValue::Double(decode(typ, bytes)?),
+        ColumnType::Duration => CqlValue::Duration(decode(typ, bytes)?),
+        ColumnType::Float => CqlValue::Float(decode(typ, bytes)?),
+        ColumnType::Int => CqlValue::Int(decode(typ, bytes)?),
+        ColumnType::BigInt => CqlValue::BigInt(decode(typ, bytes)?),
+        ColumnType::SmallInt => CqlValue::SmallInt(decode(typ, bytes)?),
+        ColumnType::TinyInt => CqlValue::TinyInt(decode(typ, bytes)?),
+        ColumnType::Varint => CqlValue::Varint(decode(typ, bytes)?),
+        ColumnType::Timestamp => CqlValue::Timestamp(decode::<Timestamp>(typ, bytes)?.0),
+        ColumnType::Time => CqlValue::Time(decode::<Time>(typ, bytes)?.0),
+        ColumnType::Uuid => CqlValue::Uuid(decode(typ, bytes)?),
+        ColumnType::Timeuuid => CqlValue::Timeuuid(decode(typ, bytes)?),
+        #[cfg(feature = "std")]
+        ColumnType::Inet => CqlValue::Inet(decode(typ, bytes)?),
+        #[cfg(not(feature = "std"))]
+        ColumnType::Inet => return Err(FromCqlValError::BadCqlType),
+        ColumnType::List(element_type) | ColumnType::Set(element_type) => {
+            let mut buf = bytes;
+            let elements = (0..decode_count(&mut buf)?)
+                .map(|_| decode_element(element_type, &mut buf))
+                .collect::<Result<_, _>>()?;
+            match typ {
+                ColumnType::List(_) => CqlValue::List(elements),
+                _ => CqlValue::Set(elements),
+            }
+        }
+        ColumnType::Map(key_type, value_type) => {
+            let mut buf = bytes;
+            let entries = (0..decode_count(&mut buf)?)
+                .map(|_| {
+                    let key = decode_element(key_type, &mut buf)?;
+                    let value = decode_element(value_type, &mut buf)?;
+                    Ok((key, value))
+                })
+                .collect::<Result<_, _>>()?;
+            CqlValue::Map(entries)
+        }
+        // Fields added to the UDT after the value was serialized are missing at the end of it
+        ColumnType::UserDefinedType {
+            type_name,
+            keyspace,
+            field_types,
+        } => {
+            let mut buf = bytes;
+            let mut fields = Vec::with_capacity(field_types.len());
+            for (name, typ) in field_types {
+                if buf.is_empty() {
+                    break;
+                }
+                let value = read_field(&mut buf)?
+                    .map(|bytes| decode_value(typ, bytes))
+                    .transpose()?;
+                fields.push((name.clone(), value));
+            }
+            CqlValue::UserDefinedType {
+                keyspace: keyspace.clone(),
+                type_name: type_name.clone(),
+                fields,
+            }
+        }
+        ColumnType::Tuple(types) => {
+            let mut buf = bytes;
+            let fields = types
+                .iter()
+                .map(|typ| {
+                    read_value(&mut buf)?
+                        .map(|bytes| decode_value(typ, bytes))
+                        .transpose()
+                })
+                .collect::<Result<_, _>>()?;
+            CqlValue::Tuple(fields)
+        }
+    })
+}
+
+/// Appends a serialized value to the buffer, preceded by its size.
+///
+/// The elements of sets and the keys of maps are written in the order of the `CqlValue`, so they
+/// must already be sorted in the order of their CQL type and unique. [`encode_value_with_type`]
+/// sorts them instead.
+pub fn encode_value(value: &CqlValue, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+    match value {
+        CqlValue::Ascii(s) | CqlValue::Text(s) => s.serialize_cql(buf),
+        CqlValue::Blob(b) => b.serialize_cql(buf),
+        CqlValue::Boolean(b) => b.serialize_cql(buf),
+        CqlValue::Counter(c) => c.serialize_cql(buf),
+        CqlValue::Decimal(d) => d.serialize_cql(buf),
+        CqlValue::Date(d) => serialize_with_size(buf, |buf| {
+            buf.extend_from_slice(&d.to_be_bytes());
+            Ok(())
+        }),
+        CqlValue::Double(d) => d.serialize_cql(buf),
+        CqlValue::Duration(d) => d.serialize_cql(buf),
+        CqlValue::Empty => serialize_with_size(buf, |_| Ok(())),
+        CqlValue::Float(f) => f.serialize_cql(buf),
+        CqlValue::Int(i) => i.serialize_cql(buf),
+        CqlValue::BigInt(i) => i.serialize_cql(buf),
+        CqlValue::Timestamp(t) => Timestamp(*t).serialize_cql(buf),
+        #[cfg(feature = "std")]
+        CqlValue::Inet(addr) => addr.serialize_cql(buf),
+        CqlValue::List(elements) | CqlValue::Set(elements) => elements.serialize_cql(buf),
+        CqlValue::Map(entries) => serialize_with_size(buf, |buf| {
+            let count: i32 = entries.len().try_into().map_err(|_| ValueTooBig)?;
+            buf.extend_from_slice(&count.to_be_bytes());
+            for (key, value) in entries {
+                encode_value(key, buf)?;
+                encode_value(value, buf)?;
+            }
+            Ok(())
+        }),
+        // A UDT value is serialized like a tuple of its field values, in the order of the UDT definition
+        CqlValue::UserDefinedType { fields, .. } => serialize_with_size(buf, |buf| {
+            for (_, value) in fields {
+                value.serialize_cql(buf)?;
+            }
+            Ok(())
+        }),
+        CqlValue::SmallInt(i) => i.serialize_cql(buf),
+        CqlValue::TinyInt(i) => i.serialize_cql(buf),
+        CqlValue::Time(t) => Time(*t).serialize_cql(buf),
+        CqlValue::Timeuuid(u) | CqlValue::Uuid(u) => u.serialize_cql(buf),
+        CqlValue::Tuple(fields) => serialize_with_size(buf, |buf| {
+            for value in fields {
+                value.serialize_cql(buf)?;
+            }
+            Ok(())
+        }),
+        CqlValue::Varint(v) => v.serialize_cql(buf),
+    }
+}
+
+/// Appends a serialized value of the given CQL type to the buffer, preceded by its size, like
+/// [`encode_value`], but with the elements of sets and the keys of maps sorted in the order of
+/// their CQL type, as Scylla expects them. Duplicate elements and keys are dropped, keeping the
+/// first one.
+///
+/// The value must match the type, which isn't checked.
+pub fn encode_value_with_type(
+    value: &CqlValue,
+    typ: &ColumnType,
+    buf: &mut Vec<u8>,
+) -> Result<(), SerializeCqlError> {
+    match (value, typ) {
+        (CqlValue::List(elements), ColumnType::List(element_type)) => {
+            serialize_with_size(buf, |buf| {
+                let count: i32 = elements.len().try_into().map_err(|_| ValueTooBig)?;
+                buf.extend_from_slice(&count.to_be_bytes());
+                for element in elements {
+                    encode_value_with_type(element, element_type, buf)?;
+                }
+                Ok(())
+            })
+        }
+        (CqlValue::Set(elements), ColumnType::Set(element_type)) => {
+            serialize_sorted(element_type, buf, |buf, ranges| {
+                for element in elements {
+                    let start = buf.len();
+                    encode_value_with_type(element, element_type, buf)?;
+                    ranges.push((start, buf.len(), buf.len()));
+                }
+                Ok(())
+            })
+        }
+        (CqlValue::Map(entries), ColumnType::Map(key_type, value_type)) => {
+            serialize_sorted(key_type, buf, |buf, ranges| {
+                for (key, value) in entries {
+                    let start = buf.len();
+                    encode_value_with_type(key, key_type, buf)?;
+                    let key_end = buf.len();
+                    encode_value_with_type(value, value_type, buf)?;
+                    ranges.push((start, key_end, buf.len()));
+                }
+                Ok(())
+            })
+        }
+        (
+            CqlValue::UserDefinedType { fields, .. },
+            ColumnType::UserDefinedType { field_types, .. },
+        ) => serialize_with_size(buf, |buf| {
+            for ((_, value), (_, typ)) in fields.iter().zip(field_types) {
+                encode_field_with_type(value.as_ref(), typ, buf)?;
+            }
+            Ok(())
+        }),
+        (CqlValue::Tuple(fields), ColumnType::Tuple(types)) => serialize_with_size(buf, |buf| {
+            for (value, typ) in fields.iter().zip(types) {
+                encode_field_with_type(value.as_ref(), typ, buf)?;
+            }
+            Ok(())
+        }),
+        _ => encode_value(value, buf),
+    }
+}
+
+fn encode_field_with_type(
+    value: Option<&CqlValue>,
+    typ: &ColumnType,
+    buf: &mut Vec<u8>,
+) -> Result<(), SerializeCqlError> {
+    match value {
+        Some(value) => encode_value_with_type(value, typ, buf),
+        None => None::<CqlValue>.serialize_cql(buf),
+    }
+}
+
+// The number of bytes appended by encode_value, including the size
+pub(crate) fn encoded_size(value: &CqlValue) -> usize {
+    match value {
+        CqlValue::Ascii(s) | CqlValue::Text(s) => s.size_hint(),
+        CqlValue::Blob(b) => b.size_hint(),
+        CqlValue::Boolean(b) => b.size_hint(),
+        CqlValue::Counter(c) => c.size_hint(),
+        CqlValue::Decimal(d) => d.size_hint(),
+        CqlValue::Date(_) => 4 + 4,
+        CqlValue::Double(d) => d.size_hint(),
+        CqlValue::Duration(d) => d.size_hint(),
+        CqlValue::Empty => 4,
+        CqlValue::Float(f) => f.size_hint(),
+        CqlValue::Int(i) => i.size_hint(),
+        CqlValue::BigInt(i) => i.size_hint(),
+        CqlValue::Timestamp(t) => Timestamp(*t).size_hint(),
+        #[cfg(feature = "std")]
+        CqlValue::Inet(addr) => addr.size_hint(),
+        CqlValue::List(elements) | CqlValue::Set(elements) => {
+            4 + 4 + elements.iter().map(encoded_size).sum::<usize>()
+        }
+        CqlValue::Map(entries) => {
+            4 + 4
+                + entries
+                    .iter()
+                    .map(|(key, value)| encoded_size(key) + encoded_size(value))
+                    .sum::<usize>()
+        }
+        CqlValue::UserDefinedType { fields, .. } => {
+            4 + fields
+                .iter()
+                .map(|(_, value)| value.size_hint())
+                .sum::<usize>()
+        }
+        CqlValue::SmallInt(i) => i.size_hint(),
+        CqlValue::TinyInt(i) => i.size_hint(),
+        CqlValue::Time(t) => Time(*t).size_hint(),
+        CqlValue::Timeuuid(u) | CqlValue::Uuid(u) => u.size_hint(),
+        CqlValue::Tuple(fields) => 4 + fields.iter().map(SerializeCql::size_hint).sum::<usize>(),
+        CqlValue::Varint(v) => v.size_hint(),
+    }
+}
+
+// A vint is a big-endian integer of 1 to 9 bytes, with the number of bytes following the first one
+// stored as the number of its leading 1 bits. Signed values are zigzag-encoded first.
+pub(crate) fn vint_encode(value: i64, buf: &mut Vec<u8>) {
+    let size = vint_size(value);
+    let mut value = zigzag(value);
+    let size = match size {
+        1 => return buf.push(value as u8),
+        9 => {
+            buf.push(0xff);
+            8
+        }
+        size => {
+            let extra_bytes = size - 1;
+            value |= (!(0xff_u8 >> extra_bytes) as u64) << (8 * extra_bytes);
+            size
+        }
+    };
+    buf.extend_from_slice(&value.to_be_bytes()[8 - size..]);
+}
+
+pub(crate) fn zigzag(value: i64) -> u64 {
+    ((value >> 63) ^ (value << 1)) as u64
+}
+
+// Each byte stores 7 bits of the value, except the ninth one, which stores 8
+pub(crate) fn vint_size(value: i64) -> usize {
+    (((639 - 9 * zigzag(value).leading_zeros()) >> 6) as usize).max(1)
+}
+
+pub(crate) fn vint_decode(buf: &mut &[u8]) -> Option<i64> {
+    let (first_byte, rest) = buf.split_first()?;
+    let extra_bytes = first_byte.leading_ones() as usize;
+    let mut value = match extra_bytes {
+        8 => 0,
+        n => (first_byte & (0xff_u8 >> n)) as u64,
+    };
+    for byte in rest.get(..extra_bytes)? {
+        value = (value << 8) | *byte as u64;
+    }
+    *buf = &rest[extra_bytes..];
+    Some(((value >> 1) as i64) ^ -((value & 1) as i64))
+}
+
+pub(crate) fn decode_duration(bytes: &[u8]) -> Option<CqlDuration> {
+    let mut buf = bytes;
+    let months = vint_decode(&mut buf)?.try_into().ok()?;
+    let days = vint_decode(&mut buf)?.try_into().ok()?;
+    let nanoseconds = vint_decode(&mut buf)?;
+    buf.is_empty().then_some(CqlDuration {
+        months,
+        days,
+        nanoseconds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn encoded(value: &CqlValue) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_value(value, &mut buf).unwrap();
+        assert_eq!(
+            i32::from_be_bytes(buf[..4].try_into().unwrap()) as usize,
+            buf.len() - 4
+        );
+        buf.split_off(4)
+    }
+
+    fn round_trip(typ: ColumnType, value: CqlValue) {
+        assert_eq!(decode_value(&typ, &encoded(&value)), Ok(value));
+    }
+
+    #[test]
+    fn vints() {
+        let cases: [(i64, &[u8]); 7] = [
+            (0, &[0]),
+            (-1, &[1]),
+            (1, &[2]),
+            (63, &[126]),
+            (64, &[0x80, 0x80]),
+            (-8193, &[0xc0, 0x40, 0x01]),
+            (i64::MIN, &[0xff; 9]),
+        ];
+        for (value, bytes) in cases {
+            let mut buf = Vec::new();
+            vint_encode(value, &mut buf);
+            assert_eq!(buf, bytes, "{}", value);
+            assert_eq!(vint_size(value), bytes.len());
+        }
+        for shift in 0..63 {
+            for value in [
+                1_i64 << shift,
+                -(1_i64 << shift),
+                (1_i64 << shift) - 1,
+                i64::MIN,
+            ] {
+                let mut buf = Vec::new();
+                vint_encode(value, &mut buf);
+                assert_eq!(vint_size(value), buf.len());
+                let mut slice = buf.as_slice();
+                assert_eq!(vint_decode(&mut slice), Some(value));
+                assert!(slice.is_empty());
+            }
+        }
+        assert_eq!(vint_decode(&mut &[0xc0, 0x40][..]), None);
+    }
+
+    #[test]
+    fn native_types() {
+        round_trip(ColumnType::Ascii, CqlValue::Ascii("abc".to_string()));
+        round_trip(ColumnType::Text, CqlValue::Text(String::new()));
+        round_trip(ColumnType::Blob, CqlValue::Blob(vec![1, 2]));
+        round_trip(ColumnType::Boolean, CqlValue::Boolean(true));
+        round_trip(ColumnType::Date, CqlValue::Date(1 << 31));
+        round_trip(
+            ColumnType::Decimal,
+            CqlValue::Decimal("-1.05".parse().unwrap()),
+        );
+        round_trip(
+            ColumnType::Duration,
+            CqlValue::Duration(CqlDuration {
+                months: -1,
+                days: 40,
+                nanoseconds: i64::MIN,
+            }),
+        );
+        round_trip(ColumnType::Double, CqlValue::Double(0.25));
+        round_trip(ColumnType::Varint, CqlValue::Varint((-1_i64 << 40).into()));
+        round_trip(
+            ColumnType::Timestamp,
+            CqlValue::Timestamp(chrono::Duration::milliseconds(-5)),
+        );
+        round_trip(
+            ColumnType::Timeuuid,
+            CqlValue::Timeuuid(uuid::Uuid::from_u128(7)),
+        );
+        #[cfg(feature = "std")]
+        round_trip(ColumnType::Inet, CqlValue::Inet([10, 0, 0, 1].into()));
+        round_trip(ColumnType::Int, CqlValue::Empty);
+    }
+
+    #[test]
+    fn nested_types() {
+        let udt = ColumnType::UserDefinedType {
+            type_name: "udt".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: vec![
+                ("a".to_string(), ColumnType::Int),
+                (
+                    "b".to_string(),
+                    ColumnType::Map(
+                        Box::new(ColumnType::Text),
+                        Box::new(ColumnType::Set(Box::new(ColumnType::TinyInt))),
+                    ),
+                ),
+            ],
+        };
+        let value = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "udt".to_string(),
+            fields: vec![
+                ("a".to_string(), None),
+                (
+                    "b".to_string(),
+                    Some(CqlValue::Map(vec![(
+                        CqlValue::Text("x".to_string()),
+                        CqlValue::Set(vec![CqlValue::TinyInt(1), CqlValue::TinyInt(2)]),
+                    )])),
+                ),
+            ],
+        };
+        round_trip(udt.clone(), value);
+        // a value serialized before the second field was added to the UDT
+        let old = [0, 0, 0, 4, 0, 0, 0, 1];
+        assert_eq!(
+            decode_value(&udt, &old),
+            Ok(CqlValue::UserDefinedType {
+                keyspace: "ks".to_string(),
+                type_name: "udt".to_string(),
+                fields: vec![("a".to_string(), Some(CqlValue::Int(1)))],
+            })
+        );
+        let tuple = ColumnType::Tuple(vec![
+            ColumnType::Int,
+            ColumnType::List(Box::new(ColumnType::Uuid)),
+        ]);
+        round_trip(tuple, CqlValue::Tuple(vec![Some(CqlValue::Int(3)), None]));
+    }
+
+    #[test]
+    fn malformed_values() {
+        let list = ColumnType::List(Box::new(ColumnType::Int));
+        // a null element
+        let null = [0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(decode_value(&list, &null), Err(FromCqlValError::BadVal));
+        // an element of 3 bytes
+        let short = [0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 1];
+        assert_eq!(decode_value(&list, &short), Err(FromCqlValError::BadVal));
+        assert_eq!(
+            decode_value(&ColumnType::Ascii, "ż".as_bytes()),
+            Err(FromCqlValError::BadVal)
+        );
+        // more than 23:59:59.999999999
+        let time = 86_400_000_000_000_i64.to_be_bytes();
+        assert_eq!(
+            decode_value(&ColumnType::Time, &time),
+            Err(FromCqlValError::BadVal)
+        );
+        // a duration with trailing bytes
+        assert_eq!(
+            decode_value(&ColumnType::Duration, &[0, 0, 0, 0]),
+            Err(FromCqlValError::BadVal)
+        );
+        assert_eq!(
+            decode_value(&ColumnType::Custom("a.B".to_string()), &[1]),
+            Err(FromCqlValError::BadCqlType)
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use crate::codec::FromCqlValError;
+use crate::codec::ValueTooBig;
+use crate::cql_literal::native_type_name;
+use crate::cql_value::ColumnType;
+use crate::deserialize_cql::read_value;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+
+/// The reason why a value couldn't be converted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConversionErrorKind {
+    /// A value received from Scylla can't be deserialized to the Rust type.
+    Deserialize(FromCqlValError),
+    /// A value can't be serialized to be returned to Scylla.
+    Serialize(ValueTooBig),
+    /// A value can't be encoded to be returned to Scylla, e.g. as JSON, for the given reason.
+    Encode(String),
+    /// A value passed as a native Wasm value doesn't fit in the Rust type.
+    OutOfRange,
+    /// A returned value doesn't match the CQL type declared in the `types` attribute of `#[export_udf]`.
+    TypeMismatch,
+    /// A returned value is bigger than the maximum result size, which can be set with the
+    /// `max_result_size` attribute of `#[export_udf]`. Serializing the value stops as soon as it
+    /// exceeds the limit, so `size` may be smaller than the size of the whole value.
+    ResultTooBig { size: usize, max_size: usize },
+    /// The size written by `SerializeCql` before a returned value doesn't match the number of bytes
+    /// that follow it.
+    InvalidLength,
+}
+
+impl fmt::Display for ConversionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionErrorKind::Deserialize(e) => write!(f, "{}", e),
+            ConversionErrorKind::Serialize(e) => write!(f, "{}", e),
+            ConversionErrorKind::Encode(reason) => write!(f, "Encode error: {}", reason),
+            ConversionErrorKind::OutOfRange => f.write_str("value out of range"),
+            ConversionErrorKind::TypeMismatch => {
+                f.write_str("value doesn't match the declared type")
+            }
+            ConversionErrorKind::ResultTooBig { size, max_size } => write!(
+                f,
+                "value of at least {} bytes exceeds the maximum result size of {} bytes",
+                size, max_size
+            ),
+            ConversionErrorKind::InvalidLength => {
+                f.write_str("serialized size doesn't match the length of the value")
+            }
+        }
+    }
+}
+
+/// The place of a converted value in the signature of a UDF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValuePosition {
+    /// The argument with the given index, starting from 0.
+    Argument(usize),
+    /// The return value.
+    ReturnValue,
+}
+
+/// An error of converting an argument or a return value of a UDF between its Wasm representation
+/// and a Rust type, returned by [`WasmConvertible`](crate::mapping::WasmConvertible) and the other
+/// conversion traits.
+///
+/// The functions generated by `#[export_udf]` add the name of the UDF and the position of the value
+/// to the error, and fail the call with a panic whose message describes it, e.g.
+/// ``Failed to convert argument 0 of UDF `sum` from CQL type list<int> to alloc::vec::Vec<i32> at byte 16: Bad Value``.
+// The details are boxed, to keep the results of conversions small
+#[derive(Clone, Debug)]
+pub struct ConversionError(Box<ErrorDetails>);
+
+#[derive(Clone, Debug)]
+struct ErrorDetails {
+    kind: ConversionErrorKind,
+    rust_type: &'static str,
+    column_type: Option<ColumnType>,
+    offset: Option<usize>,
+    udf: Option<(&'static str, ValuePosition)>,
+}
+
+impl ConversionError {
+    /// Creates an error of converting a value of the Rust type `T`.
+    pub fn new<T: ?Sized>(kind: ConversionErrorKind) -> Self {
+        ConversionError(Box::new(ErrorDetails {
+            kind,
+            rust_type: core::any::type_name::<T>(),
+            column_type: None,
+            offset: None,
+            udf: None,
+        }))
+    }
+
+    // Creates an error of deserializing the given bytes as a value of the Rust type `T`, pointing at
+    // the first malformed part of the value if there is one
+    pub(crate) fn deserialize<T: ?Sized>(
+        error: FromCqlValError,
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Self {
+        let error = ConversionError::new::<T>(ConversionErrorKind::Deserialize(error))
+            .with_column_type(typ.clone());
+        match bytes {
+            Some(bytes) => error.with_offset(malformed_offset(typ, bytes)),
+            None => error,
+        }
+    }
+
+    /// Sets the CQL type of the converted value.
+    pub fn with_column_type(mut self, column_type: ColumnType) -> Self {
+        self.0.column_type = Some(column_type);
+        self
+    }
+
+    /// Sets the offset in the serialized value at which the conversion failed.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.0.offset = Some(offset);
+        self
+    }
+
+    /// Sets the name of the UDF and the place of the value in its signature.
+    pub fn in_udf(mut self, udf: &'static str, position: ValuePosition) -> Self {
+        self.0.udf = Some((udf, position));
+        self
+    }
+
+    pub fn kind(&self) -> &ConversionErrorKind {
+        &self.0.kind
+    }
+
+    /// The name of the Rust type, as returned by `core::any::type_name`.
+    pub fn rust_type(&self) -> &'static str {
+        self.0.rust_type
+    }
+
+    pub fn column_type(&self) -> Option<&ColumnType> {
+        self.0.column_type.as_ref()
+    }
+
+    /// The offset in the serialized value at which the conversion failed, if the value was
+    /// serialized and it's known.
+    pub fn offset(&self) -> Option<usize> {
+        self.0.offset
+    }
+
+    pub fn udf_name(&self) -> Option<&'static str> {
+        self.0.udf.map(|(name, _)| name)
+    }
+
+    pub fn position(&self) -> Option<ValuePosition> {
+        self.0.udf.map(|(_, position)| position)
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Failed to convert ")?;
+        match self.0.udf {
+            Some((name, ValuePosition::Argument(index))) => {
+                write!(f, "argument {} of UDF `{}`", index, name)?
+            }
+            Some((name, ValuePosition::ReturnValue)) => {
+                write!(f, "the return value of UDF `{}`", name)?
+            }
+            None => f.write_str("a value")?,
+        }
+        let to_rust = matches!(
+            self.0.kind,
+            ConversionErrorKind::Deserialize(_) | ConversionErrorKind::OutOfRange
+        );
+        match (&self.0.column_type, to_rust) {
+            (Some(typ), true) => write!(
+                f,
+                " from CQL type {} to {}",
+                CqlTypeName(typ),
+                self.0.rust_type
+            )?,
+            (Some(typ), false) => write!(
+                f,
+                " from {} to CQL type {}",
+                self.0.rust_type,
+                CqlTypeName(typ)
+            )?,
+            (None, true) => write!(f, " to {}", self.0.rust_type)?,
+            (None, false) => write!(f, " from {}", self.0.rust_type)?,
+        }
+        if let Some(offset) = self.0.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        write!(f, ": {}", self.0.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConversionError {}
+
+/// Fails the call of a UDF with the given error.
+#[doc(hidden)]
+#[cold]
+pub fn conversion_failed(error: ConversionError) -> ! {
+    panic!("{}", error)
+}
+
+// Formats a column type the way it's written in CQL
+struct CqlTypeName<'a>(&'a ColumnType);
+
+impl fmt::Display for CqlTypeName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ColumnType::List(typ) => write!(f, "list<{}>", CqlTypeName(typ)),
+            ColumnType::Set(typ) => write!(f, "set<{}>", CqlTypeName(typ)),
+            ColumnType::Map(key, value) => {
+                write!(f, "map<{}, {}>", CqlTypeName(key), CqlTypeName(value))
+            }
+            ColumnType::Tuple(types) => {
+                f.write_str("tuple<")?;
+                for (i, typ) in types.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", CqlTypeName(typ))?;
+                }
+                f.write_str(">")
+            }
+            ColumnType::UserDefinedType { type_name, .. } => f.write_str(type_name),
+            ColumnType::Custom(name) => write!(f, "'{}'", name),
+            typ => f.write_str(native_type_name(typ).unwrap_or_default()),
+        }
+    }
+}
+
+// Returns the offset of the first malformed part of a serialized value of the given type. It's
+// only looked for after the deserialization failed, and if the value is well-formed, the error
+// is caused by the value as a whole (e.g. a wrong inet address family), so 0 is returned.
+fn malformed_offset(typ: &ColumnType, bytes: &[u8]) -> usize {
+    find_malformed(typ, bytes, bytes.as_ptr() as usize).unwrap_or(0)
+}
+
+// Looks for a malformed part of the serialized value `bytes`, and returns its offset from `start`
+fn find_malformed(typ: &ColumnType, bytes: &[u8], start: usize) -> Option<usize> {
+    let offset = |bytes: &[u8]| bytes.as_ptr() as usize - start;
+    // empty values are well-formed for all types
+    if bytes.is_empty() {
+        return None;
+    }
+    let fixed_size = match typ {
+        ColumnType::List(elem) | ColumnType::Set(elem) => {
+            return find_malformed_collection(&[elem], bytes, start)
+        }
+        ColumnType::Map(key, value) => {
+            return find_malformed_collection(&[key, value], bytes, start)
+        }
+        ColumnType::Tuple(types) => return find_malformed_fields(types.iter(), bytes, start),
+        ColumnType::UserDefinedType { field_types, .. } => {
+            return find_malformed_fields(field_types.iter().map(|(_, typ)| typ), bytes, start)
+        }
+        ColumnType::Boolean | ColumnType::TinyInt => 1,
+        ColumnType::SmallInt => 2,
+        ColumnType::Int | ColumnType::Date | ColumnType::Float => 4,
+        ColumnType::BigInt
+        | ColumnType::Counter
+        | ColumnType::Double
+        | ColumnType::Time
+        | ColumnType::Timestamp => 8,
+        ColumnType::Uuid | ColumnType::Timeuuid => 16,
+        ColumnType::Inet => return (bytes.len() != 4 && bytes.len() != 16).then(|| offset(bytes)),
+        // the scale followed by the unscaled value, which is 0 if it has no bytes
+        ColumnType::Decimal => return (bytes.len() < 4).then(|| offset(bytes)),
+        ColumnType::Ascii => {
+            return bytes
+                .iter()
+                .position(|b| !b.is_ascii())
+                .map(|i| offset(bytes) + i)
+        }
+        ColumnType::Text => {
+            return core::str::from_utf8(bytes)
+                .err()
+                .map(|e| offset(bytes) + e.valid_up_to())
+        }
+        ColumnType::Blob | ColumnType::Varint | ColumnType::Duration | ColumnType::Custom(_) => {
+            return None
+        }
+    };
+    (bytes.len() != fixed_size).then(|| offset(bytes))
+}
+
+// A collection is serialized as the number of elements followed by the elements,
+// each of them consisting of the given number of values
+fn find_malformed_collection(types: &[&ColumnType], bytes: &[u8], start: usize) -> Option<usize> {
+    let offset = |bytes: &[u8]| bytes.as_ptr() as usize - start;
+    let (count, mut buf) = match bytes.get(..4) {
+        Some(count) => (i32::from_be_bytes(count.try_into().unwrap()), &bytes[4..]),
+        None => return Some(offset(bytes)),
+    };
+    if count < 0 {
+        return Some(offset(bytes));
+    }
+    for _ in 0..count {
+        for typ in types {
+            if let Some(found) = find_malformed_value(typ, &mut buf, start) {
+                return Some(found);
+            }
+        }
+    }
+    (!buf.is_empty()).then(|| offset(buf))
+}
+
+// Tuples and UDTs are serialized as a sequence of values, which may be shorter than the
+// number of fields, when the fields added to a UDT after the value was created are missing
+fn find_malformed_fields<'a>(
+    types: impl Iterator<Item = &'a ColumnType>,
+    bytes: &[u8],
+    start: usize,
+) -> Option<usize> {
+    let mut buf = bytes;
+    for typ in types {
+        if buf.is_empty() {
+            return None;
+        }
+        if let Some(found) = find_malformed_value(typ, &mut buf, start) {
+            return Some(found);
+        }
+    }
+    (!buf.is_empty()).then(|| buf.as_ptr() as usize - start)
+}
+
+// Reads a value preceded by its size from the buffer, and looks for a malformed part in it
+fn find_malformed_value(typ: &ColumnType, buf: &mut &[u8], start: usize) -> Option<usize> {
+    let at = buf.as_ptr() as usize - start;
+    match read_value(buf) {
+        Ok(Some(value)) => find_malformed(typ, value, start),
+        Ok(None) => None,
+        Err(_) => Some(at),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::{FromWasmPtr, WasmConvertible, WasmPtr};
+    use alloc::string::ToString;
+
+    fn argument(bytes: &[u8]) -> WasmPtr {
+        let mut ptr = WasmPtr::with_size(bytes.len() as u32).unwrap();
+        ptr.as_mut_slice().unwrap().copy_from_slice(bytes);
+        ptr
+    }
+
+    #[test]
+    fn error_context() {
+        // the second element of the list is 3 bytes long
+        let bytes = [0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 2];
+        let error = Vec::<i32>::from_wasmptr(argument(&bytes))
+            .unwrap_err()
+            .in_udf("sum", ValuePosition::Argument(1));
+        assert_eq!(
+            error.kind(),
+            &ConversionErrorKind::Deserialize(FromCqlValError::BadVal)
+        );
+        assert_eq!(error.offset(), Some(16));
+        assert_eq!(error.udf_name(), Some("sum"));
+        assert_eq!(error.position(), Some(ValuePosition::Argument(1)));
+        assert_eq!(
+            error.to_string(),
+            "Failed to convert argument 1 of UDF `sum` from CQL type list<int> to alloc::vec::Vec<i32> at byte 16: Bad Value"
+        );
+    }
+
+    #[test]
+    fn malformed_offsets() {
+        let map = ColumnType::Map(
+            Box::new(ColumnType::Text),
+            Box::new(ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Ascii])),
+        );
+        // {'a': (1, 'b\xff')}
+        let bytes = [
+            0, 0, 0, 1, 0, 0, 0, 1, b'a', 0, 0, 0, 14, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 2, b'b',
+            0xff,
+        ];
+        assert_eq!(malformed_offset(&map, &bytes), 26);
+        // a size exceeding the remaining bytes
+        assert_eq!(malformed_offset(&map, &bytes[..20]), 9);
+        // trailing bytes after the elements
+        let list = ColumnType::List(Box::new(ColumnType::BigInt));
+        assert_eq!(malformed_offset(&list, &[0, 0, 0, 0, 1]), 4);
+        assert_eq!(malformed_offset(&ColumnType::Text, b"ab\xffc"), 2);
+        // a decimal truncated in its scale, after a well-formed one with an empty unscaled value
+        let decimals = ColumnType::List(Box::new(ColumnType::Decimal));
+        let bytes = [0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0];
+        assert_eq!(malformed_offset(&decimals, &bytes), 16);
+        assert_eq!(
+            crate::codec::decode_value(&decimals, &bytes),
+            Err(FromCqlValError::BadVal)
+        );
+        // a well-formed value
+        assert_eq!(malformed_offset(&ColumnType::Inet, &[127, 0, 0, 1]), 0);
+        assert_eq!(malformed_offset(&ColumnType::Decimal, &[0, 0, 0, 1]), 0);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let error = i8::from_wasm(300).unwrap_err();
+        assert_eq!(error.kind(), &ConversionErrorKind::OutOfRange);
+        assert_eq!(
+            error.to_string(),
+            "Failed to convert a value from CQL type tinyint to i8: value out of range"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to convert the return value of UDF `foo` from i32")]
+    fn conversion_failure_panics() {
+        conversion_failed(
+            ConversionError::new::<i32>(ConversionErrorKind::TypeMismatch)
+                .in_udf("foo", ValuePosition::ReturnValue),
+        );
+    }
+}
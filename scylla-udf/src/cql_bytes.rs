@@ -0,0 +1,141 @@
+use crate::codec::FromCqlValError;
+use crate::codec::SerializeCqlError;
+use crate::cql_value::{ColumnType, CqlValue};
+use crate::deserialize_cql::DeserializeCql;
+use crate::serialize_cql::{serialize_bytes, SerializeCql};
+use crate::to_columntype::ToColumnType;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+/// The serialized bytes of a CQL value of the type mapped to `T`, kept without decoding them.
+///
+/// `CqlBytes<T>` can be used as an argument or return type of a UDF in place of `T`, when the value
+/// only needs to be forwarded, hashed or compared. The value is decoded only when [`CqlBytes::decode`]
+/// is called. Values of the same type are equal if their serialized forms are equal.
+/// ```
+/// use scylla_udf::{export_udf, CqlBytes};
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::{Hash, Hasher};
+///
+/// #[export_udf]
+/// fn fingerprint(arg: CqlBytes<(String, Vec<i32>)>) -> i64 {
+///     let mut hasher = DefaultHasher::new();
+///     arg.hash(&mut hasher);
+///     hasher.finish() as i64
+/// }
+/// ```
+/// Values nested in collections, tuples or UDTs are copied without decoding as well, unless
+/// they're nested in a type deserialized through a `CqlValue`, like [`CqlValue`](crate::CqlValue) itself.
+pub struct CqlBytes<T> {
+    bytes: Vec<u8>,
+    typ: PhantomData<fn() -> T>,
+}
+
+impl<T> CqlBytes<T> {
+    /// Wraps the serialized bytes of a value (without the size prefix).
+    /// The bytes aren't checked to be a valid serialization of a value of the type.
+    pub fn from_bytes(bytes: Vec<u8>) -> CqlBytes<T> {
+        CqlBytes {
+            bytes,
+            typ: PhantomData,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<T: ToColumnType> CqlBytes<T> {
+    /// The CQL type of the value.
+    pub fn column_type(&self) -> ColumnType {
+        T::to_column_type()
+    }
+}
+
+impl<T: DeserializeCql + ToColumnType> CqlBytes<T> {
+    /// Decodes the value.
+    pub fn decode(&self) -> Result<T, FromCqlValError> {
+        T::deserialize_cql_bytes(&T::to_column_type(), Some(&self.bytes))
+    }
+}
+
+impl<T: SerializeCql> CqlBytes<T> {
+    /// Serializes the value. Fails if the value can't be serialized.
+    pub fn encode(value: &T) -> Result<CqlBytes<T>, SerializeCqlError> {
+        let mut buf = Vec::new();
+        value.serialize_cql(&mut buf)?;
+        buf.drain(..4);
+        Ok(CqlBytes::from_bytes(buf))
+    }
+}
+
+// The traits are implemented manually, so that they don't require T to implement them
+impl<T> Clone for CqlBytes<T> {
+    fn clone(&self) -> Self {
+        CqlBytes::from_bytes(self.bytes.clone())
+    }
+}
+
+impl<T> fmt::Debug for CqlBytes<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CqlBytes").field(&self.bytes).finish()
+    }
+}
+
+impl<T> PartialEq for CqlBytes<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<T> Eq for CqlBytes<T> {}
+
+impl<T> Hash for CqlBytes<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state)
+    }
+}
+
+impl<T: ToColumnType> ToColumnType for CqlBytes<T> {
+    fn to_column_type() -> ColumnType {
+        T::to_column_type()
+    }
+}
+
+impl<T> SerializeCql for CqlBytes<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_bytes(&self.bytes, buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        4 + self.bytes.len()
+    }
+}
+
+impl<T: ToColumnType> DeserializeCql for CqlBytes<T> {
+    // Used for nested values, which were already decoded, so they're serialized again
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        let val = val.ok_or(FromCqlValError::ValIsNull)?;
+        let mut buf = Vec::new();
+        val.serialize_cql(&mut buf)
+            .map_err(|_| FromCqlValError::BadVal)?;
+        buf.drain(..4);
+        Ok(CqlBytes::from_bytes(buf))
+    }
+
+    // Top-level values are copied without decoding them
+    fn deserialize_cql_bytes(
+        _typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        let bytes = bytes.ok_or(FromCqlValError::ValIsNull)?;
+        Ok(CqlBytes::from_bytes(bytes.to_vec()))
+    }
+}
@@ -0,0 +1,894 @@
+use crate::codec::decode_value;
+use crate::cql_value::{ColumnType, CqlValue};
+use crate::cql_value::{Counter, Timestamp};
+use crate::deserialize_cql::DeserializeCql;
+use crate::serialize_cql::SerializeCql;
+use crate::temporal::{format_duration, format_timestamp, parse_duration};
+use crate::to_columntype::ToColumnType;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use core::convert::TryInto;
+use core::fmt::{self, Write};
+use core::str::FromStr;
+use num_bigint::BigInt;
+#[cfg(feature = "std")]
+use std::net::IpAddr;
+
+/// Formatting of values as CQL literals, e.g. `{'a': [1, 2]}` for a `HashMap<String, Vec<i32>>`.
+///
+/// The trait is implemented for all types that can be used as arguments or return values of UDFs,
+/// including `#[export_udt]` structs, which are formatted as UDT literals. The literals can be used
+/// in CQL statements, e.g. as the `INITCOND` of an aggregate, and can be parsed with [`FromCqlLiteral`].
+/// ```
+/// use scylla_udf::ToCqlLiteral;
+/// use std::collections::BTreeMap;
+///
+/// let map = BTreeMap::from([(String::from("it's"), vec![1.5, -2.0])]);
+/// assert_eq!(map.to_cql_literal(), "{'it''s': [1.5, -2.0]}");
+/// ```
+pub trait ToCqlLiteral {
+    fn to_cql_literal(&self) -> String;
+}
+
+/// Parsing of CQL literals, written like in `cqlsh`, into values.
+///
+/// The trait is implemented for all types that can be used as arguments or return values of UDFs.
+/// The literal is parsed according to the CQL type the Rust type is mapped to, e.g. a set literal
+/// `{1, 2}` for a `BTreeSet<i32>` and a UDT literal `{a: 1, b: 'x'}` for an `#[export_udt]` struct.
+/// ```
+/// use scylla_udf::FromCqlLiteral;
+///
+/// let value = <(String, Option<i32>, Vec<u8>)>::from_cql_literal("('a', null, 0x0102)").unwrap();
+/// assert_eq!(value, (String::from("a"), None, vec![1, 2]));
+/// ```
+pub trait FromCqlLiteral: Sized {
+    fn from_cql_literal(literal: &str) -> Result<Self, ParseCqlLiteralError>;
+}
+
+impl<T: SerializeCql + ToColumnType> ToCqlLiteral for T {
+    fn to_cql_literal(&self) -> String {
+        let typ = T::to_column_type();
+        let mut buf = Vec::new();
+        self.serialize_cql(&mut buf)
+            .unwrap_or_else(|e| panic!("Failed to format a value as a CQL literal: {}", e));
+        let size = i32::from_be_bytes(buf[..4].try_into().unwrap());
+        if size < 0 {
+            return String::from("null");
+        }
+        let value = decode_value(&typ, &buf[4..])
+            .unwrap_or_else(|e| panic!("Failed to format a value as a CQL literal: {}", e));
+        format_cql_literal(&typ, Some(&value))
+    }
+}
+
+impl<T: DeserializeCql + ToColumnType> FromCqlLiteral for T {
+    fn from_cql_literal(literal: &str) -> Result<Self, ParseCqlLiteralError> {
+        let value = parse_cql_literal(&T::to_column_type(), literal)?;
+        T::deserialize_cql(value).map_err(|e| ParseCqlLiteralError::new(literal, &e.to_string()))
+    }
+}
+
+/// The error returned when parsing a CQL literal fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseCqlLiteralError {
+    msg: String,
+}
+
+impl ParseCqlLiteralError {
+    fn new(literal: &str, reason: &str) -> ParseCqlLiteralError {
+        ParseCqlLiteralError {
+            msg: format!("Invalid CQL literal {:?}: {}", literal, reason),
+        }
+    }
+}
+
+impl fmt::Display for ParseCqlLiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseCqlLiteralError {}
+
+// Days of the `date` type are counted from 2^31 days before the epoch
+const DATE_EPOCH_OFFSET: i64 = 1 << 31;
+
+fn unix_epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+}
+
+// The name of a native type, used in `blobAs<type>` functions
+pub(crate) fn native_type_name(typ: &ColumnType) -> Option<&'static str> {
+    use ColumnType::*;
+    Some(match typ {
+        Ascii => "ascii",
+        BigInt => "bigint",
+        Blob => "blob",
+        Boolean => "boolean",
+        Counter => "counter",
+        Date => "date",
+        Decimal => "decimal",
+        Double => "double",
+        Duration => "duration",
+        Float => "float",
+        Inet => "inet",
+        Int => "int",
+        SmallInt => "smallint",
+        Text => "text",
+        Time => "time",
+        Timestamp => "timestamp",
+        Timeuuid => "timeuuid",
+        TinyInt => "tinyint",
+        Uuid => "uuid",
+        Varint => "varint",
+        List(_) | Map(_, _) | Set(_) | Tuple(_) | UserDefinedType { .. } | Custom(_) => {
+            return None
+        }
+    })
+}
+
+fn write_blob(out: &mut String, bytes: &[u8]) {
+    out.push_str("0x");
+    for byte in bytes {
+        let _ = write!(out, "{:02x}", byte);
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('\'');
+    out.push_str(&s.replace('\'', "''"));
+    out.push('\'');
+}
+
+// Identifiers that aren't lowercase need to be quoted, as unquoted identifiers are case-insensitive
+fn write_identifier(out: &mut String, name: &str) {
+    let mut chars = name.chars();
+    let is_plain = chars.next().map_or(false, |c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if is_plain {
+        out.push_str(name);
+    } else {
+        out.push('"');
+        out.push_str(&name.replace('"', "\"\""));
+        out.push('"');
+    }
+}
+
+fn write_float(out: &mut String, value: f64, debug: impl fmt::Debug) {
+    if value.is_nan() {
+        out.push_str("NaN");
+    } else if value.is_infinite() {
+        out.push_str(if value > 0.0 { "Infinity" } else { "-Infinity" });
+    } else {
+        // Debug, unlike Display, uses the exponent notation for very large and small values
+        let _ = write!(out, "{:?}", debug);
+    }
+}
+
+fn write_list<'a>(
+    out: &mut String,
+    typ: &ColumnType,
+    (open, close): (char, char),
+    values: impl Iterator<Item = Option<&'a CqlValue>>,
+) {
+    out.push(open);
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_value(out, typ, value);
+    }
+    out.push(close);
+}
+
+fn write_value(out: &mut String, typ: &ColumnType, value: Option<&CqlValue>) {
+    let value = match value {
+        Some(value) => value,
+        None => return out.push_str("null"),
+    };
+    match value {
+        CqlValue::Ascii(s) | CqlValue::Text(s) => write_string(out, s),
+        CqlValue::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        CqlValue::Blob(bytes) => write_blob(out, bytes),
+        CqlValue::Counter(Counter(c)) => out.push_str(&c.to_string()),
+        CqlValue::Decimal(d) => out.push_str(&d.to_string()),
+        CqlValue::Date(days) => {
+            let date = unix_epoch()
+                .checked_add_signed(chrono::Duration::days(*days as i64 - DATE_EPOCH_OFFSET));
+            match date {
+                Some(date) if (1..=9999).contains(&chrono::Datelike::year(&date)) => {
+                    write_string(out, &date.format("%Y-%m-%d").to_string())
+                }
+                // dates outside of the range of the string format are written as the number of days
+                _ => out.push_str(&days.to_string()),
+            }
+        }
+        CqlValue::Double(d) => write_float(out, *d, d),
+        CqlValue::Float(f) => write_float(out, *f as f64, f),
+        CqlValue::Duration(duration) => match format_duration(duration) {
+            Some(literal) => out.push_str(&literal),
+            // durations with components of different signs can't be written in the duration format
+            None => {
+                let mut buf = Vec::new();
+                let _ = duration.serialize_cql(&mut buf);
+                out.push_str("blobAsDuration(");
+                write_blob(out, &buf[4..]);
+                out.push(')');
+            }
+        },
+        CqlValue::Empty => match native_type_name(typ) {
+            Some(name) => {
+                let _ = write!(out, "blobAs{}(0x)", name);
+            }
+            // an empty collection is equivalent to null
+            None => out.push_str("null"),
+        },
+        CqlValue::Int(i) => out.push_str(&i.to_string()),
+        CqlValue::BigInt(i) => out.push_str(&i.to_string()),
+        CqlValue::SmallInt(i) => out.push_str(&i.to_string()),
+        CqlValue::TinyInt(i) => out.push_str(&i.to_string()),
+        CqlValue::Varint(i) => out.push_str(&i.to_string()),
+        CqlValue::Timestamp(millis) => match format_timestamp(Timestamp(*millis)) {
+            Some(literal) => write_string(out, &literal),
+            None => out.push_str(&millis.num_milliseconds().to_string()),
+        },
+        CqlValue::Time(nanos) => {
+            let nanos = nanos.num_nanoseconds().unwrap_or(-1);
+            if (0..86_400_000_000_000).contains(&nanos) {
+                let secs = nanos / 1_000_000_000;
+                let literal = format!(
+                    "{:02}:{:02}:{:02}.{:09}",
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60,
+                    nanos % 1_000_000_000
+                );
+                write_string(out, &literal);
+            } else {
+                out.push_str(&nanos.to_string());
+            }
+        }
+        #[cfg(feature = "std")]
+        CqlValue::Inet(addr) => write_string(out, &addr.to_string()),
+        CqlValue::Uuid(uuid) | CqlValue::Timeuuid(uuid) => out.push_str(&uuid.to_string()),
+        CqlValue::List(values) => {
+            let typ = match typ {
+                ColumnType::List(typ) => typ,
+                _ => typ,
+            };
+            write_list(out, typ, ('[', ']'), values.iter().map(Some))
+        }
+        CqlValue::Set(values) => {
+            let typ = match typ {
+                ColumnType::Set(typ) => typ,
+                _ => typ,
+            };
+            write_list(out, typ, ('{', '}'), values.iter().map(Some))
+        }
+        CqlValue::Map(entries) => {
+            let (key_type, value_type) = match typ {
+                ColumnType::Map(key_type, value_type) => (key_type.as_ref(), value_type.as_ref()),
+                _ => (typ, typ),
+            };
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_value(out, key_type, Some(key));
+                out.push_str(": ");
+                write_value(out, value_type, Some(value));
+            }
+            out.push('}');
+        }
+        CqlValue::Tuple(values) => {
+            out.push('(');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                let field_type = match typ {
+                    ColumnType::Tuple(types) => types.get(i).unwrap_or(typ),
+                    _ => typ,
+                };
+                write_value(out, field_type, value.as_ref());
+            }
+            out.push(')');
+        }
+        CqlValue::UserDefinedType { fields, .. } => {
+            out.push('{');
+            for (i, (name, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                let field_type = match typ {
+                    ColumnType::UserDefinedType { field_types, .. } => {
+                        field_types.get(i).map_or(typ, |(_, typ)| typ)
+                    }
+                    _ => typ,
+                };
+                write_identifier(out, name);
+                out.push_str(": ");
+                write_value(out, field_type, value.as_ref());
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Formats a value of the given CQL type (`None` for null) as a CQL literal.
+///
+/// Empty values are written as `blobAs<type>(0x)` function calls, e.g. `blobAsint(0x)`.
+pub fn format_cql_literal(typ: &ColumnType, value: Option<&CqlValue>) -> String {
+    let mut out = String::new();
+    write_value(&mut out, typ, value);
+    out
+}
+
+/// Parses a CQL literal of the given type. Returns `None` for `null`.
+pub fn parse_cql_literal(
+    typ: &ColumnType,
+    literal: &str,
+) -> Result<Option<CqlValue>, ParseCqlLiteralError> {
+    let mut parser = Parser { rest: literal };
+    let value = parser
+        .value(typ)
+        .map_err(|reason| ParseCqlLiteralError::new(literal, &reason))?;
+    parser.skip_whitespace();
+    if !parser.rest.is_empty() {
+        return Err(ParseCqlLiteralError::new(
+            literal,
+            &format!("unexpected {:?} after the value", parser.rest),
+        ));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+type ParseResult<T> = Result<T, String>;
+
+fn parse_number<T: FromStr>(word: &str, typ: &str) -> ParseResult<T> {
+    word.parse()
+        .map_err(|_| format!("{:?} is not a valid {} value", word, typ))
+}
+
+fn parse_date(s: &str) -> ParseResult<u32> {
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format!("{:?} is not a valid date, expected yyyy-mm-dd", s))?;
+    let days = (date - unix_epoch()).num_days() + DATE_EPOCH_OFFSET;
+    Ok(days as u32)
+}
+
+// `i64::MIN` milliseconds don't fit in a `chrono::Duration`
+fn timestamp_millis(millis: i64) -> ParseResult<chrono::Duration> {
+    chrono::Duration::try_milliseconds(millis)
+        .ok_or_else(|| format!("\"{}\" is not a valid timestamp value", millis))
+}
+
+// Accepts the formats accepted by Scylla: yyyy-mm-dd[(T| )hh:mm[:ss[.fff]]][Z|(+|-)hh[:]mm].
+// Timestamps without a time zone are interpreted as UTC.
+fn parse_timestamp(s: &str) -> ParseResult<i64> {
+    let invalid = || format!("{:?} is not a valid timestamp", s);
+    let (datetime, offset_minutes) = match s.strip_suffix(['Z', 'z']) {
+        Some(datetime) => (datetime, 0),
+        None => match s.rfind(['+', '-']).filter(|pos| *pos > 10) {
+            Some(pos) => {
+                let (sign, zone) = (&s[pos..pos + 1], s[pos + 1..].replace(':', ""));
+                if zone.len() != 4 || !zone.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                let minutes =
+                    zone[..2].parse::<i64>().unwrap() * 60 + zone[2..].parse::<i64>().unwrap();
+                (&s[..pos], if sign == "-" { -minutes } else { minutes })
+            }
+            None => (s, 0),
+        },
+    };
+    let datetime = datetime.replacen(' ', "T", 1);
+    let naive = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&datetime, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(&datetime, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(invalid)?;
+    Ok(naive.and_utc().timestamp_millis() - offset_minutes * 60_000)
+}
+
+// hh:mm:ss[.fffffffff]
+fn parse_time(s: &str) -> ParseResult<i64> {
+    let invalid = || format!("{:?} is not a valid time, expected hh:mm:ss[.fffffffff]", s);
+    let (hms, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let parts: Vec<&str> = hms.split(':').collect();
+    if parts.len() != 3 || fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let mut nanos = 0_i64;
+    for (part, limit) in parts.iter().zip([24, 60, 60]) {
+        let value: i64 = part
+            .parse()
+            .ok()
+            .filter(|v| (0..limit).contains(v))
+            .ok_or_else(invalid)?;
+        nanos = nanos * 60 + value;
+    }
+    let fraction = format!("{:0<9}", fraction).parse::<i64>().unwrap();
+    Ok(nanos * 1_000_000_000 + fraction)
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> ParseResult<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected {:?} at {:?}", c, self.rest))
+        }
+    }
+
+    // A bare token, e.g. a number, a UUID, a duration or a keyword
+    fn peek_word(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest;
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "+-._".contains(c)))
+            .unwrap_or(rest.len());
+        &rest[..end]
+    }
+
+    fn word(&mut self, what: &str) -> ParseResult<&'a str> {
+        let word = self.peek_word();
+        if word.is_empty() {
+            return Err(format!("expected {} at {:?}", what, self.rest));
+        }
+        self.rest = &self.rest[word.len()..];
+        Ok(word)
+    }
+
+    fn string(&mut self) -> ParseResult<String> {
+        self.skip_whitespace();
+        if let Some(rest) = self.rest.strip_prefix("$$") {
+            let end = rest.find("$$").ok_or("unterminated $$ string")?;
+            self.rest = &rest[end + 2..];
+            return Ok(rest[..end].to_string());
+        }
+        let mut rest = self
+            .rest
+            .strip_prefix('\'')
+            .ok_or_else(|| format!("expected a string at {:?}", self.rest))?;
+        let mut result = String::new();
+        loop {
+            let end = rest.find('\'').ok_or("unterminated string")?;
+            result.push_str(&rest[..end]);
+            rest = &rest[end + 1..];
+            // a quote is escaped by doubling it
+            match rest.strip_prefix('\'') {
+                Some(after) => {
+                    result.push('\'');
+                    rest = after;
+                }
+                None => break,
+            }
+        }
+        self.rest = rest;
+        Ok(result)
+    }
+
+    fn blob(&mut self) -> ParseResult<Vec<u8>> {
+        let word = self.word("a blob")?;
+        let hex = word
+            .strip_prefix("0x")
+            .or_else(|| word.strip_prefix("0X"))
+            .filter(|hex| hex.len() % 2 == 0)
+            .ok_or_else(|| {
+                format!(
+                    "{:?} is not a valid blob, expected 0x followed by hex digits",
+                    word
+                )
+            })?;
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("{:?} is not a valid blob", word))
+    }
+
+    fn identifier(&mut self) -> ParseResult<String> {
+        self.skip_whitespace();
+        if let Some(mut rest) = self.rest.strip_prefix('"') {
+            let mut result = String::new();
+            loop {
+                let end = rest.find('"').ok_or("unterminated quoted identifier")?;
+                result.push_str(&rest[..end]);
+                rest = &rest[end + 1..];
+                match rest.strip_prefix('"') {
+                    Some(after) => {
+                        result.push('"');
+                        rest = after;
+                    }
+                    None => break,
+                }
+            }
+            self.rest = rest;
+            return Ok(result);
+        }
+        // unquoted identifiers are case-insensitive
+        Ok(self.word("a field name")?.to_lowercase())
+    }
+
+    // Parses a comma-separated sequence of items until the closing character
+    fn sequence(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> ParseResult<()>,
+    ) -> ParseResult<()> {
+        if self.eat(close) {
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            if self.eat(close) {
+                return Ok(());
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn non_null_value(&mut self, typ: &ColumnType) -> ParseResult<CqlValue> {
+        self.value(typ)?
+            .ok_or_else(|| String::from("collection elements can't be null"))
+    }
+
+    fn value(&mut self, typ: &ColumnType) -> ParseResult<Option<CqlValue>> {
+        let word = self.peek_word();
+        if word.eq_ignore_ascii_case("null") {
+            self.rest = &self.rest[word.len()..];
+            return Ok(None);
+        }
+        if word.len() > 6 && word[..6].eq_ignore_ascii_case("blobas") {
+            return self.blob_as(typ, word).map(Some);
+        }
+        let value = match typ {
+            ColumnType::Ascii => {
+                let s = self.string()?;
+                if !s.is_ascii() {
+                    return Err(format!("{:?} is not a valid ascii value", s));
+                }
+                CqlValue::Ascii(s)
+            }
+            ColumnType::Text => CqlValue::Text(self.string()?),
+            ColumnType::Boolean => match self.word("a boolean")?.to_lowercase().as_str() {
+                "true" => CqlValue::Boolean(true),
+                "false" => CqlValue::Boolean(false),
+                word => return Err(format!("{:?} is not a valid boolean value", word)),
+            },
+            ColumnType::Blob => CqlValue::Blob(self.blob()?),
+            ColumnType::Counter => {
+                CqlValue::Counter(Counter(parse_number(self.word("a number")?, "counter")?))
+            }
+            ColumnType::Decimal => CqlValue::Decimal(parse_number::<BigDecimal>(
+                self.word("a number")?,
+                "decimal",
+            )?),
+            ColumnType::Double => CqlValue::Double(parse_number(self.word("a number")?, "double")?),
+            ColumnType::Float => CqlValue::Float(parse_number(self.word("a number")?, "float")?),
+            ColumnType::Int => CqlValue::Int(parse_number(self.word("a number")?, "int")?),
+            ColumnType::BigInt => CqlValue::BigInt(parse_number(self.word("a number")?, "bigint")?),
+            ColumnType::SmallInt => {
+                CqlValue::SmallInt(parse_number(self.word("a number")?, "smallint")?)
+            }
+            ColumnType::TinyInt => {
+                CqlValue::TinyInt(parse_number(self.word("a number")?, "tinyint")?)
+            }
+            ColumnType::Varint => {
+                CqlValue::Varint(parse_number::<BigInt>(self.word("a number")?, "varint")?)
+            }
+            // dates, times and timestamps can also be given as numbers
+            ColumnType::Date if word.is_empty() => CqlValue::Date(parse_date(&self.string()?)?),
+            ColumnType::Date => CqlValue::Date(parse_number(self.word("a date")?, "date")?),
+            ColumnType::Timestamp if word.is_empty() => {
+                CqlValue::Timestamp(timestamp_millis(parse_timestamp(&self.string()?)?)?)
+            }
+            ColumnType::Timestamp => CqlValue::Timestamp(timestamp_millis(parse_number(
+                self.word("a timestamp")?,
+                "timestamp",
+            )?)?),
+            ColumnType::Time => {
+                let nanos = if word.is_empty() {
+                    parse_time(&self.string()?)?
+                } else {
+                    parse_number(self.word("a time")?, "time")?
+                };
+                if !(0..86_400_000_000_000).contains(&nanos) {
+                    return Err(format!("time {} is out of range", nanos));
+                }
+                CqlValue::Time(chrono::Duration::nanoseconds(nanos))
+            }
+            ColumnType::Duration => CqlValue::Duration(
+                parse_duration(self.word("a duration")?).map_err(|e| e.to_string())?,
+            ),
+            #[cfg(feature = "std")]
+            ColumnType::Inet => {
+                let s = self.string()?;
+                CqlValue::Inet(
+                    s.parse::<IpAddr>()
+                        .map_err(|_| format!("{:?} is not a valid inet value", s))?,
+                )
+            }
+            ColumnType::Uuid | ColumnType::Timeuuid => {
+                let word = self.word("a uuid")?;
+                let uuid = uuid::Uuid::parse_str(word)
+                    .map_err(|_| format!("{:?} is not a valid uuid", word))?;
+                if let ColumnType::Timeuuid = typ {
+                    if uuid.get_version_num() != 1 {
+                        return Err(format!("{} is not a version 1 uuid", uuid));
+                    }
+                    CqlValue::Timeuuid(uuid)
+                } else {
+                    CqlValue::Uuid(uuid)
+                }
+            }
+            ColumnType::List(element_type) => {
+                self.expect('[')?;
+                let mut values = Vec::new();
+                self.sequence(']', |p| {
+                    values.push(p.non_null_value(element_type)?);
+                    Ok(())
+                })?;
+                CqlValue::List(values)
+            }
+            ColumnType::Set(element_type) => {
+                self.expect('{')?;
+                let mut values = Vec::new();
+                self.sequence('}', |p| {
+                    values.push(p.non_null_value(element_type)?);
+                    Ok(())
+                })?;
+                CqlValue::Set(values)
+            }
+            ColumnType::Map(key_type, value_type) => {
+                self.expect('{')?;
+                let mut entries = Vec::new();
+                self.sequence('}', |p| {
+                    let key = p.non_null_value(key_type)?;
+                    p.expect(':')?;
+                    entries.push((key, p.non_null_value(value_type)?));
+                    Ok(())
+                })?;
+                CqlValue::Map(entries)
+            }
+            ColumnType::Tuple(types) => {
+                self.expect('(')?;
+                let mut values = Vec::new();
+                self.sequence(')', |p| {
+                    let typ = types
+                        .get(values.len())
+                        .ok_or_else(|| format!("the tuple has more than {} fields", types.len()))?;
+                    values.push(p.value(typ)?);
+                    Ok(())
+                })?;
+                // missing trailing fields are null
+                values.resize(types.len(), None);
+                CqlValue::Tuple(values)
+            }
+            ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types,
+            } => {
+                self.expect('{')?;
+                let mut fields: Vec<(String, Option<CqlValue>)> = field_types
+                    .iter()
+                    .map(|(name, _)| (name.clone(), None))
+                    .collect();
+                let mut seen = vec![false; fields.len()];
+                self.sequence('}', |p| {
+                    let name = p.identifier()?;
+                    let index = field_types
+                        .iter()
+                        .position(|(field_name, _)| *field_name == name)
+                        .ok_or_else(|| format!("unknown field {:?} of {}", name, type_name))?;
+                    if core::mem::replace(&mut seen[index], true) {
+                        return Err(format!("duplicate field {:?}", name));
+                    }
+                    p.expect(':')?;
+                    fields[index].1 = p.value(&field_types[index].1)?;
+                    Ok(())
+                })?;
+                CqlValue::UserDefinedType {
+                    keyspace: keyspace.clone(),
+                    type_name: type_name.clone(),
+                    fields,
+                }
+            }
+            ColumnType::Custom(name) => return Err(format!("unsupported custom type {}", name)),
+            #[cfg(not(feature = "std"))]
+            ColumnType::Inet => return Err(String::from("inet values require the std feature")),
+        };
+        Ok(Some(value))
+    }
+
+    // blobAs<type>(0x...) converts the bytes to a value of a native type, which may be empty
+    fn blob_as(&mut self, typ: &ColumnType, word: &str) -> ParseResult<CqlValue> {
+        let type_name = native_type_name(typ)
+            .filter(|name| word[6..].eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("{} can't be used for a {:?} value", word, typ))?;
+        self.rest = &self.rest[word.len()..];
+        self.expect('(')?;
+        let bytes = self.blob()?;
+        self.expect(')')?;
+        if bytes.is_empty() && type_name != "blob" {
+            return Ok(CqlValue::Empty);
+        }
+        decode_value(typ, &bytes).map_err(|e| format!("invalid {} value: {}", type_name, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql_value::CqlDuration;
+    use crate::maybe_empty::MaybeEmpty;
+    use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+    fn round_trip<T>(value: T, literal: &str)
+    where
+        T: ToCqlLiteral + FromCqlLiteral + PartialEq + fmt::Debug,
+    {
+        assert_eq!(value.to_cql_literal(), literal);
+        assert_eq!(T::from_cql_literal(literal).unwrap(), value);
+    }
+
+    #[test]
+    fn native_literals() {
+        round_trip(String::from("it's"), "'it''s'");
+        round_trip(vec![0xca_u8, 0xfe], "0xcafe");
+        round_trip(-5_i8, "-5");
+        round_trip(1.5_f64, "1.5");
+        round_trip(1e100_f64, "1e100");
+        round_trip(f32::NEG_INFINITY, "-Infinity");
+        round_trip(true, "true");
+        round_trip("-12.50".parse::<BigDecimal>().unwrap(), "-12.50");
+        round_trip(
+            NaiveDate::from_ymd_opt(2023, 2, 28).unwrap(),
+            "'2023-02-28'",
+        );
+        round_trip(
+            Timestamp(chrono::Duration::milliseconds(1_700_000_000_123)),
+            "'2023-11-14T22:13:20.123Z'",
+        );
+        round_trip(
+            crate::cql_value::Time(chrono::Duration::nanoseconds(3_723_000_000_005)),
+            "'01:02:03.000000005'",
+        );
+        round_trip(
+            CqlDuration {
+                months: 14,
+                days: 3,
+                nanoseconds: 4_000_000_000,
+            },
+            "1y2mo3d4s",
+        );
+        round_trip("::1".parse::<IpAddr>().unwrap(), "'::1'");
+        round_trip(
+            uuid::Uuid::from_u128(0x12345678_9abc_1def_8000_00000000002a),
+            "12345678-9abc-1def-8000-00000000002a",
+        );
+        assert!(f64::from_cql_literal("NaN").unwrap().is_nan());
+        assert_eq!(MaybeEmpty::<i32>::Empty.to_cql_literal(), "blobAsint(0x)");
+        assert_eq!(
+            MaybeEmpty::<i32>::from_cql_literal(" BLOBASINT( 0x ) ").unwrap(),
+            MaybeEmpty::Empty
+        );
+        assert_eq!(i32::from_cql_literal("blobAsInt(0x0000002a)").unwrap(), 42);
+    }
+
+    #[test]
+    fn alternative_native_literals() {
+        assert_eq!(
+            String::from_cql_literal("$$it's$$").unwrap(),
+            String::from("it's")
+        );
+        let timestamp = |literal| {
+            Timestamp::from_cql_literal(literal)
+                .unwrap()
+                .0
+                .num_milliseconds()
+        };
+        assert_eq!(timestamp("'2023-11-14 22:13:20.123'"), 1_700_000_000_123);
+        assert_eq!(
+            timestamp("'2023-11-14T23:13:20.123+0100'"),
+            1_700_000_000_123
+        );
+        assert_eq!(timestamp("'2023-11-14T22:13-00:00'"), 1_699_999_980_000);
+        assert_eq!(timestamp("'1970-01-02'"), 86_400_000);
+        assert_eq!(timestamp("1700000000123"), 1_700_000_000_123);
+        assert_eq!(
+            NaiveDate::from_cql_literal("2147483648").unwrap(),
+            unix_epoch()
+        );
+        assert_eq!(Option::<i32>::from_cql_literal("NULL").unwrap(), None);
+    }
+
+    #[test]
+    fn collection_literals() {
+        round_trip(vec![1, 2], "[1, 2]");
+        round_trip(
+            BTreeMap::from([(String::from("a"), vec![1_i64, 2])]),
+            "{'a': [1, 2]}",
+        );
+        round_trip(BTreeSet::from([-1_i16, 3]), "{-1, 3}");
+        round_trip((1_i32, None::<String>, (true,)), "(1, null, (true))");
+        let set = HashSet::<i32>::from_cql_literal("{ 3 ,1,2 }").unwrap();
+        assert_eq!(set, HashSet::from([1, 2, 3]));
+        assert_eq!(
+            <(i32, Option<i32>)>::from_cql_literal("(1)").unwrap(),
+            (1, None)
+        );
+    }
+
+    #[test]
+    fn invalid_literals() {
+        for literal in ["", "1 2", "1.5", "'1'", "2147483648", "null x"] {
+            assert!(i32::from_cql_literal(literal).is_err(), "{}", literal);
+        }
+        assert!(i32::from_cql_literal("null").is_err());
+        assert!(String::from_cql_literal("'abc").is_err());
+        assert!(Vec::<u8>::from_cql_literal("0xabc").is_err());
+        assert!(Vec::<i32>::from_cql_literal("[1, null]").is_err());
+        assert!(<(i32,)>::from_cql_literal("(1, 2)").is_err());
+        assert!(Timestamp::from_cql_literal("-9223372036854775808").is_err());
+        let err = BTreeSet::<i32>::from_cql_literal("{1, x}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Invalid CQL literal "{1, x}": "x" is not a valid int value"#
+        );
+    }
+
+    #[test]
+    fn udt_literals() {
+        let typ = ColumnType::UserDefinedType {
+            type_name: String::from("point"),
+            keyspace: String::from("ks"),
+            field_types: vec![
+                (String::from("x"), ColumnType::Int),
+                (String::from("Label"), ColumnType::Text),
+            ],
+        };
+        let value = parse_cql_literal(&typ, r#"{"Label": 'a', X: 1}"#).unwrap();
+        assert_eq!(
+            format_cql_literal(&typ, value.as_ref()),
+            r#"{x: 1, "Label": 'a'}"#
+        );
+        let value = parse_cql_literal(&typ, "{x: 1}").unwrap();
+        assert_eq!(
+            format_cql_literal(&typ, value.as_ref()),
+            r#"{x: 1, "Label": null}"#
+        );
+        assert!(parse_cql_literal(&typ, "{y: 1}").is_err());
+        assert!(parse_cql_literal(&typ, "{x: 1, x: 2}").is_err());
+    }
+}
@@ -0,0 +1,802 @@
+use crate::codec::zigzag;
+use crate::cql_value::{ColumnType, Counter, CqlDuration, Time, Timestamp};
+use crate::to_columntype::ToColumnType;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::TryInto;
+use core::ops::Deref;
+use num_bigint::BigInt;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Comparison of values in the order used by Scylla for the CQL type the Rust type is mapped to.
+///
+/// The order may differ from the `Ord` implementation of the Rust type, if it has one. For example,
+/// UUIDs are compared by version first and version 1 UUIDs by their timestamp, `f64` values are
+/// totally ordered with NaN greater than all other values, and sets and maps are compared
+/// as lists of their elements in CQL order. Nulls (`None`) and empty values are smaller than all
+/// other values, and tuples and UDTs are compared field by field.
+///
+/// `uuid::Uuid` values are compared like `uuid` values. For version 1 UUIDs, this is the same as
+/// the `timeuuid` order, except that values with equal timestamps are then compared by the remaining
+/// bytes as unsigned, not signed bytes.
+///
+/// `Json`, `Blob` and protobuf messages don't implement `CqlOrd`, because Scylla compares them
+/// by their encoded bytes, which would have to be encoded for every comparison.
+pub trait CqlOrd {
+    fn cql_cmp(&self, other: &Self) -> Ordering;
+}
+
+// This macro implements CqlOrd for types whose Ord implementation matches the CQL order
+macro_rules! impl_cql_ord_by_ord {
+    ($($rust_type:ty),*) => {
+        $(
+            impl CqlOrd for $rust_type {
+                fn cql_cmp(&self, other: &Self) -> Ordering {
+                    self.cmp(other)
+                }
+            }
+        )*
+    };
+}
+
+// Strings and blobs are compared as unsigned bytes, like their Ord implementations
+impl_cql_ord_by_ord!(
+    bool,
+    i8,
+    i16,
+    i32,
+    i64,
+    chrono::NaiveDate,
+    num_bigint::BigInt
+);
+impl_cql_ord_by_ord!(bigdecimal::BigDecimal);
+#[cfg(feature = "std")]
+impl_cql_ord_by_ord!(Ipv4Addr, Ipv6Addr);
+impl_cql_ord_by_ord!(String, Box<str>, Arc<str>, Cow<'static, str>);
+impl_cql_ord_by_ord!(Vec<u8>, Box<[u8]>, bytes::Bytes);
+
+impl CqlOrd for Counter {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl CqlOrd for Timestamp {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl CqlOrd for Time {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl CqlOrd for f32 {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_floats(self.is_nan(), other.is_nan(), || self.total_cmp(other))
+    }
+}
+
+impl CqlOrd for f64 {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_floats(self.is_nan(), other.is_nan(), || self.total_cmp(other))
+    }
+}
+
+impl CqlOrd for uuid::Uuid {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        // both values have 16 bytes, so the comparison can't fail
+        compare_uuids(self.as_bytes(), other.as_bytes(), false).unwrap_or(Ordering::Equal)
+    }
+}
+
+// Addresses are compared bytewise, so an IPv4 address isn't always smaller than an IPv6 address
+#[cfg(feature = "std")]
+impl CqlOrd for IpAddr {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (IpAddr::V4(a), IpAddr::V6(b)) => a.octets()[..].cmp(&b.octets()[..]),
+            (IpAddr::V6(a), IpAddr::V4(b)) => a.octets()[..].cmp(&b.octets()[..]),
+            (a, b) => a.cmp(b),
+        }
+    }
+}
+
+// Durations are compared bytewise by Scylla. Each field is a vint of its zigzag encoding, which is
+// ordered like the encoded value and is never a prefix of another vint, so the fields can be compared
+// in order.
+impl CqlOrd for CqlDuration {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        let fields = |d: &CqlDuration| {
+            (
+                zigzag(d.months.into()),
+                zigzag(d.days.into()),
+                zigzag(d.nanoseconds),
+            )
+        };
+        fields(self).cmp(&fields(other))
+    }
+}
+
+impl<T: ToColumnType> CqlOrd for crate::cql_bytes::CqlBytes<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_serialized(&T::to_column_type(), self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<T: CqlOrd> CqlOrd for crate::maybe_empty::MaybeEmpty<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        use crate::maybe_empty::MaybeEmpty::*;
+        match (self, other) {
+            (Value(a), Value(b)) => a.cql_cmp(b),
+            (a, b) => a.is_empty().cmp(&b.is_empty()).reverse(),
+        }
+    }
+}
+
+impl<T: CqlOrd> CqlOrd for Option<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Some(a), Some(b)) => a.cql_cmp(b),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        }
+    }
+}
+
+// Compares the elements pairwise with `compare`, with a sequence smaller than the sequences it's
+// a prefix of. Returns None if `compare` fails, e.g. for malformed serialized values.
+fn compare_sequences<A, B>(
+    mut a: impl Iterator<Item = A>,
+    mut b: impl Iterator<Item = B>,
+    mut compare: impl FnMut(A, B) -> Option<Ordering>,
+) -> Option<Ordering> {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) => match compare(a, b)? {
+                Ordering::Equal => continue,
+                ordering => return Some(ordering),
+            },
+            (a, b) => return Some(a.is_some().cmp(&b.is_some())),
+        }
+    }
+}
+
+fn compare_elements<'a, T: CqlOrd + 'a>(
+    a: impl Iterator<Item = &'a T>,
+    b: impl Iterator<Item = &'a T>,
+) -> Ordering {
+    // the elements are compared with `cql_cmp`, which can't fail
+    compare_sequences(a, b, |a, b| Some(a.cql_cmp(b))).unwrap_or(Ordering::Equal)
+}
+
+// Sets are compared as lists of their elements, which are serialized in CQL order without duplicates
+fn sorted_elements<'a, T: CqlOrd>(elements: impl Iterator<Item = &'a T>) -> Vec<&'a T> {
+    let mut elements: Vec<&T> = elements.collect();
+    elements.sort_by(|a, b| a.cql_cmp(b));
+    elements.dedup_by(|a, b| a.cql_cmp(b).is_eq());
+    elements
+}
+
+fn compare_sets<'a, T: CqlOrd + 'a>(
+    a: impl Iterator<Item = &'a T>,
+    b: impl Iterator<Item = &'a T>,
+) -> Ordering {
+    compare_elements(
+        sorted_elements(a).into_iter(),
+        sorted_elements(b).into_iter(),
+    )
+}
+
+// Maps are compared as lists of keys and values, with entries sorted by key
+fn sorted_entries<'a, K: CqlOrd, V>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+) -> Vec<(&'a K, &'a V)> {
+    let mut entries: Vec<(&K, &V)> = entries.collect();
+    entries.sort_by(|(a, _), (b, _)| a.cql_cmp(b));
+    entries.dedup_by(|(a, _), (b, _)| a.cql_cmp(b).is_eq());
+    entries
+}
+
+fn compare_maps<'a, K: CqlOrd + 'a, V: CqlOrd + 'a>(
+    a: impl Iterator<Item = (&'a K, &'a V)>,
+    b: impl Iterator<Item = (&'a K, &'a V)>,
+) -> Ordering {
+    compare_sequences(
+        sorted_entries(a).into_iter(),
+        sorted_entries(b).into_iter(),
+        |(a_key, a_value), (b_key, b_value)| {
+            Some(a_key.cql_cmp(b_key).then_with(|| a_value.cql_cmp(b_value)))
+        },
+    )
+    .unwrap_or(Ordering::Equal)
+}
+
+impl<T: CqlOrd> CqlOrd for Vec<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_elements(self.iter(), other.iter())
+    }
+}
+
+impl<T: CqlOrd, const N: usize> CqlOrd for [T; N] {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_elements(self.iter(), other.iter())
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> CqlOrd for smallvec::SmallVec<A>
+where
+    A::Item: CqlOrd,
+{
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_elements(self.iter(), other.iter())
+    }
+}
+
+impl<T: CqlOrd> CqlOrd for BTreeSet<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_sets(self.iter(), other.iter())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: CqlOrd> CqlOrd for HashSet<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_sets(self.iter(), other.iter())
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T: CqlOrd> CqlOrd for indexmap::IndexSet<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_sets(self.iter(), other.iter())
+    }
+}
+
+impl<K: CqlOrd, V: CqlOrd> CqlOrd for BTreeMap<K, V> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_maps(self.iter(), other.iter())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: CqlOrd, V: CqlOrd> CqlOrd for HashMap<K, V> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_maps(self.iter(), other.iter())
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: CqlOrd, V: CqlOrd> CqlOrd for indexmap::IndexMap<K, V> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        compare_maps(self.iter(), other.iter())
+    }
+}
+
+macro_rules! tuple_impls {
+    ( $( $idx:tt $types:ident )* ) => {
+        impl<$($types: CqlOrd),*> CqlOrd for ($($types,)*) {
+            fn cql_cmp(&self, other: &Self) -> Ordering {
+                $(
+                    match self.$idx.cql_cmp(&other.$idx) {
+                        Ordering::Equal => {}
+                        ordering => return ordering,
+                    }
+                )*
+                Ordering::Equal
+            }
+        }
+    };
+}
+
+for_each_tuple!(tuple_impls);
+
+// Reads a [bytes] value: a 4-byte size (negative for null) followed by the bytes.
+// Returns None if the buffer is malformed.
+pub(crate) fn read_value<'a>(buf: &mut &'a [u8]) -> Option<Option<&'a [u8]>> {
+    let size = i32::from_be_bytes(buf.get(..4)?.try_into().ok()?);
+    *buf = &buf[4..];
+    if size < 0 {
+        return Some(None);
+    }
+    let size = size as usize;
+    let value = buf.get(..size)?;
+    *buf = &buf[size..];
+    Some(Some(value))
+}
+
+pub(crate) fn read_count(buf: &mut &[u8]) -> Option<usize> {
+    let count = i32::from_be_bytes(buf.get(..4)?.try_into().ok()?);
+    *buf = &buf[4..];
+    count.try_into().ok()
+}
+
+// Compares big-endian two's complement integers of the same size
+fn compare_signed(a: &[u8], b: &[u8]) -> Ordering {
+    match (a.split_first(), b.split_first()) {
+        (Some((a_first, a_rest)), Some((b_first, b_rest))) if a.len() == b.len() => (*a_first
+            as i8)
+            .cmp(&(*b_first as i8))
+            .then_with(|| a_rest.cmp(b_rest)),
+        _ => compare_varints(a, b),
+    }
+}
+
+fn compare_varints(a: &[u8], b: &[u8]) -> Ordering {
+    BigInt::from_signed_bytes_be(a).cmp(&BigInt::from_signed_bytes_be(b))
+}
+
+// Compares floats like Java's Float.compare and Double.compare: -0.0 is smaller than 0.0,
+// and NaN is equal to itself and greater than all other values
+pub(crate) fn compare_floats(
+    a_nan: bool,
+    b_nan: bool,
+    total_cmp: impl FnOnce() -> Ordering,
+) -> Ordering {
+    match (a_nan, b_nan) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => total_cmp(),
+    }
+}
+
+// The unscaled values are brought to the same scale before comparing them
+fn compare_decimals(a: &[u8], b: &[u8]) -> Option<Ordering> {
+    let decimal = |bytes: &[u8]| {
+        let scale = i32::from_be_bytes(bytes.get(..4)?.try_into().ok()?);
+        Some((scale as i64, BigInt::from_signed_bytes_be(&bytes[4..])))
+    };
+    let ((a_scale, a), (b_scale, b)) = (decimal(a)?, decimal(b)?);
+    let rescale = |value: BigInt, by: i64| value * BigInt::from(10).pow(by as u32);
+    Some(match a_scale.cmp(&b_scale) {
+        Ordering::Less => rescale(a, b_scale - a_scale).cmp(&b),
+        Ordering::Greater => a.cmp(&rescale(b, a_scale - b_scale)),
+        Ordering::Equal => a.cmp(&b),
+    })
+}
+
+// The timestamp of a version 1 UUID, reordered from the time_low, time_mid and time_hi fields
+fn uuid_timestamp(uuid: &[u8]) -> u64 {
+    let time_hi = u16::from_be_bytes([uuid[6] & 0x0f, uuid[7]]) as u64;
+    let time_mid = u16::from_be_bytes([uuid[4], uuid[5]]) as u64;
+    let time_low = u32::from_be_bytes([uuid[0], uuid[1], uuid[2], uuid[3]]) as u64;
+    (time_hi << 48) | (time_mid << 32) | time_low
+}
+
+fn compare_signed_bytes(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter().map(|x| *x as i8).cmp(b.iter().map(|x| *x as i8))
+}
+
+pub(crate) fn compare_uuids(a: &[u8], b: &[u8], timeuuid: bool) -> Option<Ordering> {
+    if a.len() != 16 || b.len() != 16 {
+        return None;
+    }
+    if timeuuid {
+        // like Scylla, the clock sequence and node are compared as signed bytes
+        return Some(
+            uuid_timestamp(a)
+                .cmp(&uuid_timestamp(b))
+                .then_with(|| compare_signed_bytes(&a[8..], &b[8..])),
+        );
+    }
+    let (a_version, b_version) = (a[6] >> 4, b[6] >> 4);
+    let mut ordering = a_version.cmp(&b_version);
+    if ordering.is_eq() && a_version == 1 {
+        ordering = uuid_timestamp(a).cmp(&uuid_timestamp(b));
+    }
+    Some(ordering.then_with(|| a.cmp(b)))
+}
+
+// Reads `count` [bytes] values, yielding None for the first malformed one
+fn read_values(mut buf: &[u8], count: usize) -> impl Iterator<Item = Option<Option<&[u8]>>> + '_ {
+    (0..count).map(move |_| read_value(&mut buf))
+}
+
+// Compares sequences of [bytes] values, e.g. the elements of a list or the fields of a tuple,
+// with types given by `types`, which has an element for each value
+fn compare_serialized_sequences<'t>(
+    types: impl Iterator<Item = &'t ColumnType>,
+    a: &[u8],
+    b: &[u8],
+    a_count: usize,
+    b_count: usize,
+) -> Option<Ordering> {
+    compare_sequences(
+        types.zip(read_values(a, a_count)),
+        read_values(b, b_count),
+        |(typ, a), b| Some(compare_nullable(typ, a?, b?)),
+    )
+}
+
+fn compare_collections(
+    element_types: &[&ColumnType],
+    mut a: &[u8],
+    mut b: &[u8],
+) -> Option<Ordering> {
+    let a_count = read_count(&mut a)? * element_types.len();
+    let b_count = read_count(&mut b)? * element_types.len();
+    let types = element_types.iter().copied().cycle();
+    compare_serialized_sequences(types, a, b, a_count, b_count)
+}
+
+// Tuples and UDTs don't store the number of their fields, so it's counted
+fn count_values(mut buf: &[u8]) -> Option<usize> {
+    let mut count = 0;
+    while !buf.is_empty() {
+        read_value(&mut buf)?;
+        count += 1;
+    }
+    Some(count)
+}
+
+fn compare_fields<'t>(
+    types: impl ExactSizeIterator<Item = &'t ColumnType>,
+    a: &[u8],
+    b: &[u8],
+) -> Option<Ordering> {
+    let (a_count, b_count) = (count_values(a)?, count_values(b)?);
+    if a_count.max(b_count) > types.len() {
+        return None;
+    }
+    compare_serialized_sequences(types, a, b, a_count, b_count)
+}
+
+fn compare_non_empty(typ: &ColumnType, a: &[u8], b: &[u8]) -> Option<Ordering> {
+    use ColumnType::*;
+    Some(match typ {
+        Int | BigInt | SmallInt | TinyInt | Counter | Timestamp | Time => compare_signed(a, b),
+        Varint => compare_varints(a, b),
+        Decimal => compare_decimals(a, b)?,
+        Float => {
+            let a = f32::from_be_bytes(a.try_into().ok()?);
+            let b = f32::from_be_bytes(b.try_into().ok()?);
+            compare_floats(a.is_nan(), b.is_nan(), || a.total_cmp(&b))
+        }
+        Double => {
+            let a = f64::from_be_bytes(a.try_into().ok()?);
+            let b = f64::from_be_bytes(b.try_into().ok()?);
+            compare_floats(a.is_nan(), b.is_nan(), || a.total_cmp(&b))
+        }
+        Uuid => compare_uuids(a, b, false)?,
+        Timeuuid => compare_uuids(a, b, true)?,
+        List(element) | Set(element) => compare_collections(&[element], a, b)?,
+        Map(key, value) => compare_collections(&[key, value], a, b)?,
+        Tuple(types) => compare_fields(types.iter(), a, b)?,
+        UserDefinedType { field_types, .. } => {
+            compare_fields(field_types.iter().map(|(_, typ)| typ), a, b)?
+        }
+        // dates are unsigned, and the remaining types are compared bytewise
+        Ascii | Text | Blob | Boolean | Date | Inet | Duration | Custom(_) => a.cmp(b),
+    })
+}
+
+/// Compares two serialized values of the given type in the order used by Scylla, e.g. for the elements of sets.
+/// Empty values are smaller than all other values. Malformed values are compared bytewise.
+pub(crate) fn compare_serialized(typ: &ColumnType, a: &[u8], b: &[u8]) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => compare_non_empty(typ, a, b).unwrap_or_else(|| a.cmp(b)),
+    }
+}
+
+/// Like `compare_serialized`, with nulls smaller than all other values.
+pub(crate) fn compare_nullable(typ: &ColumnType, a: Option<&[u8]>, b: Option<&[u8]>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare_serialized(typ, a, b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// Like `compare_nullable`, for values preceded by their size, as written by `SerializeCql`.
+pub(crate) fn compare_with_size(typ: &ColumnType, mut a: &[u8], mut b: &[u8]) -> Ordering {
+    match (read_value(&mut a), read_value(&mut b)) {
+        (Some(a), Some(b)) => compare_nullable(typ, a, b),
+        _ => a.cmp(b),
+    }
+}
+
+/// A wrapper that orders values by their [`CqlOrd`] implementation.
+///
+/// `ByCqlOrder<T>` implements `Ord`, so it can be used as the key of a `BTreeSet` or `BTreeMap`,
+/// or with `sort` and `max`, to get the same order as Scylla. It maps to the same CQL type as `T`,
+/// so it can also be used as an argument or return type of a UDF.
+/// ```
+/// use scylla_udf::{export_udf, ByCqlOrder};
+/// use std::collections::BTreeSet;
+///
+/// #[export_udf]
+/// fn latest(ids: Vec<uuid::Uuid>) -> Option<uuid::Uuid> {
+///     ids.into_iter().map(ByCqlOrder).max().map(ByCqlOrder::into_inner)
+/// }
+///
+/// #[export_udf]
+/// fn merge(a: Vec<f64>, b: Vec<f64>) -> BTreeSet<ByCqlOrder<f64>> {
+///     a.into_iter().chain(b).map(ByCqlOrder).collect()
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ByCqlOrder<T>(pub T);
+
+impl<T> ByCqlOrder<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for ByCqlOrder<T> {
+    fn from(value: T) -> ByCqlOrder<T> {
+        ByCqlOrder(value)
+    }
+}
+
+impl<T> Deref for ByCqlOrder<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: CqlOrd> CqlOrd for ByCqlOrder<T> {
+    fn cql_cmp(&self, other: &Self) -> Ordering {
+        self.0.cql_cmp(&other.0)
+    }
+}
+
+impl<T: CqlOrd> PartialEq for ByCqlOrder<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.cql_cmp(&other.0).is_eq()
+    }
+}
+
+impl<T: CqlOrd> Eq for ByCqlOrder<T> {}
+
+impl<T: CqlOrd> PartialOrd for ByCqlOrder<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: CqlOrd> Ord for ByCqlOrder<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cql_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialize_cql::SerializeCql;
+
+    fn assert_increasing<T: CqlOrd + core::fmt::Debug>(values: &[T]) {
+        for pair in values.windows(2) {
+            assert_eq!(pair[0].cql_cmp(&pair[1]), Ordering::Less, "{:?}", pair);
+            assert_eq!(pair[1].cql_cmp(&pair[0]), Ordering::Greater, "{:?}", pair);
+            assert_eq!(pair[0].cql_cmp(&pair[0]), Ordering::Equal, "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn native_types() {
+        assert_increasing(&[f64::NEG_INFINITY, -1.0, -0.0, 0.0, 1.0, f64::NAN]);
+        assert_increasing(&[String::from("Z"), String::from("a"), String::from("ab")]);
+        assert_increasing(&[vec![0x7f_u8], vec![0x80], vec![0x80, 0]]);
+        assert_increasing(&[
+            "1.5".parse::<bigdecimal::BigDecimal>().unwrap(),
+            "10".parse().unwrap(),
+        ]);
+        assert_increasing(&[
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+        ]);
+        // version 1 uuids are ordered by timestamp, which starts with its most significant bits in the 7th byte
+        assert_increasing(&[
+            uuid::Uuid::from_u128(0xffffffff_ffff_1000_8000_000000000000),
+            uuid::Uuid::from_u128(0x00000000_0000_1001_8000_000000000000),
+            uuid::Uuid::from_u128(0x00000000_0000_4000_8000_000000000000),
+        ]);
+        assert_increasing(&[
+            CqlDuration {
+                months: 0,
+                days: 1,
+                nanoseconds: 0,
+            },
+            CqlDuration {
+                months: 1,
+                days: 0,
+                nanoseconds: 0,
+            },
+        ]);
+    }
+
+    #[test]
+    fn durations_in_serialized_order() {
+        use crate::cql_bytes::CqlBytes;
+        let values = [
+            i64::MIN,
+            -(1 << 40),
+            -65,
+            -64,
+            -1,
+            0,
+            1,
+            63,
+            64,
+            1 << 40,
+            i64::MAX,
+        ];
+        let durations: Vec<_> = values
+            .iter()
+            .flat_map(|a| {
+                values.iter().map(move |b| CqlDuration {
+                    months: *a as i32,
+                    days: (*b >> 32) as i32,
+                    nanoseconds: *b,
+                })
+            })
+            .collect();
+        let serialized = |d: &CqlDuration| CqlBytes::<CqlDuration>::encode(d).unwrap();
+        for a in &durations {
+            for b in &durations {
+                let expected = serialized(a).cql_cmp(&serialized(b));
+                assert_eq!(a.cql_cmp(b), expected, "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn nulls_and_empty_values_first() {
+        use crate::maybe_empty::MaybeEmpty;
+        assert_increasing(&[
+            None,
+            Some(MaybeEmpty::Empty),
+            Some(MaybeEmpty::Value(i32::MIN)),
+        ]);
+        assert_increasing(&[(None, 5_i8), (Some(1_i32), 1), (Some(1), 2)]);
+    }
+
+    #[test]
+    fn collections() {
+        assert_increasing(&[vec![], vec![1.0_f32], vec![1.0, f32::NAN], vec![2.0]]);
+        let set = |values: &[i64]| values.iter().copied().collect::<HashSet<_>>();
+        assert_increasing(&[set(&[]), set(&[3, -1]), set(&[-1, 5]), set(&[3])]);
+        let map = |entries: &[(i32, &str)]| {
+            entries
+                .iter()
+                .map(|(k, v)| (ByCqlOrder(*k), String::from(*v)))
+                .collect::<BTreeMap<_, _>>()
+        };
+        assert_increasing(&[
+            map(&[(1, "b")]),
+            map(&[(2, "a"), (1, "c")]),
+            map(&[(2, "a")]),
+        ]);
+    }
+
+    #[test]
+    fn by_cql_order() {
+        let mut values: Vec<_> = [1.0, f64::NAN, -0.0, 0.0, -1.0]
+            .into_iter()
+            .map(ByCqlOrder)
+            .collect();
+        values.sort();
+        let sorted: Vec<f64> = values.into_iter().map(ByCqlOrder::into_inner).collect();
+        assert_eq!(format!("{:?}", sorted), "[-1.0, -0.0, 0.0, 1.0, NaN]");
+        let set: BTreeSet<_> = [2.0, 2.0, f64::NAN, f64::NAN]
+            .into_iter()
+            .map(ByCqlOrder)
+            .collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    fn cmp<T: SerializeCql + ToColumnType>(a: T, b: T) -> Ordering {
+        let (mut a_buf, mut b_buf) = (Vec::new(), Vec::new());
+        a.serialize_cql(&mut a_buf).unwrap();
+        b.serialize_cql(&mut b_buf).unwrap();
+        compare_with_size(&T::to_column_type(), &a_buf, &b_buf)
+    }
+
+    #[test]
+    fn compare_numbers() {
+        assert_eq!(cmp(-1_i32, 1), Ordering::Less);
+        assert_eq!(cmp(i64::MIN, i64::MAX), Ordering::Less);
+        assert_eq!(cmp(-1_i8, -2), Ordering::Greater);
+        assert_eq!(cmp(-0.0_f64, 0.0), Ordering::Less);
+        assert_eq!(cmp(f32::NAN, f32::INFINITY), Ordering::Greater);
+        assert_eq!(cmp(f64::NAN, f64::NAN), Ordering::Equal);
+        assert_eq!(cmp(BigInt::from(-300), BigInt::from(2)), Ordering::Less);
+        let dec = |s: &str| s.parse::<bigdecimal::BigDecimal>().unwrap();
+        assert_eq!(cmp(dec("1.50"), dec("1.5")), Ordering::Equal);
+        assert_eq!(cmp(dec("-10.5"), dec("2")), Ordering::Less);
+        assert_eq!(cmp(dec("1E+3"), dec("999.99")), Ordering::Greater);
+        assert_eq!(cmp(dec("-0.001"), dec("-1E-2")), Ordering::Greater);
+    }
+    #[test]
+    fn compare_strings_and_options() {
+        assert_eq!(
+            cmp(String::from("b"), String::from("ab")),
+            Ordering::Greater
+        );
+        assert_eq!(cmp(String::from("é"), String::from("z")), Ordering::Greater);
+        assert_eq!(cmp(None, Some(i32::MIN)), Ordering::Less);
+        assert_eq!(cmp(vec![1_u8, 2], vec![255]), Ordering::Less);
+    }
+    #[test]
+    fn compare_uuids_and_timeuuids() {
+        let v1 = |timestamp: u64, node: u8| {
+            let mut bytes = [0_u8; 16];
+            bytes[0..4].copy_from_slice(&(timestamp as u32).to_be_bytes());
+            bytes[4..6].copy_from_slice(&((timestamp >> 32) as u16).to_be_bytes());
+            bytes[6..8].copy_from_slice(&(((timestamp >> 48) as u16) | 0x1000).to_be_bytes());
+            bytes[8] = 0x80;
+            bytes[15] = node;
+            bytes
+        };
+        // the timestamp is more significant than time_low, which comes first
+        let (early, late) = (v1(0x1_0000_0000, 0), v1(0x0fff_ffff, 0));
+        assert_eq!(
+            compare_serialized(&ColumnType::Timeuuid, &early, &late),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_serialized(&ColumnType::Uuid, &early, &late),
+            Ordering::Greater
+        );
+        // the node is compared as signed bytes for timeuuids
+        let (a, b) = (v1(1, 0x7f), v1(1, 0x80));
+        assert_eq!(
+            compare_serialized(&ColumnType::Timeuuid, &a, &b),
+            Ordering::Greater
+        );
+        let v4 = uuid::Uuid::from_u128(0x4000 << 64).into_bytes();
+        assert_eq!(
+            compare_serialized(&ColumnType::Uuid, &early, &v4),
+            Ordering::Less
+        );
+    }
+    #[test]
+    fn compare_nested() {
+        assert_eq!(cmp(vec![1, -1], vec![1]), Ordering::Greater);
+        assert_eq!(cmp(vec![-1, 5], vec![1]), Ordering::Less);
+        assert_eq!(
+            cmp((1, String::from("b")), (1, String::from("a"))),
+            Ordering::Greater
+        );
+        assert_eq!(cmp((None, 2), (Some(-5), 1)), Ordering::Less);
+        let map = |entries: &[(i32, i32)]| {
+            entries
+                .iter()
+                .copied()
+                .collect::<std::collections::BTreeMap<_, _>>()
+        };
+        assert_eq!(
+            cmp(map(&[(-1, 3)]), map(&[(-1, 2), (0, 0)])),
+            Ordering::Greater
+        );
+    }
+    #[test]
+    fn empty_values_first() {
+        assert_eq!(
+            compare_serialized(&ColumnType::Int, &[], &(i32::MIN).to_be_bytes()),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_nullable(&ColumnType::Int, None, Some(&[])),
+            Ordering::Less
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use num_bigint::BigInt;
+#[cfg(feature = "std")]
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// The CQL type of a value, e.g. of an argument or the return value of a UDF.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    Custom(String),
+    Ascii,
+    Boolean,
+    Blob,
+    Counter,
+    Date,
+    Decimal,
+    Double,
+    Duration,
+    Float,
+    Int,
+    BigInt,
+    Text,
+    Timestamp,
+    Inet,
+    List(Box<ColumnType>),
+    Map(Box<ColumnType>, Box<ColumnType>),
+    Set(Box<ColumnType>),
+    UserDefinedType {
+        type_name: String,
+        keyspace: String,
+        field_types: Vec<(String, ColumnType)>,
+    },
+    SmallInt,
+    TinyInt,
+    Time,
+    Timeuuid,
+    Tuple(Vec<ColumnType>),
+    Uuid,
+    Varint,
+}
+
+/// A CQL value of any type, decoded with [`codec::decode_value`](crate::codec::decode_value).
+///
+/// `INET` values can only be represented with the `std` feature, which enables the `Inet` variant.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum CqlValue {
+    Ascii(String),
+    Boolean(bool),
+    Blob(Vec<u8>),
+    Counter(Counter),
+    Decimal(BigDecimal),
+    /// Days since -5877641-06-23 i.e. 2^31 days before unix epoch
+    /// Can be converted to chrono::NaiveDate (-262145-1-1 to 262143-12-31) using as_date
+    Date(u32),
+    Double(f64),
+    Duration(CqlDuration),
+    /// An empty value, which all types except `ascii`, `text` and `blob` can have.
+    Empty,
+    Float(f32),
+    Int(i32),
+    BigInt(i64),
+    Text(String),
+    /// Milliseconds since unix epoch
+    Timestamp(chrono::Duration),
+    #[cfg(feature = "std")]
+    Inet(IpAddr),
+    List(Vec<CqlValue>),
+    Map(Vec<(CqlValue, CqlValue)>),
+    Set(Vec<CqlValue>),
+    UserDefinedType {
+        keyspace: String,
+        type_name: String,
+        /// Order of `fields` vector must match the order of fields as defined in the UDT.
+        fields: Vec<(String, Option<CqlValue>)>,
+    },
+    SmallInt(i16),
+    TinyInt(i8),
+    /// Nanoseconds since midnight
+    Time(chrono::Duration),
+    Timeuuid(Uuid),
+    Tuple(Vec<Option<CqlValue>>),
+    Uuid(Uuid),
+    Varint(BigInt),
+}
+
+/// A value of the CQL `counter` type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Counter(pub i64);
+
+/// A value of the CQL `timestamp` type: milliseconds since unix epoch.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Timestamp(pub chrono::Duration);
+
+/// A value of the CQL `time` type: nanoseconds since midnight.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Time(pub chrono::Duration);
+
+/// A value of the CQL `duration` type.
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub struct CqlDuration {
+    pub months: i32,
+    pub days: i32,
+    pub nanoseconds: i64,
+}
+
+impl CqlValue {
+    pub fn as_ascii(&self) -> Option<&String> {
+        match self {
+            Self::Ascii(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_date(&self) -> Option<NaiveDate> {
+        let date_days = match self {
+            CqlValue::Date(days) => *days,
+            _ => return None,
+        };
+
+        // date_days is u32 then converted to i64, then we substract 2^31 - this can't overflow
+        let days_since_epoch =
+            chrono::Duration::days(date_days.into()) - chrono::Duration::days(1 << 31);
+
+        NaiveDate::from_ymd_opt(1970, 1, 1)
+            .unwrap()
+            .checked_add_signed(days_since_epoch)
+    }
+
+    pub fn as_duration(&self) -> Option<chrono::Duration> {
+        match self {
+            Self::Timestamp(i) => Some(*i),
+            Self::Time(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_timestamp(&self) -> Option<Timestamp> {
+        match self {
+            Self::Timestamp(i) => Some(Timestamp(*i)),
+            _ => None,
+        }
+    }
+
+    pub fn as_time(&self) -> Option<Time> {
+        match self {
+            Self::Time(i) => Some(Time(*i)),
+            _ => None,
+        }
+    }
+
+    pub fn as_cql_duration(&self) -> Option<CqlDuration> {
+        match self {
+            Self::Duration(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_counter(&self) -> Option<Counter> {
+        match self {
+            Self::Counter(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_boolean(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_double(&self) -> Option<f64> {
+        match self {
+            Self::Double(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        match self {
+            Self::Uuid(u) => Some(*u),
+            Self::Timeuuid(u) => Some(*u),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f32> {
+        match self {
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bigint(&self) -> Option<i64> {
+        match self {
+            Self::BigInt(i) => Some(*i),
+            Self::Timestamp(d) => Some(d.num_milliseconds()),
+            _ => None,
+        }
+    }
+
+    pub fn as_tinyint(&self) -> Option<i8> {
+        match self {
+            Self::TinyInt(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_smallint(&self) -> Option<i16> {
+        match self {
+            Self::SmallInt(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_blob(&self) -> Option<&Vec<u8>> {
+        match self {
+            Self::Blob(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&String> {
+        match self {
+            Self::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_timeuuid(&self) -> Option<Uuid> {
+        match self {
+            Self::Timeuuid(u) => Some(*u),
+            _ => None,
+        }
+    }
+
+    pub fn into_string(self) -> Option<String> {
+        match self {
+            Self::Ascii(s) => Some(s),
+            Self::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn into_blob(self) -> Option<Vec<u8>> {
+        match self {
+            Self::Blob(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn as_inet(&self) -> Option<IpAddr> {
+        match self {
+            Self::Inet(a) => Some(*a),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<CqlValue>> {
+        match self {
+            Self::List(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_set(&self) -> Option<&Vec<CqlValue>> {
+        match self {
+            Self::Set(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Vec<(CqlValue, CqlValue)>> {
+        match self {
+            Self::Map(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_udt(&self) -> Option<&Vec<(String, Option<CqlValue>)>> {
+        match self {
+            Self::UserDefinedType { fields, .. } => Some(fields),
+            _ => None,
+        }
+    }
+
+    pub fn into_vec(self) -> Option<Vec<CqlValue>> {
+        match self {
+            Self::List(s) => Some(s),
+            Self::Set(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn into_pair_vec(self) -> Option<Vec<(CqlValue, CqlValue)>> {
+        match self {
+            Self::Map(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn into_udt_pair_vec(self) -> Option<Vec<(String, Option<CqlValue>)>> {
+        match self {
+            Self::UserDefinedType { fields, .. } => Some(fields),
+            _ => None,
+        }
+    }
+
+    pub fn into_varint(self) -> Option<BigInt> {
+        match self {
+            Self::Varint(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn into_decimal(self) -> Option<BigDecimal> {
+        match self {
+            Self::Decimal(i) => Some(i),
+            _ => None,
+        }
+    }
+}
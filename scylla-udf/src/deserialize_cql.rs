@@ -0,0 +1,725 @@
+use crate::codec::{decode_duration, decode_value, FromCqlValError};
+use crate::cql_value::{ColumnType, Counter, CqlDuration, CqlValue, Time, Timestamp};
+use crate::maybe_empty::MaybeEmpty;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+#[cfg(feature = "serde")]
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+#[cfg(feature = "std")]
+use core::hash::Hash;
+use core::iter::FromIterator;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+/// Conversion of CQL values received from Scylla to a Rust type.
+pub trait DeserializeCql: Sized {
+    /// Converts a deserialized CQL value (`None` for null) to the Rust type.
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError>;
+
+    /// Converts a serialized CQL value of the given type (`None` for null) to the Rust type.
+    /// By default, the value is decoded to a `CqlValue` first. The types provided by this crate
+    /// override it to decode the value directly from the bytes.
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        let val = match bytes {
+            Some(bytes) => Some(decode_value(typ, bytes)?),
+            None => None,
+        };
+        Self::deserialize_cql(val)
+    }
+}
+
+/// Reads a serialized value preceded by its size (negative for null) from the buffer,
+/// e.g. an element of a collection or a field of a tuple or a UDT.
+pub fn read_value<'a>(buf: &mut &'a [u8]) -> Result<Option<&'a [u8]>, FromCqlValError> {
+    crate::cql_ord::read_value(buf).ok_or(FromCqlValError::BadVal)
+}
+
+// Returns the bytes of a non-null value. Empty values can only be read as types that can represent
+// them, like MaybeEmpty, so they fail like the `CqlValue::Empty` they would be deserialized to.
+fn value_bytes(bytes: Option<&[u8]>) -> Result<&[u8], FromCqlValError> {
+    match bytes {
+        None => Err(FromCqlValError::ValIsNull),
+        Some([]) => Err(FromCqlValError::BadCqlType),
+        Some(bytes) => Ok(bytes),
+    }
+}
+
+fn fixed_size<const N: usize>(bytes: Option<&[u8]>) -> Result<[u8; N], FromCqlValError> {
+    value_bytes(bytes)?
+        .try_into()
+        .map_err(|_| FromCqlValError::BadVal)
+}
+
+// This macro implements DeserializeCql for a Rust type, given the method of `CqlValue` converting
+// a value to it, and a function decoding the value directly from its bytes
+macro_rules! impl_deserialize_cql {
+    ($rust_type:ty, $method:ident, $decode:expr) => {
+        impl DeserializeCql for $rust_type {
+            fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+                val.ok_or(FromCqlValError::ValIsNull)?
+                    .$method()
+                    .ok_or(FromCqlValError::BadCqlType)
+            }
+
+            fn deserialize_cql_bytes(
+                _typ: &ColumnType,
+                bytes: Option<&[u8]>,
+            ) -> Result<Self, FromCqlValError> {
+                $decode(bytes)
+            }
+        }
+    };
+}
+
+fn decode_date(bytes: Option<&[u8]>) -> Result<chrono::NaiveDate, FromCqlValError> {
+    let days = u32::from_be_bytes(fixed_size(bytes)?) as i64 - (1 << 31);
+    chrono::NaiveDate::from_ymd_opt(1970, 1, 1)
+        .unwrap()
+        .checked_add_signed(chrono::Duration::days(days))
+        .ok_or(FromCqlValError::BadVal)
+}
+
+fn decode_decimal(bytes: Option<&[u8]>) -> Result<bigdecimal::BigDecimal, FromCqlValError> {
+    let bytes = value_bytes(bytes)?;
+    let scale: [u8; 4] = bytes
+        .get(..4)
+        .and_then(|scale| scale.try_into().ok())
+        .ok_or(FromCqlValError::BadVal)?;
+    Ok(bigdecimal::BigDecimal::new(
+        num_bigint::BigInt::from_signed_bytes_be(&bytes[4..]),
+        i32::from_be_bytes(scale) as i64,
+    ))
+}
+
+#[cfg(feature = "std")]
+fn decode_inet(bytes: Option<&[u8]>) -> Result<std::net::IpAddr, FromCqlValError> {
+    let bytes = value_bytes(bytes)?;
+    if let Ok(octets) = <[u8; 4]>::try_from(bytes) {
+        return Ok(std::net::IpAddr::from(octets));
+    }
+    <[u8; 16]>::try_from(bytes)
+        .map(std::net::IpAddr::from)
+        .map_err(|_| FromCqlValError::BadVal)
+}
+
+fn decode_text(bytes: Option<&[u8]>) -> Result<String, FromCqlValError> {
+    let bytes = bytes.ok_or(FromCqlValError::ValIsNull)?;
+    core::str::from_utf8(bytes)
+        .map(String::from)
+        .map_err(|_| FromCqlValError::BadVal)
+}
+
+fn decode_blob(bytes: Option<&[u8]>) -> Result<Vec<u8>, FromCqlValError> {
+    bytes.map(<[u8]>::to_vec).ok_or(FromCqlValError::ValIsNull)
+}
+
+// Valid values are between 0 and 86399999999999, the last nanosecond of a day
+fn decode_time(bytes: Option<&[u8]>) -> Result<Time, FromCqlValError> {
+    let nanoseconds = i64::from_be_bytes(fixed_size(bytes)?);
+    if !(0..86_400_000_000_000).contains(&nanoseconds) {
+        return Err(FromCqlValError::BadVal);
+    }
+    Ok(Time(chrono::Duration::nanoseconds(nanoseconds)))
+}
+
+impl_deserialize_cql!(
+    bool,
+    as_boolean,
+    |bytes| Ok(fixed_size::<1>(bytes)?[0] != 0)
+);
+impl_deserialize_cql!(Vec<u8>, into_blob, decode_blob);
+impl_deserialize_cql!(Counter, as_counter, |bytes| Ok(Counter(
+    i64::from_be_bytes(fixed_size(bytes)?)
+)));
+impl_deserialize_cql!(chrono::NaiveDate, as_date, decode_date);
+impl_deserialize_cql!(bigdecimal::BigDecimal, into_decimal, decode_decimal);
+impl_deserialize_cql!(f64, as_double, |bytes| Ok(f64::from_be_bytes(fixed_size(
+    bytes
+)?)));
+impl_deserialize_cql!(CqlDuration, as_cql_duration, |bytes| value_bytes(bytes)
+    .and_then(|bytes| decode_duration(bytes).ok_or(FromCqlValError::BadVal)));
+impl_deserialize_cql!(f32, as_float, |bytes| Ok(f32::from_be_bytes(fixed_size(
+    bytes
+)?)));
+impl_deserialize_cql!(i32, as_int, |bytes| Ok(i32::from_be_bytes(fixed_size(
+    bytes
+)?)));
+impl_deserialize_cql!(i64, as_bigint, |bytes| Ok(i64::from_be_bytes(fixed_size(
+    bytes
+)?)));
+impl_deserialize_cql!(String, into_string, decode_text);
+// `i64::MIN` milliseconds don't fit in a `chrono::Duration`
+impl_deserialize_cql!(Timestamp, as_timestamp, |bytes| {
+    chrono::Duration::try_milliseconds(i64::from_be_bytes(fixed_size(bytes)?))
+        .map(Timestamp)
+        .ok_or(FromCqlValError::BadVal)
+});
+#[cfg(feature = "std")]
+impl_deserialize_cql!(std::net::IpAddr, as_inet, decode_inet);
+impl_deserialize_cql!(i16, as_smallint, |bytes| Ok(i16::from_be_bytes(
+    fixed_size(bytes)?
+)));
+impl_deserialize_cql!(i8, as_tinyint, |bytes| Ok(i8::from_be_bytes(fixed_size(
+    bytes
+)?)));
+impl_deserialize_cql!(Time, as_time, decode_time);
+impl_deserialize_cql!(uuid::Uuid, as_uuid, |bytes| Ok(uuid::Uuid::from_bytes(
+    fixed_size(bytes)?
+)));
+impl_deserialize_cql!(num_bigint::BigInt, into_varint, |bytes| Ok(
+    num_bigint::BigInt::from_signed_bytes_be(value_bytes(bytes)?)
+));
+
+// This macro implements DeserializeCql for a Rust type that can be created from another type implementing DeserializeCql
+macro_rules! impl_deserialize_cql_via_from {
+    ($rust_type:ty, $from_type:ty) => {
+        impl DeserializeCql for $rust_type {
+            fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+                <$from_type as DeserializeCql>::deserialize_cql(val).map(<$rust_type>::from)
+            }
+
+            fn deserialize_cql_bytes(
+                typ: &ColumnType,
+                bytes: Option<&[u8]>,
+            ) -> Result<Self, FromCqlValError> {
+                <$from_type as DeserializeCql>::deserialize_cql_bytes(typ, bytes)
+                    .map(<$rust_type>::from)
+            }
+        }
+    };
+}
+
+impl_deserialize_cql_via_from!(Box<[u8]>, Vec<u8>);
+impl_deserialize_cql_via_from!(bytes::Bytes, Vec<u8>);
+impl_deserialize_cql_via_from!(Box<str>, String);
+impl_deserialize_cql_via_from!(Arc<str>, String);
+impl_deserialize_cql_via_from!(Cow<'static, str>, String);
+
+// Fails with FromCqlValError::BadVal if the address is of a different family
+#[cfg(feature = "std")]
+impl DeserializeCql for std::net::Ipv4Addr {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        match std::net::IpAddr::deserialize_cql(val)? {
+            std::net::IpAddr::V4(addr) => Ok(addr),
+            std::net::IpAddr::V6(_) => Err(FromCqlValError::BadVal),
+        }
+    }
+
+    fn deserialize_cql_bytes(
+        _typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        fixed_size::<4>(bytes).map(Self::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl DeserializeCql for std::net::Ipv6Addr {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        match std::net::IpAddr::deserialize_cql(val)? {
+            std::net::IpAddr::V6(addr) => Ok(addr),
+            std::net::IpAddr::V4(_) => Err(FromCqlValError::BadVal),
+        }
+    }
+
+    fn deserialize_cql_bytes(
+        _typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        fixed_size::<16>(bytes).map(Self::from)
+    }
+}
+
+// Empty values are converted to None, unless T can represent them, like MaybeEmpty. Types that can't
+// represent them fail with BadCqlType, and other errors are returned.
+fn empty_to_option<T>(result: Result<T, FromCqlValError>) -> Result<Option<T>, FromCqlValError> {
+    match result {
+        Err(FromCqlValError::BadCqlType) => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+impl<T: DeserializeCql> DeserializeCql for Option<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        match val {
+            None => Ok(None),
+            Some(CqlValue::Empty) => empty_to_option(T::deserialize_cql(Some(CqlValue::Empty))),
+            val => T::deserialize_cql(val).map(Some),
+        }
+    }
+
+    // Overridden so that T::deserialize_cql_bytes is used for non-null values
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        match bytes {
+            None => Ok(None),
+            Some([]) => empty_to_option(T::deserialize_cql_bytes(typ, Some(&[]))),
+            bytes => T::deserialize_cql_bytes(typ, bytes).map(Some),
+        }
+    }
+}
+
+impl<T: DeserializeCql> DeserializeCql for crate::cql_ord::ByCqlOrder<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        T::deserialize_cql(val).map(crate::cql_ord::ByCqlOrder)
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        T::deserialize_cql_bytes(typ, bytes).map(crate::cql_ord::ByCqlOrder)
+    }
+}
+
+impl<T: DeserializeCql> DeserializeCql for MaybeEmpty<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        match val {
+            Some(CqlValue::Empty) => Ok(MaybeEmpty::Empty),
+            val => T::deserialize_cql(val).map(MaybeEmpty::Value),
+        }
+    }
+
+    // Empty text and blob values are deserialized to empty strings and blobs, not CqlValue::Empty
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        match (typ, bytes) {
+            (ColumnType::Ascii | ColumnType::Text | ColumnType::Blob, _) | (_, None) => {
+                T::deserialize_cql_bytes(typ, bytes).map(MaybeEmpty::Value)
+            }
+            (_, Some([])) => Ok(MaybeEmpty::Empty),
+            (_, bytes) => T::deserialize_cql_bytes(typ, bytes).map(MaybeEmpty::Value),
+        }
+    }
+}
+
+// Fails with FromCqlValError::Parse if the text isn't valid JSON for T
+#[cfg(feature = "serde")]
+impl<T: serde::de::DeserializeOwned> DeserializeCql for crate::json::Json<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        let text = String::deserialize_cql(val)?;
+        serde_json::from_str(&text)
+            .map(crate::json::Json)
+            .map_err(|e| FromCqlValError::Parse(e.to_string()))
+    }
+
+    fn deserialize_cql_bytes(
+        _typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        let bytes = bytes.ok_or(FromCqlValError::ValIsNull)?;
+        serde_json::from_slice(bytes)
+            .map(crate::json::Json)
+            .map_err(|e| FromCqlValError::Parse(e.to_string()))
+    }
+}
+
+// Fails with FromCqlValError::Parse if the bytes can't be decoded to T
+#[cfg(feature = "serde")]
+impl<T: serde::de::DeserializeOwned, C: crate::blob::BlobCodec> DeserializeCql
+    for crate::blob::Blob<T, C>
+{
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        let bytes = Vec::<u8>::deserialize_cql(val)?;
+        C::decode(&bytes)
+            .map(crate::blob::Blob::new)
+            .map_err(|e| FromCqlValError::Parse(e.to_string()))
+    }
+
+    fn deserialize_cql_bytes(
+        _typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        let bytes = bytes.ok_or(FromCqlValError::ValIsNull)?;
+        C::decode(bytes)
+            .map(crate::blob::Blob::new)
+            .map_err(|e| FromCqlValError::Parse(e.to_string()))
+    }
+}
+
+fn into_elements<T: DeserializeCql>(
+    val: Option<CqlValue>,
+) -> Result<impl Iterator<Item = Result<T, FromCqlValError>>, FromCqlValError> {
+    Ok(val
+        .ok_or(FromCqlValError::ValIsNull)?
+        .into_vec()
+        .ok_or(FromCqlValError::BadCqlType)?
+        .into_iter()
+        .map(|elem| T::deserialize_cql(Some(elem))))
+}
+
+fn into_entries<K: DeserializeCql, V: DeserializeCql>(
+    val: Option<CqlValue>,
+) -> Result<impl Iterator<Item = Result<(K, V), FromCqlValError>>, FromCqlValError> {
+    Ok(val
+        .ok_or(FromCqlValError::ValIsNull)?
+        .into_pair_vec()
+        .ok_or(FromCqlValError::BadCqlType)?
+        .into_iter()
+        .map(|(key, value)| {
+            Ok((
+                K::deserialize_cql(Some(key))?,
+                V::deserialize_cql(Some(value))?,
+            ))
+        }))
+}
+
+/// Reads a field of a tuple or a UDT value from the buffer. Returns `None` for null fields,
+/// and for fields missing at the end of the value, e.g. because they were added to the UDT later.
+pub fn read_field<'a>(buf: &mut &'a [u8]) -> Result<Option<&'a [u8]>, FromCqlValError> {
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    read_value(buf)
+}
+
+// Decodes the serialized elements of a list or a set one by one
+fn decode_elements<T: DeserializeCql, C: FromIterator<T>>(
+    typ: &ColumnType,
+    bytes: Option<&[u8]>,
+) -> Result<C, FromCqlValError> {
+    let element_type = match typ {
+        ColumnType::List(element_type) | ColumnType::Set(element_type) => element_type,
+        _ => return Err(FromCqlValError::BadCqlType),
+    };
+    let mut buf = value_bytes(bytes)?;
+    let count = crate::cql_ord::read_count(&mut buf).ok_or(FromCqlValError::BadVal)?;
+    (0..count)
+        .map(|_| T::deserialize_cql_bytes(element_type, read_value(&mut buf)?))
+        .collect()
+}
+
+fn decode_entries<K: DeserializeCql, V: DeserializeCql, C: FromIterator<(K, V)>>(
+    typ: &ColumnType,
+    bytes: Option<&[u8]>,
+) -> Result<C, FromCqlValError> {
+    let (key_type, value_type) = match typ {
+        ColumnType::Map(key_type, value_type) => (key_type, value_type),
+        _ => return Err(FromCqlValError::BadCqlType),
+    };
+    let mut buf = value_bytes(bytes)?;
+    let count = crate::cql_ord::read_count(&mut buf).ok_or(FromCqlValError::BadVal)?;
+    (0..count)
+        .map(|_| {
+            let key = K::deserialize_cql_bytes(key_type, read_value(&mut buf)?)?;
+            let value = V::deserialize_cql_bytes(value_type, read_value(&mut buf)?)?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+impl<T: DeserializeCql> DeserializeCql for Vec<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_elements(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_elements(typ, bytes)
+    }
+}
+
+// Fixed-size arrays are deserialized from lists, which must have exactly N elements
+impl<T: DeserializeCql, const N: usize> DeserializeCql for [T; N] {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        let elements = into_elements(val)?.collect::<Result<Vec<T>, _>>()?;
+        <[T; N]>::try_from(elements).map_err(|_| FromCqlValError::BadVal)
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        let elements: Vec<T> = decode_elements(typ, bytes)?;
+        <[T; N]>::try_from(elements).map_err(|_| FromCqlValError::BadVal)
+    }
+}
+
+impl<T: DeserializeCql + Ord> DeserializeCql for BTreeSet<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_elements(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_elements(typ, bytes)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: DeserializeCql + Eq + Hash> DeserializeCql for HashSet<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_elements(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_elements(typ, bytes)
+    }
+}
+
+impl<K: DeserializeCql + Ord, V: DeserializeCql> DeserializeCql for BTreeMap<K, V> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_entries(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_entries(typ, bytes)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: DeserializeCql + Eq + Hash, V: DeserializeCql> DeserializeCql for HashMap<K, V> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_entries(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_entries(typ, bytes)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> DeserializeCql for smallvec::SmallVec<A>
+where
+    A::Item: DeserializeCql,
+{
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_elements(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_elements(typ, bytes)
+    }
+}
+
+// IndexSet and IndexMap keep the elements in the order in which they were received
+#[cfg(feature = "indexmap")]
+impl<T: DeserializeCql + Eq + Hash> DeserializeCql for indexmap::IndexSet<T> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_elements(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_elements(typ, bytes)
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: DeserializeCql + Eq + Hash, V: DeserializeCql> DeserializeCql for indexmap::IndexMap<K, V> {
+    fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+        into_entries(val)?.collect()
+    }
+
+    fn deserialize_cql_bytes(
+        typ: &ColumnType,
+        bytes: Option<&[u8]>,
+    ) -> Result<Self, FromCqlValError> {
+        decode_entries(typ, bytes)
+    }
+}
+
+macro_rules! tuple_impls {
+    ( $( $idx:tt $types:ident )* ) => {
+        impl<$($types: DeserializeCql),*> DeserializeCql for ($($types,)*) {
+            fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+                let fields = match val.ok_or(FromCqlValError::ValIsNull)? {
+                    CqlValue::Tuple(fields) => fields,
+                    _ => return Err(FromCqlValError::BadCqlType),
+                };
+                let mut fields = fields.into_iter();
+                let tuple = ($($types::deserialize_cql(
+                    fields.next().ok_or(FromCqlValError::BadCqlType)?,
+                )?,)*);
+                if fields.next().is_some() {
+                    return Err(FromCqlValError::BadCqlType);
+                }
+                Ok(tuple)
+            }
+
+            // Missing trailing fields are null
+            fn deserialize_cql_bytes(
+                typ: &ColumnType,
+                bytes: Option<&[u8]>,
+            ) -> Result<Self, FromCqlValError> {
+                let mut types = match typ {
+                    ColumnType::Tuple(types) => types.iter(),
+                    _ => return Err(FromCqlValError::BadCqlType),
+                };
+                let mut buf = value_bytes(bytes)?;
+                let tuple = ($($types::deserialize_cql_bytes(
+                    types.next().ok_or(FromCqlValError::BadCqlType)?,
+                    read_field(&mut buf)?,
+                )?,)*);
+                if types.next().is_some() || !buf.is_empty() {
+                    return Err(FromCqlValError::BadCqlType);
+                }
+                Ok(tuple)
+            }
+        }
+    };
+}
+
+for_each_tuple!(tuple_impls);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialize_cql::SerializeCql;
+    use crate::to_columntype::ToColumnType;
+    use core::fmt::Debug;
+
+    // Deserializes the bytes directly and through a CqlValue, and checks that the results match
+    fn both_paths<T: DeserializeCql + ToColumnType + PartialEq + Debug>(
+        bytes: Option<&[u8]>,
+    ) -> Result<T, FromCqlValError> {
+        let typ = T::to_column_type();
+        let direct = T::deserialize_cql_bytes(&typ, bytes);
+        let via_cql_value = match bytes {
+            Some(bytes) => decode_value(&typ, bytes).and_then(|val| T::deserialize_cql(Some(val))),
+            None => T::deserialize_cql(None),
+        };
+        assert_eq!(direct, via_cql_value);
+        direct
+    }
+
+    fn check<T: DeserializeCql + SerializeCql + ToColumnType + PartialEq + Debug>(value: T) {
+        let mut buf = Vec::new();
+        value.serialize_cql(&mut buf).unwrap();
+        assert_eq!(both_paths::<T>(Some(&buf[4..])), Ok(value));
+    }
+
+    #[test]
+    fn native_types() {
+        check(true);
+        check(-42_i8);
+        check(-42_i16);
+        check(-42_i32);
+        check(-42_i64);
+        check(0.5_f32);
+        check(-0.5_f64);
+        check(Counter(7));
+        check(Timestamp(chrono::Duration::milliseconds(-1234)));
+        check(Time(chrono::Duration::nanoseconds(1234)));
+        check(chrono::NaiveDate::from_ymd_opt(1410, 7, 15).unwrap());
+        check(uuid::Uuid::from_u128(0x1234));
+        check("127.0.0.1".parse::<std::net::IpAddr>().unwrap());
+        check("::1".parse::<std::net::IpAddr>().unwrap());
+        check(num_bigint::BigInt::from(-1_i64 << 40));
+        check("-12.345".parse::<bigdecimal::BigDecimal>().unwrap());
+        check(String::from("zażółć"));
+        check(String::new());
+        check(vec![0_u8, 1, 2]);
+        check(Vec::<u8>::new());
+        check(CqlDuration {
+            months: 1,
+            days: 2,
+            nanoseconds: 3,
+        });
+    }
+
+    #[test]
+    fn nested_types() {
+        check(vec![String::from("a"), String::new()]);
+        check(BTreeSet::from([1_i32, 2, 3]));
+        check(HashSet::from([1_i32, 2, 3]));
+        check(BTreeMap::from([(String::from("a"), vec![1_i64, 2])]));
+        check(HashMap::from([(1_i16, 2_i8), (3, 4)]));
+        check(Vec::<Vec<i32>>::new());
+        check((1_i32, String::from("a"), Some(vec![true])));
+        check((Option::<i32>::None, [1.5_f64, 2.5]));
+        check(vec![MaybeEmpty::Value(1_i32), MaybeEmpty::Empty]);
+    }
+
+    #[test]
+    fn null_and_empty_values() {
+        assert_eq!(both_paths::<Option<i32>>(None), Ok(None));
+        assert_eq!(both_paths::<i32>(None), Err(FromCqlValError::ValIsNull));
+        assert_eq!(both_paths::<Option<i32>>(Some(&[])), Ok(None));
+        assert_eq!(both_paths::<Option<Vec<i32>>>(Some(&[])), Ok(None));
+        // other errors aren't hidden by the empty value
+        #[cfg(feature = "serde")]
+        assert!(matches!(
+            both_paths::<Option<crate::json::Json<i32>>>(Some(&[])),
+            Err(FromCqlValError::Parse(_))
+        ));
+        assert_eq!(
+            both_paths::<MaybeEmpty<i32>>(Some(&[])),
+            Ok(MaybeEmpty::Empty)
+        );
+        assert_eq!(
+            both_paths::<Option<String>>(Some(&[])),
+            Ok(Some(String::new()))
+        );
+        assert_eq!(
+            both_paths::<MaybeEmpty<String>>(Some(&[])),
+            Ok(MaybeEmpty::Value(String::new()))
+        );
+        // the direct path also accepts values that deserializing to a CqlValue rejects:
+        // tuples lacking trailing fields and null values in collections
+        let typ = <(i32, Option<i32>)>::to_column_type();
+        let tuple = [0, 0, 0, 4, 0, 0, 0, 1];
+        assert_eq!(
+            <(i32, Option<i32>)>::deserialize_cql_bytes(&typ, Some(&tuple)),
+            Ok((1, None))
+        );
+        let typ = Vec::<Option<i32>>::to_column_type();
+        let list = [0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(
+            Vec::<Option<i32>>::deserialize_cql_bytes(&typ, Some(&list)),
+            Ok(vec![None])
+        );
+    }
+
+    #[test]
+    fn malformed_values() {
+        let typ = i32::to_column_type();
+        assert!(i32::deserialize_cql_bytes(&typ, Some(&[0, 0, 1])).is_err());
+        let typ = String::to_column_type();
+        assert!(String::deserialize_cql_bytes(&typ, Some(&[0xff])).is_err());
+        let typ = Vec::<i32>::to_column_type();
+        // a list of 2 elements with only one
+        let list = [0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 1];
+        assert!(Vec::<i32>::deserialize_cql_bytes(&typ, Some(&list)).is_err());
+        assert!(Vec::<i32>::deserialize_cql_bytes(&ColumnType::Int, Some(&list)).is_err());
+        // a valid timestamp that doesn't fit in a chrono::Duration
+        assert_eq!(
+            both_paths::<Timestamp>(Some(&i64::MIN.to_be_bytes())),
+            Err(FromCqlValError::BadVal)
+        );
+        assert_eq!(
+            both_paths::<Timestamp>(Some(&(-i64::MAX).to_be_bytes())),
+            Ok(Timestamp(chrono::Duration::milliseconds(-i64::MAX)))
+        );
+    }
+}
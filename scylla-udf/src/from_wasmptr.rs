@@ -1,24 +1,21 @@
+use crate::conversion_error::ConversionError;
+use crate::deserialize_cql::DeserializeCql;
 use crate::to_columntype::ToColumnType;
 use crate::wasmptr::WasmPtr;
-use scylla_cql::cql_to_rust::FromCqlVal;
-use scylla_cql::frame::response::result::{deser_cql_value, CqlValue};
 
-pub trait FromWasmPtr {
-    fn from_wasmptr(wasmptr: WasmPtr) -> Self;
+/// Conversion from a `WasmPtr`, implemented for all types implementing `DeserializeCql` and `ToColumnType`.
+pub trait FromWasmPtr: Sized {
+    fn from_wasmptr(wasmptr: WasmPtr) -> Result<Self, ConversionError>;
 }
 
 impl<T> FromWasmPtr for T
 where
-    T: FromCqlVal<Option<CqlValue>> + ToColumnType,
+    T: DeserializeCql + ToColumnType,
 {
-    fn from_wasmptr(wasmptr: WasmPtr) -> Self {
-        if wasmptr.is_null() {
-            return T::from_cql(None).unwrap();
-        }
-        let mut slice = wasmptr.as_slice().expect("WasmPtr::as_slice returned None");
-        T::from_cql(Some(
-            deser_cql_value(&T::to_column_type(), &mut slice).unwrap(),
-        ))
-        .unwrap()
+    fn from_wasmptr(wasmptr: WasmPtr) -> Result<Self, ConversionError> {
+        let typ = T::to_column_type();
+        let bytes = wasmptr.as_slice();
+        T::deserialize_cql_bytes(&typ, bytes)
+            .map_err(|e| ConversionError::deserialize::<T>(e, &typ, bytes))
     }
 }
@@ -0,0 +1,170 @@
+use core::fmt;
+use core::str::FromStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// An IP network, given by an address and a prefix length, e.g. `192.168.0.0/16`.
+///
+/// Scylla has no CIDR type, so `IpCidr` can't be used as an argument or return type of a UDF,
+/// but it can be used to work with `inet` values, e.g. to check whether an address belongs to a network:
+/// ```
+/// use scylla_udf::IpCidr;
+/// use std::net::IpAddr;
+///
+/// let net: IpCidr = "10.1.0.0/16".parse().unwrap();
+/// assert!(net.contains(&"10.1.2.3".parse::<IpAddr>().unwrap()));
+/// assert_eq!(net.broadcast(), "10.1.255.255".parse::<IpAddr>().unwrap());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+/// The error returned when parsing an [`IpCidr`] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseIpCidrError;
+
+impl fmt::Display for ParseIpCidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "invalid CIDR notation, expected an address and a prefix length, e.g. 10.0.0.0/8",
+        )
+    }
+}
+
+impl std::error::Error for ParseIpCidrError {}
+
+const fn max_prefix_len(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+// Returns the address as a number aligned to the most significant bits of a u128,
+// so that the same masks can be used for both address families. from_bits ignores
+// the bits that don't fit in the address.
+fn to_bits(addr: &IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(v4) => (u32::from(*v4) as u128) << 96,
+        IpAddr::V6(v6) => u128::from(*v6),
+    }
+}
+
+fn from_bits(bits: u128, family: &IpAddr) -> IpAddr {
+    match family {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from((bits >> 96) as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+    }
+}
+
+impl IpCidr {
+    /// Creates a network from an address and a prefix length.
+    /// Returns `None` if the prefix length is greater than the number of bits in the address.
+    /// The address doesn't need to be the first address of the network.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<IpCidr> {
+        if prefix_len > max_prefix_len(&addr) {
+            return None;
+        }
+        Some(IpCidr { addr, prefix_len })
+    }
+
+    /// The address the network was created with.
+    pub const fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// The number of leading bits of the address that identify the network.
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    fn mask_bits(&self) -> u128 {
+        u128::MAX
+            .checked_shl(128 - self.prefix_len as u32)
+            .unwrap_or(0)
+    }
+
+    /// The network mask, e.g. `255.255.0.0` for a `/16` IPv4 network.
+    pub fn netmask(&self) -> IpAddr {
+        from_bits(self.mask_bits(), &self.addr)
+    }
+
+    /// The first address of the network.
+    pub fn network(&self) -> IpAddr {
+        from_bits(to_bits(&self.addr) & self.mask_bits(), &self.addr)
+    }
+
+    /// The last address of the network, which for IPv4 networks is the broadcast address.
+    pub fn broadcast(&self) -> IpAddr {
+        from_bits(to_bits(&self.addr) | !self.mask_bits(), &self.addr)
+    }
+
+    /// Checks whether the address belongs to the network.
+    /// Addresses of a different family than the network never belong to it.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        addr.is_ipv4() == self.addr.is_ipv4()
+            && to_bits(addr) & self.mask_bits() == to_bits(&self.addr) & self.mask_bits()
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = ParseIpCidrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = s.split_once('/').ok_or(ParseIpCidrError)?;
+        let addr = addr.parse().map_err(|_| ParseIpCidrError)?;
+        let prefix_len = prefix_len.parse().map_err(|_| ParseIpCidrError)?;
+        IpCidr::new(addr, prefix_len).ok_or(ParseIpCidrError)
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IpCidr;
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn ipv4_cidr() {
+        let net: IpCidr = "192.168.17.5/20".parse().unwrap();
+        assert_eq!(net.netmask(), ip("255.255.240.0"));
+        assert_eq!(net.network(), ip("192.168.16.0"));
+        assert_eq!(net.broadcast(), ip("192.168.31.255"));
+        assert!(net.contains(&ip("192.168.31.1")));
+        assert!(!net.contains(&ip("192.168.32.1")));
+        assert!(!net.contains(&ip("::ffff:192.168.17.5")));
+        assert_eq!(net.to_string(), "192.168.17.5/20");
+    }
+    #[test]
+    fn ipv6_cidr() {
+        let net: IpCidr = "2001:db8::1/32".parse().unwrap();
+        assert_eq!(net.network(), ip("2001:db8::"));
+        assert_eq!(
+            net.broadcast(),
+            ip("2001:db8:ffff:ffff:ffff:ffff:ffff:ffff")
+        );
+        assert!(net.contains(&ip("2001:db8:1::")));
+        assert!(!net.contains(&ip("2001:db9::")));
+    }
+    #[test]
+    fn edge_prefixes() {
+        let all: IpCidr = "10.0.0.1/0".parse().unwrap();
+        assert!(all.contains(&ip("255.255.255.255")));
+        assert_eq!(all.broadcast(), ip("255.255.255.255"));
+        let single: IpCidr = "::1/128".parse().unwrap();
+        assert_eq!(single.network(), ip("::1"));
+        assert_eq!(single.broadcast(), ip("::1"));
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("10.0.0.0".parse::<IpCidr>().is_err());
+    }
+}
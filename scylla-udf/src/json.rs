@@ -0,0 +1,45 @@
+use core::ops::{Deref, DerefMut};
+
+/// A value stored as JSON in a `text` column.
+///
+/// `Json<T>` can be used as an argument or return type of a UDF for any `T` that implements
+/// `serde::Serialize` and `serde::de::DeserializeOwned`. Arguments are parsed from JSON, and return
+/// values are serialized to JSON. If an argument isn't valid JSON for `T`, calling the UDF fails
+/// with a conversion error.
+/// ```
+/// use scylla_udf::{export_udf, Json};
+/// use std::collections::HashMap;
+///
+/// #[export_udf]
+/// fn total(payload: Json<HashMap<String, i64>>) -> i64 {
+///     payload.values().sum()
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Json<T> {
+    fn from(value: T) -> Json<T> {
+        Json(value)
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
@@ -1,21 +1,78 @@
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+#[cfg(all(feature = "std", not(test)))]
+extern crate std;
+
+// Invokes the given macro for tuples of every arity from 1 to 32, with the index and the type
+// parameter of each element, e.g. `$m! { 0 A 1 B }` for pairs
+macro_rules! for_each_tuple {
+    ($m:ident) => {
+        for_each_tuple!(@ $m [] [0 A 1 B 2 C 3 D 4 E 5 F 6 G 7 H 8 I 9 J 10 K 11 L 12 M 13 N 14 O 15 P 16 Q 17 R 18 S 19 T 20 U 21 V 22 W 23 X 24 Y 25 Z 26 AA 27 AB 28 AC 29 AD 30 AE 31 AF]);
+    };
+    (@ $m:ident [$($done:tt)*] []) => {};
+    (@ $m:ident [$($done:tt)*] [$idx:tt $typ:ident $($rest:tt)*]) => {
+        $m! { $($done)* $idx $typ }
+        for_each_tuple!(@ $m [$($done)* $idx $typ] [$($rest)*]);
+    };
+}
+
 mod abi_exports;
+mod arena;
+#[cfg(feature = "serde")]
+mod blob;
+pub mod codec;
+mod conversion_error;
+mod cql_bytes;
+mod cql_literal;
+mod cql_ord;
+mod cql_value;
+mod deserialize_cql;
 mod from_wasmptr;
+#[cfg(feature = "std")]
+mod inet;
+#[cfg(feature = "serde")]
+mod json;
+pub mod mapping;
+mod maybe_empty;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+#[cfg(feature = "scylla-cql")]
+mod scylla_cql_conversions;
+mod serialize_cql;
+pub mod temporal;
 mod to_columntype;
 mod to_wasmptr;
+mod views;
 mod wasm_convertible;
+mod wasm_convertible_with_type;
 mod wasmptr;
 
 /// Not a part of public API. May change in minor releases.
 /// Contains all the items used by the scylla_udf macros.
 #[doc(hidden)]
 pub mod _macro_internal {
+    pub use crate::arena::CallScope;
+    pub use crate::codec::{FromCqlValError, SerializeCqlError, ValueTooBig};
+    pub use crate::conversion_error::{conversion_failed, ConversionError, ValuePosition};
+    pub use crate::cql_ord::CqlOrd;
+    pub use crate::cql_value::{ColumnType, CqlValue};
+    pub use crate::deserialize_cql::{read_field, DeserializeCql};
     pub use crate::from_wasmptr::FromWasmPtr;
+    #[cfg(feature = "protobuf")]
+    pub use crate::protobuf::new_udf_extractor;
+    pub use crate::serialize_cql::{serialize_with_size, SerializeCql};
     pub use crate::to_columntype::ToColumnType;
-    pub use crate::to_wasmptr::ToWasmPtr;
+    pub use crate::to_wasmptr::{MaxResultSize, ToWasmPtr};
     pub use crate::wasm_convertible::WasmConvertible;
+    pub use crate::wasm_convertible_with_type::WasmConvertibleWithType;
     pub use crate::wasmptr::WasmPtr;
-    pub use scylla_cql::_macro_internal::*;
-    pub use scylla_cql::frame::response::result::ColumnType;
+    pub use alloc::boxed::Box;
+    pub use alloc::string::ToString;
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+    #[cfg(feature = "protobuf")]
+    pub use prost;
 }
 
 /// This macro allows using a Rust function as a Scylla UDF.
@@ -35,6 +92,33 @@ pub mod _macro_internal {
 /// ```text
 /// CREATE FUNCTION foo(arg int) RETURNS NULL ON NULL INPUT RETURNS int LANGUAGE rust AS '(module ...)`;
 /// ```
+///
+/// Arguments and return values that can be of any CQL type can be declared as [`CqlValue`]
+/// (or `Option<CqlValue>`), with their CQL type given in the `types` attribute of the macro.
+/// The return value is referred to as `return`:
+/// ```
+/// use scylla_udf::CqlValue;
+///
+/// #[scylla_udf::export_udf(types(arg = "map<text, frozen<list<int>>>", return = "text"))]
+/// fn describe(arg: CqlValue) -> CqlValue {
+///     CqlValue::Text(format!("{:?}", arg))
+/// }
+/// ```
+/// Returning a `CqlValue` that does not match the declared type fails the call.
+///
+/// If an argument or the return value can't be converted, the call fails with a panic whose
+/// message describes the [`ConversionError`], including the name of the function and the
+/// position of the value.
+///
+/// Returned values are limited to [`DEFAULT_MAX_RESULT_SIZE`] bytes, the maximum size of a CQL value.
+/// A lower limit can be set with the `max_result_size` attribute, failing the call when the
+/// returned value is bigger:
+/// ```
+/// #[scylla_udf::export_udf(max_result_size = 65536)]
+/// fn repeat(text: String, times: i32) -> String {
+///     text.repeat(times as usize)
+/// }
+/// ```
 pub use scylla_udf_macros::export_udf;
 
 /// This macro allows mapping a Rust struct to a UDT from Scylla, and using in a scylla_udf function.
@@ -57,6 +141,8 @@ pub use scylla_udf_macros::export_udf;
 ///     c: String,
 /// }
 /// ```
+/// The macro also implements [`CqlOrd`] for the struct, comparing the fields in order.
+/// The implementation only applies if all field types implement `CqlOrd`.
 pub use scylla_udf_macros::export_udt;
 
 /// This macro allows (de)serializing a cql type to/from a Rust "newtype" struct.
@@ -74,7 +160,7 @@ pub use scylla_udf_macros::export_udt;
 ///
 /// #[scylla_udf::export_udf]
 /// fn foo(arg: MyInt) -> MyInt {
-///     ...
+///     MyInt(arg.0 + 1)
 /// }
 /// ```
 /// and a table:
@@ -87,4 +173,54 @@ pub use scylla_udf_macros::export_udt;
 /// ```
 pub use scylla_udf_macros::export_newtype;
 
-pub use scylla_cql::frame::value::{Counter, CqlDuration, Time, Timestamp};
+/// This macro allows using a protobuf message as an argument or return type of a scylla_udf function,
+/// mapped to a `blob` containing the encoded message. It requires the `protobuf` feature.
+///
+/// The macro takes a struct implementing `prost::Message`:
+/// ```
+/// #[scylla_udf::export_protobuf]
+/// #[derive(Clone, PartialEq, prost::Message)]
+/// struct Point {
+///     #[prost(int32, tag = "1")]
+///     x: i32,
+///     #[prost(int32, tag = "2")]
+///     y: i32,
+/// }
+///
+/// #[scylla_udf::export_udf]
+/// fn swap(point: Point) -> Point {
+///     Point { x: point.y, y: point.x }
+/// }
+/// ```
+/// For types generated by `prost-build`, the attribute can be added with
+/// `prost_build::Config::message_attribute(".", "#[scylla_udf::export_protobuf]")`.
+#[cfg(feature = "protobuf")]
+pub use scylla_udf_macros::export_protobuf;
+
+#[cfg(feature = "arena")]
+pub use arena::Arena;
+#[cfg(feature = "bincode")]
+pub use blob::Bincode;
+#[cfg(feature = "cbor")]
+pub use blob::Cbor;
+#[cfg(feature = "msgpack")]
+pub use blob::MessagePack;
+#[cfg(feature = "serde")]
+pub use blob::{Blob, BlobCodec};
+pub use codec::{FromCqlValError, SerializeCqlError, ValueTooBig};
+pub use conversion_error::{ConversionError, ConversionErrorKind, ValuePosition};
+pub use cql_bytes::CqlBytes;
+pub use cql_literal::{
+    format_cql_literal, parse_cql_literal, FromCqlLiteral, ParseCqlLiteralError, ToCqlLiteral,
+};
+pub use cql_ord::{ByCqlOrder, CqlOrd};
+pub use cql_value::{ColumnType, Counter, CqlDuration, CqlValue, Time, Timestamp};
+#[cfg(feature = "std")]
+pub use inet::{IpCidr, ParseIpCidrError};
+#[cfg(feature = "serde")]
+pub use json::Json;
+pub use maybe_empty::MaybeEmpty;
+#[cfg(feature = "protobuf")]
+pub use protobuf::{ProtobufFieldError, ProtobufFieldExtractor};
+pub use to_wasmptr::DEFAULT_MAX_RESULT_SIZE;
+pub use views::{ListView, MapView, SetView};
@@ -0,0 +1,97 @@
+//! Traits for mapping Rust types to CQL types, for libraries that add support for their own types.
+//!
+//! A type can be used as an argument or return type of a function annotated with `#[export_udf]`
+//! if it implements [`WasmConvertible`]. Types whose values are passed as [`WasmPtr`]s only need to
+//! implement [`ToColumnType`], [`SerializeCql`] and [`DeserializeCql`], and then get a
+//! `WasmConvertible` implementation from the [`impl_wasm_convertible`](crate::impl_wasm_convertible) macro.
+//!
+//! # Encoding
+//!
+//! Scylla passes the values of `boolean`, `tinyint`, `smallint` and `int` as `i32`, `bigint` as `i64`,
+//! `float` as `f32` and `double` as `f64`, if the function is declared with `RETURNS NULL ON NULL INPUT`.
+//! All other values, including all values of functions declared with `CALLED ON NULL INPUT`, are passed
+//! as a [`WasmPtr`] to a buffer with the value serialized as in the CQL binary protocol (version 4),
+//! without the size prefix, or as a null `WasmPtr` for null. Values of types that can be passed
+//! as native Wasm values must therefore also be accepted as `WasmPtr`s when wrapped in `Option`.
+//!
+//! [`SerializeCql::serialize_cql`] appends the serialized value preceded by its size as a 4-byte
+//! big-endian integer, or only the size -1 for null, which is how values are nested in collections,
+//! tuples and UDTs. [`serialize_with_size`] can be used to write the size of a value made
+//! of nested values. Returned values are serialized into a single buffer passed to Scylla without
+//! copying, which is allocated according to [`SerializeCql::size_hint`]. [`DeserializeCql`]
+//! receives the value either as a [`CqlValue`](crate::CqlValue) decoded according to
+//! [`ToColumnType::to_column_type`], or as the serialized bytes. Arguments of UDFs are passed as
+//! bytes, so overriding [`DeserializeCql::deserialize_cql_bytes`] avoids building the `CqlValue`;
+//! nested values can be read with [`read_value`] and [`read_field`].
+//!
+//! # Example
+//!
+//! A type mapped to `frozen<tuple<int, int>>`, serialized like a tuple of its fields:
+//! ```
+//! use scylla_udf::mapping::{serialize_with_size, DeserializeCql, SerializeCql, ToColumnType};
+//! use scylla_udf::{export_udf, impl_wasm_convertible, ColumnType, CqlValue, FromCqlValError, SerializeCqlError};
+//!
+//! #[derive(Debug, PartialEq)]
+//! pub struct Point {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! impl ToColumnType for Point {
+//!     fn to_column_type() -> ColumnType {
+//!         ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Int])
+//!     }
+//! }
+//!
+//! impl SerializeCql for Point {
+//!     fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+//!         serialize_with_size(buf, |buf| {
+//!             self.x.serialize_cql(buf)?;
+//!             self.y.serialize_cql(buf)
+//!         })
+//!     }
+//! }
+//!
+//! impl DeserializeCql for Point {
+//!     fn deserialize_cql(val: Option<CqlValue>) -> Result<Self, FromCqlValError> {
+//!         let (x, y) = <(i32, i32)>::deserialize_cql(val)?;
+//!         Ok(Point { x, y })
+//!     }
+//! }
+//!
+//! impl_wasm_convertible!(Point);
+//!
+//! #[export_udf]
+//! fn mirror(point: Point) -> Point {
+//!     Point { x: -point.x, y: point.y }
+//! }
+//! ```
+//! Generic types can implement `WasmConvertible` like the macro does, with `WasmPtr` as the
+//! `WasmType` and conversions using [`FromWasmPtr`] and [`ToWasmPtr`].
+
+pub use crate::deserialize_cql::{read_field, read_value, DeserializeCql};
+pub use crate::from_wasmptr::FromWasmPtr;
+pub use crate::serialize_cql::{serialize_with_size, SerializeCql};
+pub use crate::to_columntype::ToColumnType;
+pub use crate::to_wasmptr::ToWasmPtr;
+pub use crate::wasm_convertible::WasmConvertible;
+pub use crate::wasmptr::WasmPtr;
+
+/// Implements [`WasmConvertible`] for a type implementing [`ToColumnType`], [`SerializeCql`]
+/// and [`DeserializeCql`], passing its values as [`WasmPtr`]s.
+#[macro_export]
+macro_rules! impl_wasm_convertible {
+    ($rust_type:ty) => {
+        impl $crate::mapping::WasmConvertible for $rust_type {
+            type WasmType = $crate::mapping::WasmPtr;
+            fn from_wasm(
+                arg: Self::WasmType,
+            ) -> ::core::result::Result<Self, $crate::ConversionError> {
+                <Self as $crate::mapping::FromWasmPtr>::from_wasmptr(arg)
+            }
+            fn to_wasm(&self) -> ::core::result::Result<Self::WasmType, $crate::ConversionError> {
+                <Self as $crate::mapping::ToWasmPtr>::to_wasmptr(self)
+            }
+        }
+    };
+}
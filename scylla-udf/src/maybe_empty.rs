@@ -0,0 +1,54 @@
+/// A CQL value that may be empty.
+///
+/// Besides null, values of most CQL types (e.g. `int`, `uuid` or `timestamp`, but not `text` or `blob`)
+/// can be empty, i.e. have a size of 0. Such values can't be created with CQL literals, but they can
+/// be found in data written through Thrift or migrated from Cassandra.
+///
+/// An empty value converted to `Option<T>` becomes `None`, and can't be converted to `T` at all.
+/// `MaybeEmpty<T>` keeps the empty values apart from null and regular values, and `Option<MaybeEmpty<T>>`
+/// can be used for all three states:
+/// ```
+/// use scylla_udf::{export_udf, MaybeEmpty};
+///
+/// #[export_udf]
+/// fn describe(arg: Option<MaybeEmpty<i32>>) -> String {
+///     match arg {
+///         None => String::from("null"),
+///         Some(MaybeEmpty::Empty) => String::from("empty"),
+///         Some(MaybeEmpty::Value(v)) => v.to_string(),
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MaybeEmpty<T> {
+    Empty,
+    Value(T),
+}
+
+impl<T> MaybeEmpty<T> {
+    pub const fn is_empty(&self) -> bool {
+        matches!(self, MaybeEmpty::Empty)
+    }
+
+    /// Returns a reference to the value, or `None` if it's empty.
+    pub const fn as_value(&self) -> Option<&T> {
+        match self {
+            MaybeEmpty::Empty => None,
+            MaybeEmpty::Value(v) => Some(v),
+        }
+    }
+
+    /// Returns the value, or `None` if it's empty.
+    pub fn into_value(self) -> Option<T> {
+        match self {
+            MaybeEmpty::Empty => None,
+            MaybeEmpty::Value(v) => Some(v),
+        }
+    }
+}
+
+impl<T> From<T> for MaybeEmpty<T> {
+    fn from(value: T) -> MaybeEmpty<T> {
+        MaybeEmpty::Value(value)
+    }
+}
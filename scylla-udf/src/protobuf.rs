@@ -0,0 +1,434 @@
+use crate::cql_value::{ColumnType, CqlValue};
+use crate::deserialize_cql::DeserializeCql;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor, Value,
+};
+
+/// The error returned when a field can't be extracted from a protobuf message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtobufFieldError {
+    msg: String,
+}
+
+impl ProtobufFieldError {
+    fn new(msg: String) -> ProtobufFieldError {
+        ProtobufFieldError { msg }
+    }
+}
+
+impl fmt::Display for ProtobufFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for ProtobufFieldError {}
+
+/// Extracts a field, given by a path of field names, from encoded protobuf messages.
+///
+/// The message type is looked up in a serialized `FileDescriptorSet`, e.g. one generated
+/// with `protoc --include_imports --descriptor_set_out` and embedded with `include_bytes!`.
+/// The field must be a scalar, an enum (extracted as its number), or a repeated or map field of those.
+/// All fields on the path except the last one must be singular message fields.
+///
+/// See [`protobuf_field_udf!`](crate::protobuf_field_udf) for defining a UDF that extracts a field.
+#[derive(Clone, Debug)]
+pub struct ProtobufFieldExtractor {
+    message: MessageDescriptor,
+    path: Vec<FieldDescriptor>,
+    path_str: String,
+}
+
+// Maps a protobuf field type to the CQL type of the extracted values
+fn kind_to_column_type(kind: &Kind) -> Option<ColumnType> {
+    Some(match kind {
+        Kind::Bool => ColumnType::Boolean,
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 | Kind::Enum(_) => ColumnType::Int,
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 | Kind::Uint32 | Kind::Fixed32 => {
+            ColumnType::BigInt
+        }
+        Kind::Uint64 | Kind::Fixed64 => ColumnType::Varint,
+        Kind::Float => ColumnType::Float,
+        Kind::Double => ColumnType::Double,
+        Kind::String => ColumnType::Text,
+        Kind::Bytes => ColumnType::Blob,
+        Kind::Message(_) => return None,
+    })
+}
+
+fn field_to_column_type(field: &FieldDescriptor) -> Option<ColumnType> {
+    if field.is_map() {
+        let entry = field.kind();
+        let entry = entry.as_message()?;
+        Some(ColumnType::Map(
+            Box::new(kind_to_column_type(&entry.map_entry_key_field().kind())?),
+            Box::new(kind_to_column_type(&entry.map_entry_value_field().kind())?),
+        ))
+    } else if field.is_list() {
+        Some(ColumnType::List(Box::new(kind_to_column_type(
+            &field.kind(),
+        )?)))
+    } else {
+        kind_to_column_type(&field.kind())
+    }
+}
+
+fn value_to_cql(value: &Value) -> CqlValue {
+    match value {
+        Value::Bool(v) => CqlValue::Boolean(*v),
+        Value::I32(v) | Value::EnumNumber(v) => CqlValue::Int(*v),
+        Value::I64(v) => CqlValue::BigInt(*v),
+        Value::U32(v) => CqlValue::BigInt(*v as i64),
+        Value::U64(v) => CqlValue::Varint((*v).into()),
+        Value::F32(v) => CqlValue::Float(*v),
+        Value::F64(v) => CqlValue::Double(*v),
+        Value::String(v) => CqlValue::Text(v.clone()),
+        Value::Bytes(v) => CqlValue::Blob(v.to_vec()),
+        Value::List(values) => CqlValue::List(values.iter().map(value_to_cql).collect()),
+        Value::Map(entries) => {
+            // protobuf maps are unordered, so the entries are sorted to get deterministic results
+            let mut entries: Vec<_> = entries.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            CqlValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (map_key_to_cql(key), value_to_cql(value)))
+                    .collect(),
+            )
+        }
+        // rejected when the extractor is created
+        Value::Message(_) => unreachable!("message fields can't be extracted"),
+    }
+}
+
+fn map_key_to_cql(key: &MapKey) -> CqlValue {
+    match key {
+        MapKey::Bool(v) => CqlValue::Boolean(*v),
+        MapKey::I32(v) => CqlValue::Int(*v),
+        MapKey::I64(v) => CqlValue::BigInt(*v),
+        MapKey::U32(v) => CqlValue::BigInt(*v as i64),
+        MapKey::U64(v) => CqlValue::Varint((*v).into()),
+        MapKey::String(v) => CqlValue::Text(v.clone()),
+    }
+}
+
+impl ProtobufFieldExtractor {
+    /// Creates an extractor of the field at `path` (field names separated with dots, e.g. `address.city`)
+    /// in the message named `message` (a fully qualified name, e.g. `my.package.User`).
+    pub fn new(
+        descriptor_set: &[u8],
+        message: &str,
+        path: &str,
+    ) -> Result<ProtobufFieldExtractor, ProtobufFieldError> {
+        let pool = DescriptorPool::decode(descriptor_set)
+            .map_err(|e| ProtobufFieldError::new(format!("Invalid descriptor set: {}", e)))?;
+        let message_desc = pool.get_message_by_name(message).ok_or_else(|| {
+            ProtobufFieldError::new(format!(
+                "Message {} not found in the descriptor set",
+                message
+            ))
+        })?;
+        let mut fields = Vec::new();
+        let mut current = message_desc.clone();
+        let names: Vec<&str> = path.split('.').collect();
+        for (idx, name) in names.iter().enumerate() {
+            let field = current.get_field_by_name(name).ok_or_else(|| {
+                ProtobufFieldError::new(format!(
+                    "Field {} not found in message {}",
+                    name,
+                    current.full_name()
+                ))
+            })?;
+            if idx + 1 < names.len() {
+                current = match field.kind() {
+                    Kind::Message(next) if !field.is_list() && !field.is_map() => next,
+                    _ => {
+                        return Err(ProtobufFieldError::new(format!(
+                            "Field {} in path {} is not a singular message field",
+                            name, path
+                        )))
+                    }
+                };
+            } else if field_to_column_type(&field).is_none() {
+                return Err(ProtobufFieldError::new(format!(
+                    "Field {} contains messages, which can't be extracted",
+                    path
+                )));
+            }
+            fields.push(field);
+        }
+        Ok(ProtobufFieldExtractor {
+            message: message_desc,
+            path: fields,
+            path_str: path.to_string(),
+        })
+    }
+
+    /// The CQL type of the extracted values.
+    pub fn column_type(&self) -> ColumnType {
+        // checked when the extractor is created
+        field_to_column_type(self.path.last().unwrap()).unwrap()
+    }
+
+    /// Extracts the field from an encoded message.
+    /// Returns `None` if the field, or any message on its path, is not set. Fields without presence
+    /// tracking (e.g. proto3 scalars) are always set, and have their default value if they're missing.
+    pub fn extract(&self, bytes: &[u8]) -> Result<Option<CqlValue>, ProtobufFieldError> {
+        let mut message = DynamicMessage::decode(self.message.clone(), bytes).map_err(|e| {
+            ProtobufFieldError::new(format!(
+                "Failed to decode a {} message: {}",
+                self.message.full_name(),
+                e
+            ))
+        })?;
+        let (last, intermediate) = self.path.split_last().unwrap();
+        for field in intermediate {
+            if !message.has_field(field) {
+                return Ok(None);
+            }
+            message = match message.get_field(field).into_owned() {
+                Value::Message(next) => next,
+                _ => unreachable!("intermediate fields are checked to be messages"),
+            };
+        }
+        if last.supports_presence() && !message.has_field(last) {
+            return Ok(None);
+        }
+        Ok(Some(value_to_cql(&message.get_field(last))))
+    }
+
+    /// Extracts the field from an encoded message, and converts it to a Rust type.
+    /// Use an `Option` if the field may not be set.
+    pub fn extract_as<T: DeserializeCql>(&self, bytes: &[u8]) -> Result<T, ProtobufFieldError> {
+        T::deserialize_cql(self.extract(bytes)?).map_err(|e| {
+            ProtobufFieldError::new(format!(
+                "Failed to convert field {} to {}: {}",
+                self.path_str,
+                core::any::type_name::<T>(),
+                e
+            ))
+        })
+    }
+}
+
+// Creates the extractor of a UDF defined with `protobuf_field_udf!`. It's kept in a `thread_local` and
+// reused by the following calls, so it's allocated outside of the arena, which is reset after each call.
+#[doc(hidden)]
+pub fn new_udf_extractor(
+    descriptor_set: &[u8],
+    message: &str,
+    path: &str,
+) -> ProtobufFieldExtractor {
+    crate::arena::outside_arena(|| ProtobufFieldExtractor::new(descriptor_set, message, path))
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Defines a UDF that extracts a field from a protobuf message stored in a `blob` column.
+///
+/// The message type is described by a serialized `FileDescriptorSet`, embedded in the UDF at compile time.
+/// The field is given by a path of field names separated with dots, and is converted to the return type
+/// of the UDF, which should be an `Option` if the field may not be set. See [`ProtobufFieldExtractor`](crate::ProtobufFieldExtractor)
+/// for the supported fields.
+/// ```ignore
+/// scylla_udf::protobuf_field_udf! {
+///     fn user_city(user) -> Option<String> {
+///         descriptor: include_bytes!("users.bin"),
+///         message: "users.User",
+///         path: "address.city",
+///     }
+/// }
+/// ```
+/// The UDF takes a single `blob` argument, and is created with:
+/// ```text
+/// CREATE FUNCTION user_city(user blob) RETURNS NULL ON NULL INPUT RETURNS text LANGUAGE wasm AS '...';
+/// ```
+#[macro_export]
+macro_rules! protobuf_field_udf {
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($arg:ident) -> $ret:ty {
+            descriptor: $descriptor:expr,
+            message: $message:expr,
+            path: $path:expr $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[$crate::export_udf]
+        $vis fn $name($arg: ::std::vec::Vec<::core::primitive::u8>) -> $ret {
+            ::std::thread_local! {
+                static EXTRACTOR: $crate::ProtobufFieldExtractor =
+                    $crate::_macro_internal::new_udf_extractor($descriptor, $message, $path);
+            }
+            EXTRACTOR
+                .with(|extractor| extractor.extract_as::<$ret>(&$arg))
+                .unwrap_or_else(|e| ::std::panic!("{}", e))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+    use prost_reflect::prost_types::{
+        DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        MessageOptions,
+    };
+
+    fn field(
+        name: &str,
+        number: i32,
+        typ: Type,
+        label: Label,
+        type_name: Option<&str>,
+    ) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(typ as i32),
+            label: Some(label as i32),
+            type_name: type_name.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    // message Address { string city = 1; }
+    // message User { string name = 1; optional Address address = 2; repeated int32 scores = 3; map<string, uint64> counts = 4; }
+    fn descriptor_set() -> Vec<u8> {
+        let address = DescriptorProto {
+            name: Some("Address".to_string()),
+            field: vec![field("city", 1, Type::String, Label::Optional, None)],
+            ..Default::default()
+        };
+        let counts_entry = DescriptorProto {
+            name: Some("CountsEntry".to_string()),
+            field: vec![
+                field("key", 1, Type::String, Label::Optional, None),
+                field("value", 2, Type::Uint64, Label::Optional, None),
+            ],
+            options: Some(MessageOptions {
+                map_entry: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let user = DescriptorProto {
+            name: Some("User".to_string()),
+            field: vec![
+                field("name", 1, Type::String, Label::Optional, None),
+                field(
+                    "address",
+                    2,
+                    Type::Message,
+                    Label::Optional,
+                    Some(".test.Address"),
+                ),
+                field("scores", 3, Type::Int32, Label::Repeated, None),
+                field(
+                    "counts",
+                    4,
+                    Type::Message,
+                    Label::Repeated,
+                    Some(".test.User.CountsEntry"),
+                ),
+            ],
+            nested_type: vec![counts_entry],
+            ..Default::default()
+        };
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("test.proto".to_string()),
+                package: Some("test".to_string()),
+                message_type: vec![address, user],
+                syntax: Some("proto3".to_string()),
+                ..Default::default()
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    fn user(with_address: bool) -> Vec<u8> {
+        let pool = DescriptorPool::decode(descriptor_set().as_slice()).unwrap();
+        let mut user = DynamicMessage::new(pool.get_message_by_name("test.User").unwrap());
+        user.set_field_by_name("name", Value::String("Ann".to_string()));
+        user.set_field_by_name("scores", Value::List(vec![Value::I32(3), Value::I32(5)]));
+        user.set_field_by_name(
+            "counts",
+            Value::Map(
+                [
+                    (MapKey::String("b".to_string()), Value::U64(2)),
+                    (MapKey::String("a".to_string()), Value::U64(1)),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        );
+        if with_address {
+            let mut address =
+                DynamicMessage::new(pool.get_message_by_name("test.Address").unwrap());
+            address.set_field_by_name("city", Value::String("Oslo".to_string()));
+            user.set_field_by_name("address", Value::Message(address));
+        }
+        user.encode_to_vec()
+    }
+
+    fn extractor(path: &str) -> ProtobufFieldExtractor {
+        ProtobufFieldExtractor::new(&descriptor_set(), "test.User", path).unwrap()
+    }
+
+    #[test]
+    fn extract_nested_field() {
+        let city = extractor("address.city");
+        assert!(matches!(city.column_type(), ColumnType::Text));
+        assert_eq!(
+            city.extract_as::<Option<String>>(&user(true)).unwrap(),
+            Some("Oslo".to_string())
+        );
+        assert_eq!(
+            city.extract_as::<Option<String>>(&user(false)).unwrap(),
+            None
+        );
+        assert_eq!(
+            extractor("name")
+                .extract_as::<String>(&user(false))
+                .unwrap(),
+            "Ann"
+        );
+    }
+    #[test]
+    fn extract_collections() {
+        let scores = extractor("scores");
+        assert!(matches!(
+            scores.column_type(),
+            ColumnType::List(elem) if matches!(*elem, ColumnType::Int)
+        ));
+        assert_eq!(
+            scores.extract_as::<Vec<i32>>(&user(true)).unwrap(),
+            vec![3, 5]
+        );
+        let counts = extractor("counts");
+        assert_eq!(
+            counts.extract(&user(true)).unwrap(),
+            Some(CqlValue::Map(vec![
+                (CqlValue::Text("a".to_string()), CqlValue::Varint(1.into())),
+                (CqlValue::Text("b".to_string()), CqlValue::Varint(2.into())),
+            ]))
+        );
+    }
+    #[test]
+    fn extract_errors() {
+        let descriptor_set = descriptor_set();
+        assert!(ProtobufFieldExtractor::new(&descriptor_set, "test.Missing", "name").is_err());
+        assert!(ProtobufFieldExtractor::new(&descriptor_set, "test.User", "age").is_err());
+        assert!(ProtobufFieldExtractor::new(&descriptor_set, "test.User", "address").is_err());
+        assert!(ProtobufFieldExtractor::new(&descriptor_set, "test.User", "name.first").is_err());
+        assert!(extractor("name").extract(&[0xff]).is_err());
+        assert!(extractor("name").extract_as::<i32>(&user(true)).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+//! Conversions between the CQL values of this crate and the ones of `scylla-cql`, enabled by the `scylla-cql` feature.
+//!
+//! Decimals and varints are converted through their big-endian digits, because the crates may use
+//! different versions of `bigdecimal` and `num-bigint`.
+
+use crate::codec::ValueTooBig;
+use crate::cql_value::{ColumnType, Counter, CqlDuration, CqlValue, Time, Timestamp};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use bigdecimal::BigDecimal;
+use core::convert::TryFrom;
+use num_bigint::BigInt;
+use scylla_cql::frame::response::result as driver;
+use scylla_cql::frame::value as driver_value;
+
+impl From<ColumnType> for driver::ColumnType {
+    fn from(typ: ColumnType) -> Self {
+        let convert = |typ: Box<ColumnType>| Box::new(driver::ColumnType::from(*typ));
+        match typ {
+            ColumnType::Custom(name) => driver::ColumnType::Custom(name),
+            ColumnType::Ascii => driver::ColumnType::Ascii,
+            ColumnType::Boolean => driver::ColumnType::Boolean,
+            ColumnType::Blob => driver::ColumnType::Blob,
+            ColumnType::Counter => driver::ColumnType::Counter,
+            ColumnType::Date => driver::ColumnType::Date,
+            ColumnType::Decimal => driver::ColumnType::Decimal,
+            ColumnType::Double => driver::ColumnType::Double,
+            ColumnType::Duration => driver::ColumnType::Duration,
+            ColumnType::Float => driver::ColumnType::Float,
+            ColumnType::Int => driver::ColumnType::Int,
+            ColumnType::BigInt => driver::ColumnType::BigInt,
+            ColumnType::Text => driver::ColumnType::Text,
+            ColumnType::Timestamp => driver::ColumnType::Timestamp,
+            ColumnType::Inet => driver::ColumnType::Inet,
+            ColumnType::List(typ) => driver::ColumnType::List(convert(typ)),
+            ColumnType::Map(key, value) => driver::ColumnType::Map(convert(key), convert(value)),
+            ColumnType::Set(typ) => driver::ColumnType::Set(convert(typ)),
+            ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types,
+            } => driver::ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types: field_types
+                    .into_iter()
+                    .map(|(name, typ)| (name, typ.into()))
+                    .collect(),
+            },
+            ColumnType::SmallInt => driver::ColumnType::SmallInt,
+            ColumnType::TinyInt => driver::ColumnType::TinyInt,
+            ColumnType::Time => driver::ColumnType::Time,
+            ColumnType::Timeuuid => driver::ColumnType::Timeuuid,
+            ColumnType::Tuple(types) => {
+                driver::ColumnType::Tuple(types.into_iter().map(Into::into).collect())
+            }
+            ColumnType::Uuid => driver::ColumnType::Uuid,
+            ColumnType::Varint => driver::ColumnType::Varint,
+        }
+    }
+}
+
+impl From<driver::ColumnType> for ColumnType {
+    fn from(typ: driver::ColumnType) -> Self {
+        let convert = |typ: Box<driver::ColumnType>| Box::new(ColumnType::from(*typ));
+        match typ {
+            driver::ColumnType::Custom(name) => ColumnType::Custom(name),
+            driver::ColumnType::Ascii => ColumnType::Ascii,
+            driver::ColumnType::Boolean => ColumnType::Boolean,
+            driver::ColumnType::Blob => ColumnType::Blob,
+            driver::ColumnType::Counter => ColumnType::Counter,
+            driver::ColumnType::Date => ColumnType::Date,
+            driver::ColumnType::Decimal => ColumnType::Decimal,
+            driver::ColumnType::Double => ColumnType::Double,
+            driver::ColumnType::Duration => ColumnType::Duration,
+            driver::ColumnType::Float => ColumnType::Float,
+            driver::ColumnType::Int => ColumnType::Int,
+            driver::ColumnType::BigInt => ColumnType::BigInt,
+            driver::ColumnType::Text => ColumnType::Text,
+            driver::ColumnType::Timestamp => ColumnType::Timestamp,
+            driver::ColumnType::Inet => ColumnType::Inet,
+            driver::ColumnType::List(typ) => ColumnType::List(convert(typ)),
+            driver::ColumnType::Map(key, value) => ColumnType::Map(convert(key), convert(value)),
+            driver::ColumnType::Set(typ) => ColumnType::Set(convert(typ)),
+            driver::ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types,
+            } => ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types: field_types
+                    .into_iter()
+                    .map(|(name, typ)| (name, typ.into()))
+                    .collect(),
+            },
+            driver::ColumnType::SmallInt => ColumnType::SmallInt,
+            driver::ColumnType::TinyInt => ColumnType::TinyInt,
+            driver::ColumnType::Time => ColumnType::Time,
+            driver::ColumnType::Timeuuid => ColumnType::Timeuuid,
+            driver::ColumnType::Tuple(types) => {
+                ColumnType::Tuple(types.into_iter().map(Into::into).collect())
+            }
+            driver::ColumnType::Uuid => ColumnType::Uuid,
+            driver::ColumnType::Varint => ColumnType::Varint,
+        }
+    }
+}
+
+fn convert_all<T, U: TryFrom<T, Error = ValueTooBig>>(
+    values: Vec<T>,
+) -> Result<Vec<U>, ValueTooBig> {
+    values.into_iter().map(U::try_from).collect()
+}
+
+fn convert_fields<T, U: TryFrom<T, Error = ValueTooBig>>(
+    fields: Vec<Option<T>>,
+) -> Result<Vec<Option<U>>, ValueTooBig> {
+    fields
+        .into_iter()
+        .map(|value| value.map(U::try_from).transpose())
+        .collect()
+}
+
+/// Fails only for decimals with a scale that doesn't fit in an `i32` and times that don't fit in an
+/// `i64` of nanoseconds, which can't be serialized.
+impl TryFrom<CqlValue> for driver::CqlValue {
+    type Error = ValueTooBig;
+
+    fn try_from(value: CqlValue) -> Result<Self, ValueTooBig> {
+        Ok(match value {
+            CqlValue::Ascii(s) => driver::CqlValue::Ascii(s),
+            CqlValue::Boolean(b) => driver::CqlValue::Boolean(b),
+            CqlValue::Blob(b) => driver::CqlValue::Blob(b),
+            CqlValue::Counter(c) => driver::CqlValue::Counter(c.into()),
+            CqlValue::Decimal(d) => {
+                let (digits, scale) = d.into_bigint_and_exponent();
+                driver::CqlValue::Decimal(
+                    driver_value::CqlDecimal::from_signed_be_bytes_and_exponent(
+                        digits.to_signed_bytes_be(),
+                        i32::try_from(scale).map_err(|_| ValueTooBig)?,
+                    ),
+                )
+            }
+            CqlValue::Date(d) => driver::CqlValue::Date(driver_value::CqlDate(d)),
+            CqlValue::Double(d) => driver::CqlValue::Double(d),
+            CqlValue::Duration(d) => driver::CqlValue::Duration(d.into()),
+            CqlValue::Empty => driver::CqlValue::Empty,
+            CqlValue::Float(f) => driver::CqlValue::Float(f),
+            CqlValue::Int(i) => driver::CqlValue::Int(i),
+            CqlValue::BigInt(i) => driver::CqlValue::BigInt(i),
+            CqlValue::Text(s) => driver::CqlValue::Text(s),
+            CqlValue::Timestamp(t) => driver::CqlValue::Timestamp(Timestamp(t).into()),
+            CqlValue::Inet(addr) => driver::CqlValue::Inet(addr),
+            CqlValue::List(elements) => driver::CqlValue::List(convert_all(elements)?),
+            CqlValue::Map(entries) => driver::CqlValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| Ok((key.try_into()?, value.try_into()?)))
+                    .collect::<Result<_, ValueTooBig>>()?,
+            ),
+            CqlValue::Set(elements) => driver::CqlValue::Set(convert_all(elements)?),
+            CqlValue::UserDefinedType {
+                keyspace,
+                type_name,
+                fields,
+            } => {
+                let (names, values): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+                driver::CqlValue::UserDefinedType {
+                    keyspace,
+                    type_name,
+                    fields: names.into_iter().zip(convert_fields(values)?).collect(),
+                }
+            }
+            CqlValue::SmallInt(i) => driver::CqlValue::SmallInt(i),
+            CqlValue::TinyInt(i) => driver::CqlValue::TinyInt(i),
+            CqlValue::Time(t) => driver::CqlValue::Time(Time(t).try_into()?),
+            CqlValue::Timeuuid(u) => driver::CqlValue::Timeuuid(u.into()),
+            CqlValue::Tuple(fields) => driver::CqlValue::Tuple(convert_fields(fields)?),
+            CqlValue::Uuid(u) => driver::CqlValue::Uuid(u),
+            CqlValue::Varint(i) => driver::CqlValue::Varint(
+                driver_value::CqlVarint::from_signed_bytes_be(i.to_signed_bytes_be()),
+            ),
+        })
+    }
+}
+
+/// Fails only for timestamps of `i64::MIN` milliseconds, which don't fit in a `chrono::Duration`.
+impl TryFrom<driver::CqlValue> for CqlValue {
+    type Error = ValueTooBig;
+
+    fn try_from(value: driver::CqlValue) -> Result<Self, ValueTooBig> {
+        Ok(match value {
+            driver::CqlValue::Ascii(s) => CqlValue::Ascii(s),
+            driver::CqlValue::Boolean(b) => CqlValue::Boolean(b),
+            driver::CqlValue::Blob(b) => CqlValue::Blob(b),
+            driver::CqlValue::Counter(c) => CqlValue::Counter(c.into()),
+            driver::CqlValue::Decimal(d) => {
+                let (digits, scale) = d.as_signed_be_bytes_slice_and_exponent();
+                CqlValue::Decimal(BigDecimal::new(
+                    BigInt::from_signed_bytes_be(digits),
+                    scale.into(),
+                ))
+            }
+            driver::CqlValue::Date(d) => CqlValue::Date(d.0),
+            driver::CqlValue::Double(d) => CqlValue::Double(d),
+            driver::CqlValue::Duration(d) => CqlValue::Duration(d.into()),
+            driver::CqlValue::Empty => CqlValue::Empty,
+            driver::CqlValue::Float(f) => CqlValue::Float(f),
+            driver::CqlValue::Int(i) => CqlValue::Int(i),
+            driver::CqlValue::BigInt(i) => CqlValue::BigInt(i),
+            driver::CqlValue::Text(s) => CqlValue::Text(s),
+            driver::CqlValue::Timestamp(t) => CqlValue::Timestamp(Timestamp::try_from(t)?.0),
+            driver::CqlValue::Inet(addr) => CqlValue::Inet(addr),
+            driver::CqlValue::List(elements) => CqlValue::List(convert_all(elements)?),
+            driver::CqlValue::Map(entries) => CqlValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| Ok((key.try_into()?, value.try_into()?)))
+                    .collect::<Result<_, ValueTooBig>>()?,
+            ),
+            driver::CqlValue::Set(elements) => CqlValue::Set(convert_all(elements)?),
+            driver::CqlValue::UserDefinedType {
+                keyspace,
+                type_name,
+                fields,
+            } => {
+                let (names, values): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+                CqlValue::UserDefinedType {
+                    keyspace,
+                    type_name,
+                    fields: names.into_iter().zip(convert_fields(values)?).collect(),
+                }
+            }
+            driver::CqlValue::SmallInt(i) => CqlValue::SmallInt(i),
+            driver::CqlValue::TinyInt(i) => CqlValue::TinyInt(i),
+            driver::CqlValue::Time(t) => CqlValue::Time(Time::from(t).0),
+            driver::CqlValue::Timeuuid(u) => CqlValue::Timeuuid(u.into()),
+            driver::CqlValue::Tuple(fields) => CqlValue::Tuple(convert_fields(fields)?),
+            driver::CqlValue::Uuid(u) => CqlValue::Uuid(u),
+            driver::CqlValue::Varint(i) => {
+                CqlValue::Varint(BigInt::from_signed_bytes_be(i.as_signed_bytes_be_slice()))
+            }
+        })
+    }
+}
+
+impl From<Counter> for driver_value::Counter {
+    fn from(value: Counter) -> Self {
+        driver_value::Counter(value.0)
+    }
+}
+
+impl From<driver_value::Counter> for Counter {
+    fn from(value: driver_value::Counter) -> Self {
+        Counter(value.0)
+    }
+}
+
+impl From<Timestamp> for driver_value::CqlTimestamp {
+    fn from(value: Timestamp) -> Self {
+        driver_value::CqlTimestamp(value.0.num_milliseconds())
+    }
+}
+
+/// Fails only for `i64::MIN` milliseconds, which don't fit in a `chrono::Duration`.
+impl TryFrom<driver_value::CqlTimestamp> for Timestamp {
+    type Error = ValueTooBig;
+
+    fn try_from(value: driver_value::CqlTimestamp) -> Result<Self, ValueTooBig> {
+        chrono::Duration::try_milliseconds(value.0)
+            .map(Timestamp)
+            .ok_or(ValueTooBig)
+    }
+}
+
+/// Fails for times that don't fit in an `i64` of nanoseconds, which can't be serialized.
+impl TryFrom<Time> for driver_value::CqlTime {
+    type Error = ValueTooBig;
+
+    fn try_from(value: Time) -> Result<Self, ValueTooBig> {
+        let nanoseconds = value.0.num_nanoseconds().ok_or(ValueTooBig)?;
+        Ok(driver_value::CqlTime(nanoseconds))
+    }
+}
+
+impl From<driver_value::CqlTime> for Time {
+    fn from(value: driver_value::CqlTime) -> Self {
+        Time(chrono::Duration::nanoseconds(value.0))
+    }
+}
+
+impl From<CqlDuration> for driver_value::CqlDuration {
+    fn from(value: CqlDuration) -> Self {
+        driver_value::CqlDuration {
+            months: value.months,
+            days: value.days,
+            nanoseconds: value.nanoseconds,
+        }
+    }
+}
+
+impl From<driver_value::CqlDuration> for CqlDuration {
+    fn from(value: driver_value::CqlDuration) -> Self {
+        CqlDuration {
+            months: value.months,
+            days: value.days,
+            nanoseconds: value.nanoseconds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{decode_value, encode_value};
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn round_trip(typ: ColumnType, value: CqlValue) {
+        let driver_value = driver::CqlValue::try_from(value.clone()).unwrap();
+        assert_eq!(CqlValue::try_from(driver_value.clone()).unwrap(), value);
+        // both crates serialize the value in the same way
+        let mut buf = Vec::new();
+        driver_value::Value::serialize(&driver_value, &mut buf).unwrap();
+        assert_eq!(decode_value(&typ, &buf[4..]), Ok(value.clone()));
+        let mut buf = Vec::new();
+        encode_value(&value, &mut buf).unwrap();
+        assert_eq!(
+            driver::deser_cql_value(&typ.clone().into(), &mut &buf[4..]).unwrap(),
+            driver_value
+        );
+        assert!(matches!(
+            ColumnType::from(driver::ColumnType::from(typ.clone())),
+            converted if converted == typ
+        ));
+    }
+
+    #[test]
+    fn conversions() {
+        round_trip(
+            ColumnType::Decimal,
+            CqlValue::Decimal("-1.050".parse().unwrap()),
+        );
+        round_trip(ColumnType::Varint, CqlValue::Varint((-1_i64 << 40).into()));
+        round_trip(
+            ColumnType::Duration,
+            CqlValue::Duration(CqlDuration {
+                months: 1,
+                days: -2,
+                nanoseconds: 3,
+            }),
+        );
+        round_trip(ColumnType::Counter, CqlValue::Counter(Counter(5)));
+        round_trip(
+            ColumnType::Timestamp,
+            CqlValue::Timestamp(chrono::Duration::milliseconds(-5)),
+        );
+        round_trip(
+            ColumnType::Timeuuid,
+            CqlValue::Timeuuid("8e14e760-7fa8-11eb-bc66-000000000001".parse().unwrap()),
+        );
+        let udt = ColumnType::UserDefinedType {
+            type_name: "udt".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: vec![
+                ("a".to_string(), ColumnType::Inet),
+                (
+                    "b".to_string(),
+                    ColumnType::Map(
+                        Box::new(ColumnType::Text),
+                        Box::new(ColumnType::Tuple(vec![
+                            ColumnType::Varint,
+                            ColumnType::Time,
+                        ])),
+                    ),
+                ),
+            ],
+        };
+        let value = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "udt".to_string(),
+            fields: vec![
+                ("a".to_string(), Some(CqlValue::Inet([127, 0, 0, 1].into()))),
+                (
+                    "b".to_string(),
+                    Some(CqlValue::Map(vec![(
+                        CqlValue::Text("x".to_string()),
+                        CqlValue::Tuple(vec![
+                            Some(CqlValue::Varint(7.into())),
+                            Some(CqlValue::Time(chrono::Duration::nanoseconds(9))),
+                        ]),
+                    )])),
+                ),
+            ],
+        };
+        round_trip(udt, value);
+    }
+
+    #[test]
+    fn unrepresentable_values() {
+        let decimal = BigDecimal::new(1.into(), i64::from(i32::MAX) + 1);
+        assert_eq!(
+            driver::CqlValue::try_from(CqlValue::Decimal(decimal)),
+            Err(ValueTooBig)
+        );
+        assert_eq!(
+            driver::CqlValue::try_from(CqlValue::Time(chrono::Duration::MAX)),
+            Err(ValueTooBig)
+        );
+        assert_eq!(
+            CqlValue::try_from(driver::CqlValue::Timestamp(driver_value::CqlTimestamp(
+                i64::MIN
+            ))),
+            Err(ValueTooBig)
+        );
+    }
+}
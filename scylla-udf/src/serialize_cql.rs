@@ -0,0 +1,522 @@
+use crate::codec::{
+    encode_value, encoded_size, vint_encode, vint_size, SerializeCqlError, ValueTooBig,
+};
+use crate::cql_ord::compare_with_size;
+use crate::cql_value::{ColumnType, Counter, CqlDuration, CqlValue, Time, Timestamp};
+use crate::maybe_empty::MaybeEmpty;
+use crate::to_columntype::ToColumnType;
+use crate::to_wasmptr::check_result_size;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+#[cfg(feature = "serde")]
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+/// Conversion of a Rust type to serialized CQL values sent to Scylla.
+pub trait SerializeCql {
+    /// Appends the serialized value to the buffer, preceded by its size as a 4-byte
+    /// big-endian integer (-1 for null).
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError>;
+
+    /// The number of bytes appended by `serialize_cql`, including the size.
+    /// Used to allocate the buffer for a returned value up front, so that it's passed to Scylla without
+    /// copying. The buffer is grown or shrunk if the hint is wrong, so it doesn't have to be exact,
+    /// by default it's only the size. The hints of the types in this crate are exact, except for sets
+    /// and maps with elements that are equal in the CQL order, which are dropped when serializing them,
+    /// and for `Json` and `Blob`, whose encoded size isn't known without encoding them.
+    fn size_hint(&self) -> usize {
+        4
+    }
+}
+
+// This macro implements SerializeCql for a Rust type, given a function appending the serialized value
+// (without its size) to the buffer, and a function returning the size of the serialized value
+macro_rules! impl_serialize_cql {
+    ($rust_type:ty, $write:expr, $value_size:expr) => {
+        impl SerializeCql for $rust_type {
+            fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+                serialize_with_size(buf, |buf| $write(self, buf))
+            }
+
+            fn size_hint(&self) -> usize {
+                4 + $value_size(self)
+            }
+        }
+    };
+}
+
+// This macro implements SerializeCql for numeric types serialized as their big-endian bytes
+macro_rules! impl_serialize_cql_as_be_bytes {
+    ($($rust_type:ty),*) => {
+        $(impl_serialize_cql!(
+            $rust_type,
+            |value: &$rust_type, buf: &mut Vec<u8>| put(buf, &value.to_be_bytes()),
+            |_| core::mem::size_of::<$rust_type>()
+        );)*
+    };
+}
+
+fn put(buf: &mut Vec<u8>, bytes: &[u8]) -> Result<(), SerializeCqlError> {
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+// Varints take one more byte than needed to store the absolute value, for the sign bit, except for
+// negative powers of two filling whole bytes, e.g. -128 is 0x80
+fn varint_size(value: &num_bigint::BigInt) -> usize {
+    let bits = value.bits();
+    let min_negative = value.sign() == num_bigint::Sign::Minus
+        && bits % 8 == 0
+        && value.trailing_zeros() == Some(bits - 1);
+    bits as usize / 8 + 1 - min_negative as usize
+}
+
+// Days since -5877641-06-23, i.e. 2^31 days before unix epoch
+fn write_date(date: &chrono::NaiveDate, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+    let unix_epoch = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    let days: u32 = date
+        .signed_duration_since(unix_epoch)
+        .num_days()
+        .checked_add(1 << 31)
+        .and_then(|days| days.try_into().ok())
+        .ok_or(ValueTooBig)?;
+    put(buf, &days.to_be_bytes())
+}
+
+// The scale, followed by the unscaled value
+fn write_decimal(
+    value: &bigdecimal::BigDecimal,
+    buf: &mut Vec<u8>,
+) -> Result<(), SerializeCqlError> {
+    let (unscaled, scale) = value.as_bigint_and_exponent();
+    let scale: i32 = scale.try_into().map_err(|_| ValueTooBig)?;
+    put(buf, &scale.to_be_bytes())?;
+    put(buf, &unscaled.to_signed_bytes_be())
+}
+
+fn write_duration(duration: &CqlDuration, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+    vint_encode(duration.months.into(), buf);
+    vint_encode(duration.days.into(), buf);
+    vint_encode(duration.nanoseconds, buf);
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn write_inet(addr: &std::net::IpAddr, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+    match addr {
+        std::net::IpAddr::V4(addr) => put(buf, &addr.octets()),
+        std::net::IpAddr::V6(addr) => put(buf, &addr.octets()),
+    }
+}
+
+impl_serialize_cql_as_be_bytes!(i8, i16, i32, i64, f32, f64);
+impl_serialize_cql!(bool, |value: &bool, buf| put(buf, &[*value as u8]), |_| 1);
+impl_serialize_cql!(
+    Counter,
+    |value: &Counter, buf| put(buf, &value.0.to_be_bytes()),
+    |_| 8
+);
+impl_serialize_cql!(chrono::NaiveDate, write_date, |_| 4);
+impl_serialize_cql!(
+    bigdecimal::BigDecimal,
+    write_decimal,
+    |value: &bigdecimal::BigDecimal| 4 + varint_size(&value.as_bigint_and_exponent().0)
+);
+impl_serialize_cql!(CqlDuration, write_duration, |duration: &CqlDuration| {
+    vint_size(duration.months.into())
+        + vint_size(duration.days.into())
+        + vint_size(duration.nanoseconds)
+});
+impl_serialize_cql!(
+    Timestamp,
+    |value: &Timestamp, buf| put(buf, &value.0.num_milliseconds().to_be_bytes()),
+    |_| 8
+);
+#[cfg(feature = "std")]
+impl_serialize_cql!(
+    std::net::IpAddr,
+    write_inet,
+    |addr: &std::net::IpAddr| if addr.is_ipv4() { 4 } else { 16 }
+);
+impl_serialize_cql!(
+    Time,
+    |value: &Time, buf| put(
+        buf,
+        &value.0.num_nanoseconds().ok_or(ValueTooBig)?.to_be_bytes()
+    ),
+    |_| 8
+);
+impl_serialize_cql!(
+    uuid::Uuid,
+    |value: &uuid::Uuid, buf| put(buf, value.as_bytes()),
+    |_| 16
+);
+impl_serialize_cql!(
+    num_bigint::BigInt,
+    |value: &num_bigint::BigInt, buf| put(buf, &value.to_signed_bytes_be()),
+    varint_size
+);
+
+// The elements of sets and the keys of maps are written in the order of the CqlValue
+impl SerializeCql for CqlValue {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        encode_value(self, buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        encoded_size(self)
+    }
+}
+
+pub(crate) fn serialize_bytes(bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+    let size: i32 = bytes.len().try_into().map_err(|_| ValueTooBig)?;
+    check_result_size(buf.len(), 4 + bytes.len())?;
+    buf.extend_from_slice(&size.to_be_bytes());
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+// This macro implements SerializeCql for a Rust type that dereferences to a byte slice or a str
+macro_rules! impl_serialize_cql_as_bytes {
+    ($rust_type:ty) => {
+        impl SerializeCql for $rust_type {
+            fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+                serialize_bytes(AsRef::<[u8]>::as_ref(&**self), buf)
+            }
+
+            fn size_hint(&self) -> usize {
+                4 + AsRef::<[u8]>::as_ref(&**self).len()
+            }
+        }
+    };
+}
+
+impl_serialize_cql_as_bytes!(Vec<u8>);
+impl_serialize_cql_as_bytes!(String);
+impl_serialize_cql_as_bytes!(Box<[u8]>);
+impl_serialize_cql_as_bytes!(bytes::Bytes);
+impl_serialize_cql_as_bytes!(Box<str>);
+impl_serialize_cql_as_bytes!(Arc<str>);
+impl_serialize_cql_as_bytes!(Cow<'static, str>);
+
+#[cfg(feature = "std")]
+impl SerializeCql for std::net::Ipv4Addr {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        std::net::IpAddr::V4(*self).serialize_cql(buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        4 + 4
+    }
+}
+
+#[cfg(feature = "std")]
+impl SerializeCql for std::net::Ipv6Addr {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        std::net::IpAddr::V6(*self).serialize_cql(buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        4 + 16
+    }
+}
+
+impl<T: SerializeCql> SerializeCql for Option<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        match self {
+            Some(val) => val.serialize_cql(buf),
+            None => {
+                buf.extend_from_slice(&(-1_i32).to_be_bytes());
+                Ok(())
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        self.as_ref().map_or(4, T::size_hint)
+    }
+}
+
+// serde_json only fails for values that can't be represented in JSON, e.g. maps with non-string keys,
+// which is reported as SerializeCqlError::Encode. The text is written directly to the buffer, which grows
+// as it's written, so the value is only serialized once and the size hint is only its size.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> SerializeCql for crate::json::Json<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_with_size(buf, |buf| {
+            serde_json::to_writer(buf, &self.0)
+                .map_err(|e| SerializeCqlError::Encode(e.to_string()))
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, C: crate::blob::BlobCodec> SerializeCql for crate::blob::Blob<T, C> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_with_size(buf, |buf| {
+            C::encode(&self.0, buf).map_err(|e| SerializeCqlError::Encode(e.to_string()))
+        })
+    }
+}
+
+impl<T: SerializeCql> SerializeCql for crate::cql_ord::ByCqlOrder<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        self.0.serialize_cql(buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+}
+
+impl<T: SerializeCql> SerializeCql for MaybeEmpty<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        match self {
+            MaybeEmpty::Value(val) => val.serialize_cql(buf),
+            MaybeEmpty::Empty => {
+                buf.extend_from_slice(&0_i32.to_be_bytes());
+                Ok(())
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            MaybeEmpty::Value(val) => val.size_hint(),
+            MaybeEmpty::Empty => 4,
+        }
+    }
+}
+
+/// Appends the bytes written by `write` to the buffer, preceded by their size.
+/// Used for types made of multiple serialized values: collections, tuples and UDTs.
+pub fn serialize_with_size(
+    buf: &mut Vec<u8>,
+    write: impl FnOnce(&mut Vec<u8>) -> Result<(), SerializeCqlError>,
+) -> Result<(), SerializeCqlError> {
+    let size_pos = buf.len();
+    buf.extend_from_slice(&0_i32.to_be_bytes());
+    write(buf)?;
+    check_result_size(buf.len(), 0)?;
+    let size: i32 = (buf.len() - size_pos - 4)
+        .try_into()
+        .map_err(|_| ValueTooBig)?;
+    buf[size_pos..size_pos + 4].copy_from_slice(&size.to_be_bytes());
+    Ok(())
+}
+
+// The size and the element count, followed by the elements. Duplicate elements of sets and maps
+// are removed when they're serialized, so the size is overestimated if there are any.
+fn collection_size_hint(element_sizes: impl Iterator<Item = usize>) -> usize {
+    4 + 4 + element_sizes.sum::<usize>()
+}
+
+fn serialize_list<'a, T: SerializeCql + 'a>(
+    elements: impl ExactSizeIterator<Item = &'a T>,
+    buf: &mut Vec<u8>,
+) -> Result<(), SerializeCqlError> {
+    serialize_with_size(buf, |buf| {
+        let count: i32 = elements.len().try_into().map_err(|_| ValueTooBig)?;
+        buf.extend_from_slice(&count.to_be_bytes());
+        for element in elements {
+            element.serialize_cql(buf)?;
+        }
+        Ok(())
+    })
+}
+
+// Scylla expects the elements of sets and the keys of maps to be sorted in the order of their CQL type,
+// which may be different than the order of the Rust collection, and unique. The elements are serialized
+// directly to the buffer in the order of the Rust collection, which is often already the right one,
+// e.g. for a BTreeSet of integers or strings. Otherwise, the elements are copied once more to reorder
+// them, dropping the duplicates.
+pub(crate) fn serialize_sorted(
+    typ: &ColumnType,
+    buf: &mut Vec<u8>,
+    write_elements: impl FnOnce(
+        &mut Vec<u8>,
+        &mut Vec<(usize, usize, usize)>,
+    ) -> Result<(), SerializeCqlError>,
+) -> Result<(), SerializeCqlError> {
+    serialize_with_size(buf, |buf| {
+        let count_pos = buf.len();
+        buf.extend_from_slice(&0_i32.to_be_bytes());
+        let elements_pos = buf.len();
+        // (start, end of the key, end of the element)
+        let mut ranges = Vec::new();
+        write_elements(buf, &mut ranges)?;
+        let elements = buf.as_slice();
+        let key = |(start, key_end, _): &(usize, usize, usize)| &elements[*start..*key_end];
+        let sorted = ranges
+            .windows(2)
+            .all(|pair| compare_with_size(typ, key(&pair[0]), key(&pair[1])).is_lt());
+        if !sorted {
+            ranges.sort_by(|a, b| compare_with_size(typ, key(a), key(b)));
+            ranges.dedup_by(|a, b| compare_with_size(typ, key(a), key(b)).is_eq());
+            let elements = buf.split_off(elements_pos);
+            for (start, _, end) in &ranges {
+                buf.extend_from_slice(&elements[start - elements_pos..end - elements_pos]);
+            }
+        }
+        let count: i32 = ranges.len().try_into().map_err(|_| ValueTooBig)?;
+        buf[count_pos..count_pos + 4].copy_from_slice(&count.to_be_bytes());
+        Ok(())
+    })
+}
+
+fn serialize_set<'a, T: SerializeCql + ToColumnType + 'a>(
+    elements: impl Iterator<Item = &'a T>,
+    buf: &mut Vec<u8>,
+) -> Result<(), SerializeCqlError> {
+    serialize_sorted(&T::to_column_type(), buf, |buf, ranges| {
+        for element in elements {
+            let start = buf.len();
+            element.serialize_cql(buf)?;
+            ranges.push((start, buf.len(), buf.len()));
+        }
+        Ok(())
+    })
+}
+
+fn serialize_map<'a, K: SerializeCql + ToColumnType + 'a, V: SerializeCql + 'a>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+    buf: &mut Vec<u8>,
+) -> Result<(), SerializeCqlError> {
+    serialize_sorted(&K::to_column_type(), buf, |buf, ranges| {
+        for (key, value) in entries {
+            let start = buf.len();
+            key.serialize_cql(buf)?;
+            let key_end = buf.len();
+            value.serialize_cql(buf)?;
+            ranges.push((start, key_end, buf.len()));
+        }
+        Ok(())
+    })
+}
+
+impl<T: SerializeCql> SerializeCql for Vec<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_list(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(SerializeCql::size_hint))
+    }
+}
+
+impl<T: SerializeCql, const N: usize> SerializeCql for [T; N] {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_list(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(SerializeCql::size_hint))
+    }
+}
+
+impl<T: SerializeCql + ToColumnType> SerializeCql for BTreeSet<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_set(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(SerializeCql::size_hint))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: SerializeCql + ToColumnType> SerializeCql for HashSet<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_set(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(SerializeCql::size_hint))
+    }
+}
+
+impl<K: SerializeCql + ToColumnType, V: SerializeCql> SerializeCql for BTreeMap<K, V> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_map(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(|(k, v)| k.size_hint() + v.size_hint()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: SerializeCql + ToColumnType, V: SerializeCql> SerializeCql for HashMap<K, V> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_map(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(|(k, v)| k.size_hint() + v.size_hint()))
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> SerializeCql for smallvec::SmallVec<A>
+where
+    A::Item: SerializeCql,
+{
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_list(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(SerializeCql::size_hint))
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T: SerializeCql + ToColumnType> SerializeCql for indexmap::IndexSet<T> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_set(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(SerializeCql::size_hint))
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: SerializeCql + ToColumnType, V: SerializeCql> SerializeCql for indexmap::IndexMap<K, V> {
+    fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+        serialize_map(self.iter(), buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        collection_size_hint(self.iter().map(|(k, v)| k.size_hint() + v.size_hint()))
+    }
+}
+
+macro_rules! tuple_impls {
+    ( $( $idx:tt $types:ident )* ) => {
+        impl<$($types: SerializeCql),*> SerializeCql for ($($types,)*) {
+            #[allow(non_snake_case)]
+            fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+                let ($($types,)*) = self;
+                serialize_with_size(buf, |buf| {
+                    $($types.serialize_cql(buf)?;)*
+                    Ok(())
+                })
+            }
+
+            #[allow(non_snake_case)]
+            fn size_hint(&self) -> usize {
+                let ($($types,)*) = self;
+                4 $(+ $types.size_hint())*
+            }
+        }
+    };
+}
+
+for_each_tuple!(tuple_impls);
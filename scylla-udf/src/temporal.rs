@@ -0,0 +1,597 @@
+//! Arithmetic, parsing and formatting for the CQL temporal types and counters.
+//!
+//! The [`Add`] and [`Sub`] implementations are checked: they return `None` on overflow or when the
+//! result is not a valid value. They follow the semantics used by Scylla: a [`CqlDuration`] is
+//! added to a date or time by first adding the months (clamping the day to the length of the
+//! resulting month), then the days, and then the nanoseconds.
+//! ```
+//! use scylla_udf::temporal::parse_duration;
+//! use scylla_udf::Timestamp;
+//!
+//! // 2024-01-31T00:00:00Z
+//! let ts = Timestamp(chrono::Duration::milliseconds(1706659200000));
+//! let later = (ts + parse_duration("1mo1h").unwrap()).unwrap();
+//! // 2024-02-29T01:00:00Z
+//! assert_eq!(later, Timestamp(chrono::Duration::milliseconds(1709168400000)));
+//! ```
+
+use crate::cql_value::{Counter, CqlDuration, Time, Timestamp};
+use alloc::format;
+use alloc::string::{String, ToString};
+use chrono::{DateTime, Months, NaiveDate, NaiveDateTime, Utc};
+use core::fmt;
+use core::ops::{Add, Sub};
+
+const NANOS_PER_MICRO: i64 = 1_000;
+const NANOS_PER_MILLI: i64 = 1_000_000;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const NANOS_PER_MINUTE: i64 = 60 * NANOS_PER_SECOND;
+const NANOS_PER_HOUR: i64 = 60 * NANOS_PER_MINUTE;
+const NANOS_PER_DAY: i64 = 24 * NANOS_PER_HOUR;
+const MILLIS_PER_DAY: i64 = NANOS_PER_DAY / NANOS_PER_MILLI;
+
+/// Creates a duration, checking that all of its components have the same sign, as required by CQL.
+pub fn new_duration(months: i32, days: i32, nanoseconds: i64) -> Option<CqlDuration> {
+    let duration = CqlDuration {
+        months,
+        days,
+        nanoseconds,
+    };
+    if is_valid_duration(&duration) {
+        Some(duration)
+    } else {
+        None
+    }
+}
+
+/// Checks whether all components of the duration have the same sign, as required by CQL.
+pub fn is_valid_duration(duration: &CqlDuration) -> bool {
+    let (months, days, nanos) = (duration.months, duration.days, duration.nanoseconds);
+    (months >= 0 && days >= 0 && nanos >= 0) || (months <= 0 && days <= 0 && nanos <= 0)
+}
+
+fn negate_duration(duration: CqlDuration) -> Option<CqlDuration> {
+    Some(CqlDuration {
+        months: duration.months.checked_neg()?,
+        days: duration.days.checked_neg()?,
+        nanoseconds: duration.nanoseconds.checked_neg()?,
+    })
+}
+
+fn add_months(datetime: NaiveDateTime, months: i32) -> Option<NaiveDateTime> {
+    if months >= 0 {
+        datetime.checked_add_months(Months::new(months as u32))
+    } else {
+        datetime.checked_sub_months(Months::new(months.unsigned_abs()))
+    }
+}
+
+impl Add for Counter {
+    type Output = Option<Counter>;
+    fn add(self, rhs: Counter) -> Option<Counter> {
+        self.0.checked_add(rhs.0).map(Counter)
+    }
+}
+
+impl Sub for Counter {
+    type Output = Option<Counter>;
+    fn sub(self, rhs: Counter) -> Option<Counter> {
+        self.0.checked_sub(rhs.0).map(Counter)
+    }
+}
+
+// Durations are added componentwise, the result must still have components of the same sign
+impl Add for CqlDuration {
+    type Output = Option<CqlDuration>;
+    fn add(self, rhs: CqlDuration) -> Option<CqlDuration> {
+        new_duration(
+            self.months.checked_add(rhs.months)?,
+            self.days.checked_add(rhs.days)?,
+            self.nanoseconds.checked_add(rhs.nanoseconds)?,
+        )
+    }
+}
+
+impl Sub for CqlDuration {
+    type Output = Option<CqlDuration>;
+    fn sub(self, rhs: CqlDuration) -> Option<CqlDuration> {
+        self.add(negate_duration(rhs)?)
+    }
+}
+
+// The nanoseconds are truncated to milliseconds, like in Scylla
+impl Add<CqlDuration> for Timestamp {
+    type Output = Option<Timestamp>;
+    fn add(self, rhs: CqlDuration) -> Option<Timestamp> {
+        let millis = self.0.num_milliseconds();
+        let rhs_millis = rhs.nanoseconds / NANOS_PER_MILLI;
+        let result = if rhs.months == 0 {
+            (rhs.days as i64 * MILLIS_PER_DAY)
+                .checked_add(rhs_millis)?
+                .checked_add(millis)?
+        } else {
+            let datetime = DateTime::<Utc>::from_timestamp_millis(millis)?.naive_utc();
+            add_months(datetime, rhs.months)?
+                .checked_add_signed(chrono::Duration::days(rhs.days as i64))?
+                .checked_add_signed(chrono::Duration::milliseconds(rhs_millis))?
+                .and_utc()
+                .timestamp_millis()
+        };
+        // `i64::MIN` milliseconds don't fit in a `chrono::Duration`
+        chrono::Duration::try_milliseconds(result).map(Timestamp)
+    }
+}
+
+impl Sub<CqlDuration> for Timestamp {
+    type Output = Option<Timestamp>;
+    fn sub(self, rhs: CqlDuration) -> Option<Timestamp> {
+        self.add(negate_duration(rhs)?)
+    }
+}
+
+// Only durations with a day precision can be added to dates
+impl Add<CqlDuration> for NaiveDate {
+    type Output = Option<NaiveDate>;
+    fn add(self, rhs: CqlDuration) -> Option<NaiveDate> {
+        if rhs.nanoseconds != 0 {
+            return None;
+        }
+        add_months(self.and_hms_opt(0, 0, 0)?, rhs.months)?
+            .date()
+            .checked_add_signed(chrono::Duration::days(rhs.days as i64))
+    }
+}
+
+impl Sub<CqlDuration> for NaiveDate {
+    type Output = Option<NaiveDate>;
+    fn sub(self, rhs: CqlDuration) -> Option<NaiveDate> {
+        self.add(negate_duration(rhs)?)
+    }
+}
+
+// Only durations shorter than a day can be added to times, and the result must be within the same day
+impl Add<CqlDuration> for Time {
+    type Output = Option<Time>;
+    fn add(self, rhs: CqlDuration) -> Option<Time> {
+        if rhs.months != 0 || rhs.days != 0 {
+            return None;
+        }
+        let nanos = self.0.num_nanoseconds()?.checked_add(rhs.nanoseconds)?;
+        if !(0..NANOS_PER_DAY).contains(&nanos) {
+            return None;
+        }
+        Some(Time(chrono::Duration::nanoseconds(nanos)))
+    }
+}
+
+impl Sub<CqlDuration> for Time {
+    type Output = Option<Time>;
+    fn sub(self, rhs: CqlDuration) -> Option<Time> {
+        self.add(negate_duration(rhs)?)
+    }
+}
+
+/// The error returned when parsing a duration literal fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseDurationError {
+    msg: String,
+}
+
+impl ParseDurationError {
+    fn new(literal: &str, reason: &str) -> ParseDurationError {
+        ParseDurationError {
+            msg: format!("Invalid duration literal {:?}: {}", literal, reason),
+        }
+    }
+}
+
+impl fmt::Display for ParseDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseDurationError {}
+
+// Accumulates the components of a parsed duration, checking for overflows
+#[derive(Default)]
+struct DurationBuilder {
+    months: i64,
+    days: i64,
+    nanos: i64,
+}
+
+impl DurationBuilder {
+    fn add(&mut self, value: i64, unit: &str) -> Option<()> {
+        let (component, multiplier) = match unit {
+            "y" => (&mut self.months, 12),
+            "mo" => (&mut self.months, 1),
+            "w" => (&mut self.days, 7),
+            "d" => (&mut self.days, 1),
+            "h" => (&mut self.nanos, NANOS_PER_HOUR),
+            "m" => (&mut self.nanos, NANOS_PER_MINUTE),
+            "s" => (&mut self.nanos, NANOS_PER_SECOND),
+            "ms" => (&mut self.nanos, NANOS_PER_MILLI),
+            "us" | "µs" => (&mut self.nanos, NANOS_PER_MICRO),
+            "ns" => (&mut self.nanos, 1),
+            _ => return None,
+        };
+        *component = component.checked_add(value.checked_mul(multiplier)?)?;
+        Some(())
+    }
+
+    fn build(self, negative: bool) -> Option<CqlDuration> {
+        let sign = if negative { -1 } else { 1 };
+        Some(CqlDuration {
+            months: i32::try_from(self.months * sign).ok()?,
+            days: i32::try_from(self.days * sign).ok()?,
+            nanoseconds: self.nanos * sign,
+        })
+    }
+}
+
+// Units of the standard format, in the order in which they must appear
+const STANDARD_UNITS: [&str; 11] = ["y", "mo", "w", "d", "h", "m", "s", "ms", "us", "µs", "ns"];
+
+fn parse_standard_format(source: &str, builder: &mut DurationBuilder) -> Result<(), &'static str> {
+    if source.is_empty() {
+        return Err("expected at least one quantity and unit, e.g. 1h30m");
+    }
+    let mut rest = source;
+    let mut last_unit_idx = None;
+    while !rest.is_empty() {
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits_end == 0 {
+            return Err("expected a number");
+        }
+        let value: i64 = rest[..digits_end].parse().map_err(|_| "number too large")?;
+        rest = &rest[digits_end..];
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = rest[..unit_end].to_lowercase();
+        rest = &rest[unit_end..];
+        let unit_idx = STANDARD_UNITS
+            .iter()
+            .position(|u| *u == unit)
+            .ok_or("unknown unit, expected one of y, mo, w, d, h, m, s, ms, us, µs, ns")?;
+        if last_unit_idx.map_or(false, |last| last >= unit_idx) {
+            return Err("units must be given at most once, from the largest to the smallest");
+        }
+        last_unit_idx = Some(unit_idx);
+        builder.add(value, &unit).ok_or("value out of range")?;
+    }
+    Ok(())
+}
+
+// Parses a number followed by one of the designators, in order, each designator being optional,
+// and returns the number of parsed designators
+fn parse_iso8601_designators(
+    mut source: &str,
+    designators: &[&str],
+    builder: &mut DurationBuilder,
+) -> Result<usize, &'static str> {
+    let mut parsed = 0;
+    for (designator, unit) in designators.iter().map(|d| d.split_at(1)) {
+        let digits_end = source
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(source.len());
+        if digits_end == 0 || !source[digits_end..].starts_with(designator) {
+            continue;
+        }
+        let value: i64 = source[..digits_end]
+            .parse()
+            .map_err(|_| "number too large")?;
+        builder.add(value, unit).ok_or("value out of range")?;
+        source = &source[digits_end + 1..];
+        parsed += 1;
+    }
+    if !source.is_empty() {
+        return Err("invalid ISO 8601 duration, expected e.g. P1Y2M3DT4H5M6S");
+    }
+    Ok(parsed)
+}
+
+fn parse_iso8601_format(source: &str, builder: &mut DurationBuilder) -> Result<(), &'static str> {
+    let (date, time) = match source.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (source, None),
+    };
+    let date_designators = parse_iso8601_designators(date, &["Yy", "Mmo", "Dd"], builder)?;
+    // the time part, if present, must have at least one designator, like the whole duration
+    match time {
+        Some(time) if parse_iso8601_designators(time, &["Hh", "Mm", "Ss"], builder)? == 0 => {
+            Err("expected at least one designator after T, e.g. PT1H")
+        }
+        None if date_designators == 0 => Err("expected at least one designator, e.g. P1D"),
+        _ => Ok(()),
+    }
+}
+
+fn parse_iso8601_alternative_format(
+    source: &str,
+    builder: &mut DurationBuilder,
+) -> Result<(), &'static str> {
+    const FORMAT_ERROR: &str = "invalid ISO 8601 duration, expected e.g. P0001-02-03T04:05:06";
+    let bytes = source.as_bytes();
+    if bytes.len() != 19
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return Err(FORMAT_ERROR);
+    }
+    for (start, len, unit) in [
+        (0, 4, "y"),
+        (5, 2, "mo"),
+        (8, 2, "d"),
+        (11, 2, "h"),
+        (14, 2, "m"),
+        (17, 2, "s"),
+    ] {
+        let digits = &source[start..start + len];
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(FORMAT_ERROR);
+        }
+        // at most 4 digits, so the parsing can't fail
+        builder
+            .add(digits.parse().unwrap(), unit)
+            .ok_or("value out of range")?;
+    }
+    Ok(())
+}
+
+/// Parses a duration literal, in any of the formats accepted by CQL:
+/// - the standard format, e.g. `1h30m` or `-2d12h` (units: `y`, `mo`, `w`, `d`, `h`, `m`, `s`, `ms`, `us` or `µs`, `ns`)
+/// - the ISO 8601 format, e.g. `P1DT2H`
+/// - the ISO 8601 week format, e.g. `P2W`
+/// - the ISO 8601 alternative format, e.g. `P0001-02-03T04:05:06`
+pub fn parse_duration(literal: &str) -> Result<CqlDuration, ParseDurationError> {
+    let (negative, source) = match literal.strip_prefix('-') {
+        Some(source) => (true, source),
+        None => (false, literal),
+    };
+    let mut builder = DurationBuilder::default();
+    let result = match source.strip_prefix('P') {
+        Some(iso) if iso.ends_with('W') => {
+            parse_iso8601_designators(iso, &["Ww"], &mut builder).map(|_| ())
+        }
+        Some(iso) if iso.contains('-') => parse_iso8601_alternative_format(iso, &mut builder),
+        Some(iso) => parse_iso8601_format(iso, &mut builder),
+        None => parse_standard_format(source, &mut builder),
+    };
+    result.map_err(|reason| ParseDurationError::new(literal, reason))?;
+    builder
+        .build(negative)
+        .ok_or_else(|| ParseDurationError::new(literal, "value out of range"))
+}
+
+/// Formats the duration in the standard CQL duration literal format, e.g. `1y2mo3d4h5m6s`,
+/// in the same way as Scylla does. A zero duration is formatted as `0s`.
+/// Returns `None` if the components of the duration have different signs.
+pub fn format_duration(duration: &CqlDuration) -> Option<String> {
+    if !is_valid_duration(duration) {
+        return None;
+    }
+    let mut result = String::new();
+    let mut append = |value: u64, unit_size: u64, unit: &str| -> u64 {
+        if value < unit_size {
+            return value;
+        }
+        result.push_str(&(value / unit_size).to_string());
+        result.push_str(unit);
+        value % unit_size
+    };
+    let months = append(duration.months.unsigned_abs() as u64, 12, "y");
+    append(months, 1, "mo");
+    append(duration.days.unsigned_abs() as u64, 1, "d");
+    let mut nanos = duration.nanoseconds.unsigned_abs();
+    for (unit_size, unit) in [
+        (NANOS_PER_HOUR, "h"),
+        (NANOS_PER_MINUTE, "m"),
+        (NANOS_PER_SECOND, "s"),
+        (NANOS_PER_MILLI, "ms"),
+        (NANOS_PER_MICRO, "us"),
+        (1, "ns"),
+    ] {
+        nanos = append(nanos, unit_size as u64, unit);
+    }
+    if result.is_empty() {
+        return Some(String::from("0s"));
+    }
+    if duration.months < 0 || duration.days < 0 || duration.nanoseconds < 0 {
+        result.insert(0, '-');
+    }
+    Some(result)
+}
+
+/// Converts a timestamp to a UTC date and time.
+/// Returns `None` if the timestamp is out of the range supported by `chrono`.
+pub fn timestamp_to_datetime(timestamp: Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::<Utc>::from_timestamp_millis(timestamp.0.num_milliseconds())
+}
+
+/// Converts a UTC date and time to a timestamp, truncating it to milliseconds.
+pub fn datetime_to_timestamp(datetime: DateTime<Utc>) -> Timestamp {
+    Timestamp(chrono::Duration::milliseconds(datetime.timestamp_millis()))
+}
+
+/// Formats the timestamp in the ISO 8601 format with a millisecond precision, e.g. `2024-02-29T01:00:00.000Z`.
+/// Returns `None` if the timestamp is out of the range supported by `chrono`.
+pub fn format_timestamp(timestamp: Timestamp) -> Option<String> {
+    Some(
+        timestamp_to_datetime(timestamp)?
+            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> Timestamp {
+        datetime_to_timestamp(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc))
+    }
+    fn dur(s: &str) -> CqlDuration {
+        parse_duration(s).unwrap()
+    }
+
+    #[test]
+    fn parse_standard() {
+        assert_eq!(
+            dur("1h30m"),
+            CqlDuration {
+                months: 0,
+                days: 0,
+                nanoseconds: 90 * NANOS_PER_MINUTE
+            }
+        );
+        assert_eq!(
+            dur("1y2mo3w4d"),
+            CqlDuration {
+                months: 14,
+                days: 25,
+                nanoseconds: 0
+            }
+        );
+        assert_eq!(
+            dur("-1d1ms"),
+            CqlDuration {
+                months: 0,
+                days: -1,
+                nanoseconds: -NANOS_PER_MILLI
+            }
+        );
+        assert_eq!(
+            dur("2US3Ns"),
+            CqlDuration {
+                months: 0,
+                days: 0,
+                nanoseconds: 2003
+            }
+        );
+        assert_eq!(
+            dur("1µs"),
+            CqlDuration {
+                months: 0,
+                days: 0,
+                nanoseconds: 1000
+            }
+        );
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("1h1d").is_err());
+        assert!(parse_duration("1h1h").is_err());
+        assert!(parse_duration("1x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("3000000000d").is_err());
+    }
+    #[test]
+    fn parse_iso8601() {
+        assert_eq!(
+            dur("P1DT2H"),
+            CqlDuration {
+                months: 0,
+                days: 1,
+                nanoseconds: 2 * NANOS_PER_HOUR
+            }
+        );
+        assert_eq!(
+            dur("P1Y2M"),
+            CqlDuration {
+                months: 14,
+                days: 0,
+                nanoseconds: 0
+            }
+        );
+        assert_eq!(
+            dur("PT2M"),
+            CqlDuration {
+                months: 0,
+                days: 0,
+                nanoseconds: 2 * NANOS_PER_MINUTE
+            }
+        );
+        assert_eq!(
+            dur("-P2W"),
+            CqlDuration {
+                months: 0,
+                days: -14,
+                nanoseconds: 0
+            }
+        );
+        assert_eq!(
+            dur("P0001-02-03T04:05:06"),
+            CqlDuration {
+                months: 14,
+                days: 3,
+                nanoseconds: 4 * NANOS_PER_HOUR + 5 * NANOS_PER_MINUTE + 6 * NANOS_PER_SECOND
+            }
+        );
+        assert!(parse_duration("P1H").is_err());
+        assert!(parse_duration("P1D2Y").is_err());
+        assert!(parse_duration("P0001-02-03").is_err());
+        assert!(parse_duration("P").is_err());
+        assert!(parse_duration("PT").is_err());
+        assert!(parse_duration("P1DT").is_err());
+        assert!(parse_duration("-P").is_err());
+    }
+    #[test]
+    fn format() {
+        for literal in ["1y2mo3d4h5m6s7ms8us9ns", "-14mo", "1d", "0s", "-1h1ns"] {
+            assert_eq!(
+                format_duration(&dur(literal)).unwrap(),
+                literal.replace("14mo", "1y2mo")
+            );
+        }
+        assert_eq!(
+            format_duration(&CqlDuration {
+                months: 1,
+                days: -1,
+                nanoseconds: 0
+            }),
+            None
+        );
+    }
+    #[test]
+    fn timestamp_arithmetic() {
+        let jan31 = ts("2023-01-31T10:00:00Z");
+        assert_eq!(jan31 + dur("1mo"), Some(ts("2023-02-28T10:00:00Z")));
+        assert_eq!(jan31 + dur("1mo1d"), Some(ts("2023-03-01T10:00:00Z")));
+        assert_eq!(jan31 + dur("2h1us"), Some(ts("2023-01-31T12:00:00Z")));
+        assert_eq!(jan31 - dur("1y1d"), Some(ts("2022-01-30T10:00:00Z")));
+        assert_eq!(format_timestamp(jan31).unwrap(), "2023-01-31T10:00:00.000Z");
+        let min = Timestamp(chrono::Duration::milliseconds(-i64::MAX));
+        assert_eq!(min - dur("1ms"), None);
+        assert_eq!(
+            min + dur("1ms"),
+            Some(Timestamp(min.0 + chrono::Duration::milliseconds(1)))
+        );
+    }
+    #[test]
+    fn date_and_time_arithmetic() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert_eq!(date - dur("1mo"), NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert_eq!(date + dur("1w"), NaiveDate::from_ymd_opt(2024, 4, 7));
+        assert_eq!(date + dur("1h"), None);
+        let time = Time(chrono::Duration::hours(23));
+        assert_eq!(
+            time + dur("30m"),
+            Some(Time(chrono::Duration::minutes(23 * 60 + 30)))
+        );
+        assert_eq!(time + dur("1h"), None);
+        assert_eq!(time + dur("1d"), None);
+    }
+    #[test]
+    fn duration_and_counter_arithmetic() {
+        assert_eq!(dur("1mo2d") + dur("1h"), Some(dur("1mo2d1h")));
+        assert_eq!(dur("1mo") - dur("1d"), None);
+        assert_eq!(Counter(5) - Counter(7), Some(Counter(-2)));
+        assert_eq!(Counter(i64::MAX) + Counter(1), None);
+    }
+}
@@ -1,8 +1,18 @@
-pub use scylla_cql::frame::response::result::ColumnType;
-use scylla_cql::frame::value::{Counter, CqlDuration, Time, Timestamp};
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::cql_value::ColumnType;
+use crate::cql_value::{Counter, CqlDuration, Time, Timestamp};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
 
+/// The CQL type a Rust type is mapped to.
 pub trait ToColumnType {
+    /// Returns the CQL type. UDT names and keyspaces are not checked, so they may be left empty.
     fn to_column_type() -> ColumnType;
 }
 
@@ -19,6 +29,8 @@ macro_rules! impl_to_col_type {
 
 impl_to_col_type!(bool, ColumnType::Boolean);
 impl_to_col_type!(Vec<u8>, ColumnType::Blob);
+impl_to_col_type!(Box<[u8]>, ColumnType::Blob);
+impl_to_col_type!(bytes::Bytes, ColumnType::Blob);
 impl_to_col_type!(Counter, ColumnType::Counter);
 impl_to_col_type!(chrono::NaiveDate, ColumnType::Date);
 impl_to_col_type!(bigdecimal::BigDecimal, ColumnType::Decimal);
@@ -28,27 +40,69 @@ impl_to_col_type!(f32, ColumnType::Float);
 impl_to_col_type!(i32, ColumnType::Int);
 impl_to_col_type!(i64, ColumnType::BigInt);
 impl_to_col_type!(String, ColumnType::Text);
+impl_to_col_type!(Box<str>, ColumnType::Text);
+impl_to_col_type!(Arc<str>, ColumnType::Text);
+impl_to_col_type!(Cow<'static, str>, ColumnType::Text);
 impl_to_col_type!(Timestamp, ColumnType::Timestamp);
+#[cfg(feature = "std")]
 impl_to_col_type!(std::net::IpAddr, ColumnType::Inet);
+#[cfg(feature = "std")]
+impl_to_col_type!(std::net::Ipv4Addr, ColumnType::Inet);
+#[cfg(feature = "std")]
+impl_to_col_type!(std::net::Ipv6Addr, ColumnType::Inet);
 impl_to_col_type!(i16, ColumnType::SmallInt);
 impl_to_col_type!(i8, ColumnType::TinyInt);
 impl_to_col_type!(Time, ColumnType::Time);
 impl_to_col_type!(uuid::Uuid, ColumnType::Uuid);
 impl_to_col_type!(num_bigint::BigInt, ColumnType::Varint);
 
+#[cfg(feature = "serde")]
+impl<T, C> ToColumnType for crate::blob::Blob<T, C> {
+    fn to_column_type() -> ColumnType {
+        ColumnType::Blob
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> ToColumnType for crate::json::Json<T> {
+    fn to_column_type() -> ColumnType {
+        ColumnType::Text
+    }
+}
+
+impl<T: ToColumnType> ToColumnType for crate::cql_ord::ByCqlOrder<T> {
+    fn to_column_type() -> ColumnType {
+        T::to_column_type()
+    }
+}
+
+impl<T: ToColumnType> ToColumnType for crate::maybe_empty::MaybeEmpty<T> {
+    fn to_column_type() -> ColumnType {
+        T::to_column_type()
+    }
+}
+
 impl<T: ToColumnType> ToColumnType for Vec<T> {
     fn to_column_type() -> ColumnType {
         ColumnType::List(Box::new(T::to_column_type()))
     }
 }
 
+// Fixed-size arrays are mapped to (frozen) lists
+impl<T: ToColumnType, const N: usize> ToColumnType for [T; N] {
+    fn to_column_type() -> ColumnType {
+        ColumnType::List(Box::new(T::to_column_type()))
+    }
+}
+
 impl<K: ToColumnType, V: ToColumnType> ToColumnType for BTreeMap<K, V> {
     fn to_column_type() -> ColumnType {
         ColumnType::Map(Box::new(K::to_column_type()), Box::new(V::to_column_type()))
     }
 }
 
-impl<K: ToColumnType + Eq + std::hash::Hash, V: ToColumnType> ToColumnType for HashMap<K, V> {
+#[cfg(feature = "std")]
+impl<K: ToColumnType + Eq + core::hash::Hash, V: ToColumnType> ToColumnType for HashMap<K, V> {
     fn to_column_type() -> ColumnType {
         ColumnType::Map(Box::new(K::to_column_type()), Box::new(V::to_column_type()))
     }
@@ -60,14 +114,39 @@ impl<T: ToColumnType> ToColumnType for BTreeSet<T> {
     }
 }
 
-impl<T: ToColumnType + Eq + std::hash::Hash> ToColumnType for HashSet<T> {
+#[cfg(feature = "std")]
+impl<T: ToColumnType + Eq + core::hash::Hash> ToColumnType for HashSet<T> {
+    fn to_column_type() -> ColumnType {
+        ColumnType::Set(Box::new(T::to_column_type()))
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: ToColumnType, V: ToColumnType> ToColumnType for indexmap::IndexMap<K, V> {
+    fn to_column_type() -> ColumnType {
+        ColumnType::Map(Box::new(K::to_column_type()), Box::new(V::to_column_type()))
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T: ToColumnType> ToColumnType for indexmap::IndexSet<T> {
     fn to_column_type() -> ColumnType {
         ColumnType::Set(Box::new(T::to_column_type()))
     }
 }
 
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> ToColumnType for smallvec::SmallVec<A>
+where
+    A::Item: ToColumnType,
+{
+    fn to_column_type() -> ColumnType {
+        ColumnType::List(Box::new(A::Item::to_column_type()))
+    }
+}
+
 macro_rules! tuple_impls {
-    ( $( $types:ident )* ) => {
+    ( $( $idx:tt $types:ident )* ) => {
         impl<$($types: ToColumnType),*> ToColumnType for ($($types,)*) {
             fn to_column_type() -> ColumnType {
                 ColumnType::Tuple(vec![$($types::to_column_type()),*])
@@ -76,18 +155,7 @@ macro_rules! tuple_impls {
     };
 }
 
-tuple_impls! { A }
-tuple_impls! { A B }
-tuple_impls! { A B C }
-tuple_impls! { A B C D }
-tuple_impls! { A B C D E }
-tuple_impls! { A B C D E F }
-tuple_impls! { A B C D E F G }
-tuple_impls! { A B C D E F G H }
-tuple_impls! { A B C D E F G H I }
-tuple_impls! { A B C D E F G H I J }
-tuple_impls! { A B C D E F G H I J K }
-tuple_impls! { A B C D E F G H I J K L }
+for_each_tuple!(tuple_impls);
 
 impl<T: ToColumnType> ToColumnType for Option<T> {
     fn to_column_type() -> ColumnType {
@@ -1,24 +1,273 @@
+use crate::abi_exports::HEADER_SIZE;
+use crate::codec::{SerializeCqlError, ValueTooBig};
+use crate::conversion_error::{ConversionError, ConversionErrorKind};
+use crate::serialize_cql::SerializeCql;
 use crate::wasmptr::WasmPtr;
-use core::convert::TryInto;
-use scylla_cql::frame::value::Value;
+use alloc::vec::Vec;
 
+/// The maximum size of a CQL value, which is the default limit of the size of a value returned from a UDF.
+pub const DEFAULT_MAX_RESULT_SIZE: usize = i32::MAX as usize;
+
+// Tests of UDFs on the host run in parallel threads, so each thread gets its own limit
+#[cfg(any(test, all(feature = "std", not(target_arch = "wasm32"))))]
+mod limit {
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static MAX_RESULT_SIZE: Cell<usize> = const { Cell::new(super::DEFAULT_MAX_RESULT_SIZE) };
+        static SERIALIZING: Cell<usize> = const { Cell::new(usize::MAX) };
+    }
+
+    pub(super) fn get() -> usize {
+        MAX_RESULT_SIZE.with(Cell::get)
+    }
+
+    pub(super) fn replace(max_size: usize) -> usize {
+        MAX_RESULT_SIZE.with(|limit| limit.replace(max_size))
+    }
+
+    pub(super) fn serializing() -> usize {
+        SERIALIZING.with(Cell::get)
+    }
+
+    pub(super) fn replace_serializing(max_size: usize) -> usize {
+        SERIALIZING.with(|limit| limit.replace(max_size))
+    }
+}
+
+#[cfg(not(any(test, all(feature = "std", not(target_arch = "wasm32")))))]
+mod limit {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static MAX_RESULT_SIZE: AtomicUsize = AtomicUsize::new(super::DEFAULT_MAX_RESULT_SIZE);
+    static SERIALIZING: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+    pub(super) fn get() -> usize {
+        MAX_RESULT_SIZE.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn replace(max_size: usize) -> usize {
+        MAX_RESULT_SIZE.swap(max_size, Ordering::Relaxed)
+    }
+
+    pub(super) fn serializing() -> usize {
+        SERIALIZING.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn replace_serializing(max_size: usize) -> usize {
+        SERIALIZING.swap(max_size, Ordering::Relaxed)
+    }
+}
+
+/// Conversion to a `WasmPtr`, implemented for all types implementing `SerializeCql`.
 pub trait ToWasmPtr {
-    fn to_wasmptr(&self) -> WasmPtr;
-}
-
-impl<T: Value> ToWasmPtr for T {
-    fn to_wasmptr(&self) -> WasmPtr {
-        let mut bytes = Vec::<u8>::new();
-        self.serialize(&mut bytes).expect("Error serializing value");
-        let size = u32::from_be_bytes(bytes[..4].try_into().expect("slice with incorrect length"));
-        if size == u32::MAX {
-            return WasmPtr::null();
-        }
-        let mut dest = WasmPtr::with_size(size).expect("Failed to allocate memory");
-        let dest_slice = dest
-            .as_mut_slice()
-            .expect("WasmPtr::as_mut_slice returned None");
-        dest_slice.copy_from_slice(&bytes[4..]);
-        dest
+    fn to_wasmptr(&self) -> Result<WasmPtr, ConversionError>;
+}
+
+impl<T: SerializeCql> ToWasmPtr for T {
+    fn to_wasmptr(&self) -> Result<WasmPtr, ConversionError> {
+        serialize_to_wasmptr::<T>(self.size_hint(), |buf| self.serialize_cql(buf))
+    }
+}
+
+// Serializes a value of type T with `serialize` into a buffer allocated according to the size hint,
+// which is then passed to Scylla as it is, so it's allocated outside of the arena. The maximum result
+// size is enforced while the value is serialized, and checked again afterwards for the types that
+// don't write their values with `serialize_with_size` or `serialize_bytes`.
+pub(crate) fn serialize_to_wasmptr<T>(
+    size_hint: usize,
+    serialize: impl FnOnce(&mut Vec<u8>) -> Result<(), SerializeCqlError>,
+) -> Result<WasmPtr, ConversionError> {
+    let max_size = limit::get();
+    crate::arena::outside_arena(|| {
+        let capacity = size_hint.min(max_size.saturating_add(HEADER_SIZE));
+        let mut bytes = Vec::<u8>::with_capacity(capacity);
+        let _serializing = Serializing::start(max_size);
+        serialize(&mut bytes).map_err(|e| ConversionError::new::<T>(serialize_error_kind(e)))?;
+        check_serialized(&bytes, max_size).map_err(|kind| ConversionError::new::<T>(kind))?;
+        Ok(WasmPtr::from_serialized(bytes))
+    })
+}
+
+// Sets the limit checked by `check_result_size` while a value is serialized by `serialize_to_wasmptr`
+struct Serializing {
+    previous: usize,
+}
+
+impl Serializing {
+    fn start(max_size: usize) -> Serializing {
+        Serializing {
+            previous: limit::replace_serializing(max_size),
+        }
+    }
+}
+
+impl Drop for Serializing {
+    fn drop(&mut self) {
+        limit::replace_serializing(self.previous);
+    }
+}
+
+// Fails if the buffer of a value serialized by `serialize_to_wasmptr` would exceed the maximum result
+// size after writing `additional` more bytes to it. Called by `serialize_with_size` and `serialize_bytes`,
+// so that serializing a value that is too big stops before the whole value is written. Duplicate elements
+// of sets and maps are counted too, until they're dropped when the set or map is complete.
+pub(crate) fn check_result_size(
+    buf_len: usize,
+    additional: usize,
+) -> Result<(), SerializeCqlError> {
+    let max_size = limit::serializing();
+    let size = buf_len
+        .saturating_add(additional)
+        .saturating_sub(HEADER_SIZE);
+    if size > max_size {
+        return Err(SerializeCqlError::ResultTooBig { size, max_size });
+    }
+    Ok(())
+}
+
+fn serialize_error_kind(error: SerializeCqlError) -> ConversionErrorKind {
+    match error {
+        SerializeCqlError::ValueTooBig => ConversionErrorKind::Serialize(ValueTooBig),
+        SerializeCqlError::Encode(reason) => ConversionErrorKind::Encode(reason),
+        SerializeCqlError::ResultTooBig { size, max_size } => {
+            ConversionErrorKind::ResultTooBig { size, max_size }
+        }
+    }
+}
+
+// Checks that the size preceding a serialized value is -1 for null, or the number of
+// bytes following it, which must not exceed the maximum size
+fn check_serialized(bytes: &[u8], max_size: usize) -> Result<(), ConversionErrorKind> {
+    let (size, value) = match bytes.get(..HEADER_SIZE) {
+        Some(size) => (
+            i32::from_be_bytes(size.try_into().unwrap()),
+            &bytes[HEADER_SIZE..],
+        ),
+        None => return Err(ConversionErrorKind::InvalidLength),
+    };
+    if size == -1 && value.is_empty() {
+        return Ok(());
+    }
+    if usize::try_from(size).ok() != Some(value.len()) {
+        return Err(ConversionErrorKind::InvalidLength);
+    }
+    if value.len() > max_size {
+        return Err(ConversionErrorKind::ResultTooBig {
+            size: value.len(),
+            max_size,
+        });
+    }
+    Ok(())
+}
+
+/// Limits the size of the values converted by `ToWasmPtr` until it's dropped, used by
+/// `#[export_udf]` for its `max_result_size` attribute.
+#[doc(hidden)]
+pub struct MaxResultSize {
+    previous: usize,
+}
+
+impl MaxResultSize {
+    pub fn set(max_size: usize) -> MaxResultSize {
+        MaxResultSize {
+            previous: limit::replace(max_size),
+        }
+    }
+}
+
+impl Drop for MaxResultSize {
+    fn drop(&mut self) {
+        limit::replace(self.previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes the given size followed by the given number of bytes
+    struct Serialized(i32, usize);
+
+    impl SerializeCql for Serialized {
+        fn serialize_cql(&self, buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+            buf.extend_from_slice(&self.0.to_be_bytes());
+            buf.resize(buf.len() + self.1, 0xab);
+            Ok(())
+        }
+    }
+
+    struct TooBig;
+
+    impl SerializeCql for TooBig {
+        fn serialize_cql(&self, _buf: &mut Vec<u8>) -> Result<(), SerializeCqlError> {
+            Err(SerializeCqlError::ValueTooBig)
+        }
+    }
+
+    fn error_kind(result: Result<WasmPtr, ConversionError>) -> ConversionErrorKind {
+        match result {
+            Ok(_) => panic!("the conversion succeeded"),
+            Err(error) => error.kind().clone(),
+        }
+    }
+
+    #[test]
+    fn result_size_limit() {
+        let _max_size = MaxResultSize::set(8);
+        assert_eq!(
+            String::from("12345678").to_wasmptr().unwrap().size(),
+            Some(8)
+        );
+        assert_eq!(
+            error_kind(String::from("123456789").to_wasmptr()),
+            ConversionErrorKind::ResultTooBig {
+                size: 9,
+                max_size: 8
+            }
+        );
+        assert!(None::<String>.to_wasmptr().unwrap().is_null());
+        {
+            let _nested = MaxResultSize::set(0);
+            assert_eq!(String::new().to_wasmptr().unwrap().size(), Some(0));
+            assert!(String::from("1").to_wasmptr().is_err());
+        }
+        // the previous limit is restored
+        assert!(String::from("1").to_wasmptr().is_ok());
+    }
+
+    #[test]
+    fn result_size_limit_while_serializing() {
+        let _max_size = MaxResultSize::set(16);
+        let list = vec![String::from("abcd"); 1000];
+        // serializing stops at the first element exceeding the limit
+        match error_kind(list.to_wasmptr()) {
+            ConversionErrorKind::ResultTooBig { size, max_size } => {
+                assert_eq!(max_size, 16);
+                assert!(size > 16 && size <= 24);
+            }
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+        // the limit only applies to returned values
+        let mut buf = Vec::new();
+        list.serialize_cql(&mut buf).unwrap();
+        assert_eq!(buf.len(), 4 + 4 + 1000 * 8);
+    }
+
+    #[test]
+    fn invalid_length() {
+        // a null followed by a value, negative sizes and sizes not matching the value
+        for (size, written) in [(-1, 1), (-2, 0), (i32::MIN, 0), (3, 4), (5, 4)] {
+            assert_eq!(
+                error_kind(Serialized(size, written).to_wasmptr()),
+                ConversionErrorKind::InvalidLength
+            );
+        }
+        assert!(Serialized(-1, 0).to_wasmptr().unwrap().is_null());
+        assert_eq!(Serialized(4, 4).to_wasmptr().unwrap().size(), Some(4));
+        assert_eq!(
+            error_kind(TooBig.to_wasmptr()),
+            ConversionErrorKind::Serialize(ValueTooBig)
+        );
     }
 }
@@ -0,0 +1,571 @@
+use crate::codec::FromCqlValError;
+use crate::conversion_error::{conversion_failed, ConversionError};
+use crate::cql_ord::{compare_nullable, read_count, read_value};
+use crate::cql_value::ColumnType;
+use crate::deserialize_cql::DeserializeCql;
+use crate::serialize_cql::{serialize_bytes, SerializeCql};
+use crate::to_columntype::ToColumnType;
+use crate::to_wasmptr::serialize_to_wasmptr;
+use crate::wasm_convertible::WasmConvertible;
+use crate::wasmptr::WasmPtr;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::marker::PhantomData;
+
+// The bytes of a serialized collection: borrowed, or owned by the WasmPtr received from Scylla
+enum Storage<'a> {
+    Borrowed(&'a [u8]),
+    Wasm(WasmPtr),
+}
+
+// A serialized list, set or map whose elements (or keys and values) were counted and whose
+// sizes were checked, so that they can be read without errors
+struct Collection<'a> {
+    storage: Storage<'a>,
+    len: usize,
+}
+
+impl<'a> Collection<'a> {
+    fn borrowed(bytes: &'a [u8], values_per_element: usize) -> Result<Self, FromCqlValError> {
+        let len = Self::count(Some(bytes), values_per_element)?;
+        Ok(Collection {
+            storage: Storage::Borrowed(bytes),
+            len,
+        })
+    }
+
+    // Counts the elements of a serialized collection, checking the sizes of their values
+    fn count(bytes: Option<&[u8]>, values_per_element: usize) -> Result<usize, FromCqlValError> {
+        let mut buf = bytes.ok_or(FromCqlValError::ValIsNull)?;
+        if buf.is_empty() {
+            return Err(FromCqlValError::BadCqlType);
+        }
+        let len = read_count(&mut buf).ok_or(FromCqlValError::BadVal)?;
+        for _ in 0..len * values_per_element {
+            read_value(&mut buf).ok_or(FromCqlValError::BadVal)?;
+        }
+        Ok(len)
+    }
+
+    // Takes over an argument received from Scylla, which is converted to the view `V`
+    fn from_wasm<V: ToColumnType>(
+        arg: WasmPtr,
+        values_per_element: usize,
+        view: impl FnOnce(Collection<'a>) -> V,
+    ) -> Result<V, ConversionError> {
+        match Self::count(arg.as_slice(), values_per_element) {
+            Ok(len) => Ok(view(Collection {
+                storage: Storage::Wasm(arg),
+                len,
+            })),
+            Err(e) => Err(ConversionError::deserialize::<V>(
+                e,
+                &V::to_column_type(),
+                arg.as_slice(),
+            )),
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match &self.storage {
+            Storage::Borrowed(bytes) => bytes,
+            // The WasmPtr was checked not to be null in `new`
+            Storage::Wasm(wasmptr) => wasmptr.as_slice().unwrap_or_default(),
+        }
+    }
+
+    // The serialized values following the count, `None` for nulls
+    fn values(&self) -> impl Iterator<Item = Option<&[u8]>> {
+        let mut buf = &self.bytes()[4..];
+        core::iter::from_fn(move || read_value(&mut buf))
+    }
+
+    // Returns the bytes of the view `V` to Scylla, like a serialized value
+    fn to_wasmptr<V>(&self) -> Result<WasmPtr, ConversionError> {
+        let bytes = self.bytes();
+        serialize_to_wasmptr::<V>(4 + bytes.len(), |buf| serialize_bytes(bytes, buf))
+    }
+}
+
+fn try_decode<T: DeserializeCql>(
+    typ: &ColumnType,
+    bytes: Option<&[u8]>,
+) -> Result<T, ConversionError> {
+    T::deserialize_cql_bytes(typ, bytes)
+        .map_err(|e| ConversionError::deserialize::<T>(e, typ, bytes))
+}
+
+fn decode<T: DeserializeCql>(typ: &ColumnType, bytes: Option<&[u8]>) -> T {
+    try_decode(typ, bytes).unwrap_or_else(|error| conversion_failed(error))
+}
+
+// Finds the value of a serialized element in the sorted elements of a set or keys of a map,
+// stopping at the first bigger one
+fn find_sorted<'b, T: SerializeCql + ToColumnType, E>(
+    elements: impl Iterator<Item = (Option<&'b [u8]>, E)>,
+    value: &T,
+) -> Option<E> {
+    let mut buf = Vec::new();
+    value.serialize_cql(&mut buf).ok()?;
+    let value = read_value(&mut buf.as_slice())?;
+    let typ = T::to_column_type();
+    for (element, found) in elements {
+        match compare_nullable(&typ, element, value) {
+            Ordering::Less => continue,
+            Ordering::Equal => return Some(found),
+            Ordering::Greater => return None,
+        }
+    }
+    None
+}
+
+/// A view of a serialized `list<T>`, which decodes its elements only when they're read.
+///
+/// Can be used as an argument of a UDF in place of `Vec<T>`, e.g. when the function only needs
+/// to count or search the elements, or doesn't need all of them:
+/// ```
+/// use scylla_udf::{export_udf, ListView};
+///
+/// #[export_udf]
+/// fn first_long_word(words: ListView<'_, String>) -> Option<String> {
+///     words.iter().find(|word| word.len() > 10)
+/// }
+/// ```
+/// The elements are decoded by [`ListView::iter`] and [`ListView::get`] each time they're called.
+/// Reading an element that can't be converted to `T` panics, like converting an argument would.
+/// [`ListView::try_iter`] and [`ListView::try_get`] return the error instead, e.g. to skip
+/// malformed elements.
+pub struct ListView<'a, T> {
+    collection: Collection<'a>,
+    element: PhantomData<fn() -> T>,
+}
+
+/// A view of a serialized `set<T>`, which decodes its elements only when they're read.
+///
+/// The elements are sorted in the order of their CQL type, so [`SetView::contains`] can
+/// compare them without decoding, and stop at the first element bigger than the searched one.
+pub struct SetView<'a, T> {
+    collection: Collection<'a>,
+    element: PhantomData<fn() -> T>,
+}
+
+/// A view of a serialized `map<K, V>`, which decodes its keys and values only when they're read.
+///
+/// The entries are sorted by the keys in the order of their CQL type, so [`MapView::get`] can
+/// compare the keys without decoding them, and stop at the first key bigger than the searched one.
+pub struct MapView<'a, K, V> {
+    collection: Collection<'a>,
+    entry: PhantomData<fn() -> (K, V)>,
+}
+
+macro_rules! impl_element_view {
+    ($view:ident) => {
+        impl<'a, T> $view<'a, T> {
+            /// Creates a view of a serialized value (without the size prefix).
+            /// Fails if the value isn't a collection with as many elements as it declares.
+            pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, FromCqlValError> {
+                Ok($view {
+                    collection: Collection::borrowed(bytes, 1)?,
+                    element: PhantomData,
+                })
+            }
+
+            /// The number of elements, known without decoding them.
+            pub fn len(&self) -> usize {
+                self.collection.len
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.collection.len == 0
+            }
+
+            /// The serialized bytes of the collection.
+            pub fn as_bytes(&self) -> &[u8] {
+                self.collection.bytes()
+            }
+        }
+
+        impl<'a, T: DeserializeCql + ToColumnType> $view<'a, T> {
+            /// Iterates over the elements, decoding each of them when it's reached.
+            /// Panics when an element can't be converted to `T`.
+            pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+                self.try_iter()
+                    .map(|element| element.unwrap_or_else(|error| conversion_failed(error)))
+            }
+
+            /// Iterates over the elements like [`iter`](Self::iter), returning an error for
+            /// each element that can't be converted to `T`.
+            pub fn try_iter(&self) -> impl Iterator<Item = Result<T, ConversionError>> + '_ {
+                let typ = T::to_column_type();
+                self.collection
+                    .values()
+                    .map(move |element| try_decode(&typ, element))
+            }
+        }
+
+        impl<T> fmt::Debug for $view<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($view))
+                    .field("len", &self.len())
+                    .finish_non_exhaustive()
+            }
+        }
+
+        impl<'a, T> WasmConvertible for $view<'a, T>
+        where
+            $view<'a, T>: ToColumnType,
+        {
+            type WasmType = WasmPtr;
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+                Collection::from_wasm(arg, 1, |collection| $view {
+                    collection,
+                    element: PhantomData,
+                })
+            }
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+                self.collection.to_wasmptr::<Self>()
+            }
+        }
+
+        // Empty values are converted to None, like for other collections
+        impl<'a, T> WasmConvertible for Option<$view<'a, T>>
+        where
+            $view<'a, T>: ToColumnType,
+        {
+            type WasmType = WasmPtr;
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+                match arg.as_slice() {
+                    None | Some([]) => Ok(None),
+                    Some(_) => $view::from_wasm(arg).map(Some),
+                }
+            }
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+                match self {
+                    Some(view) => view.to_wasm(),
+                    None => Ok(WasmPtr::null()),
+                }
+            }
+        }
+    };
+}
+
+impl_element_view!(ListView);
+impl_element_view!(SetView);
+
+impl<T: DeserializeCql + ToColumnType> ListView<'_, T> {
+    /// Decodes the element at the given index, or returns `None` if it's out of bounds.
+    /// Only the sizes of the preceding elements are read.
+    /// Panics when the element can't be converted to `T`.
+    pub fn get(&self, index: usize) -> Option<T> {
+        let element = self.collection.values().nth(index)?;
+        Some(decode(&T::to_column_type(), element))
+    }
+
+    /// Decodes the element at the given index like [`get`](Self::get), returning an error if
+    /// it can't be converted to `T`.
+    pub fn try_get(&self, index: usize) -> Result<Option<T>, ConversionError> {
+        self.collection
+            .values()
+            .nth(index)
+            .map(|element| try_decode(&T::to_column_type(), element))
+            .transpose()
+    }
+}
+
+impl<T: SerializeCql + ToColumnType> SetView<'_, T> {
+    /// Checks whether the set contains the value, without decoding its elements.
+    pub fn contains(&self, value: &T) -> bool {
+        find_sorted(self.collection.values().map(|element| (element, ())), value).is_some()
+    }
+}
+
+impl<T: ToColumnType> ToColumnType for ListView<'_, T> {
+    fn to_column_type() -> ColumnType {
+        ColumnType::List(Box::new(T::to_column_type()))
+    }
+}
+
+impl<T: ToColumnType> ToColumnType for SetView<'_, T> {
+    fn to_column_type() -> ColumnType {
+        ColumnType::Set(Box::new(T::to_column_type()))
+    }
+}
+
+impl<'a, K, V> MapView<'a, K, V> {
+    /// Creates a view of a serialized value (without the size prefix).
+    /// Fails if the value isn't a map with as many entries as it declares.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, FromCqlValError> {
+        Ok(MapView {
+            collection: Collection::borrowed(bytes, 2)?,
+            entry: PhantomData,
+        })
+    }
+
+    /// The number of entries, known without decoding them.
+    pub fn len(&self) -> usize {
+        self.collection.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collection.len == 0
+    }
+
+    /// The serialized bytes of the map.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.collection.bytes()
+    }
+
+    // The serialized keys and values
+    fn entries(&self) -> impl Iterator<Item = (Option<&[u8]>, Option<&[u8]>)> {
+        let mut values = self.collection.values();
+        core::iter::from_fn(move || Some((values.next()?, values.next()?)))
+    }
+}
+
+impl<K: DeserializeCql + ToColumnType, V: DeserializeCql + ToColumnType> MapView<'_, K, V> {
+    /// Iterates over the entries, decoding each of them when it's reached.
+    /// Panics when a key or a value can't be converted.
+    pub fn iter(&self) -> impl Iterator<Item = (K, V)> + '_ {
+        self.try_iter()
+            .map(|entry| entry.unwrap_or_else(|error| conversion_failed(error)))
+    }
+
+    /// Iterates over the entries like [`iter`](Self::iter), returning an error for each entry
+    /// whose key or value can't be converted.
+    pub fn try_iter(&self) -> impl Iterator<Item = Result<(K, V), ConversionError>> + '_ {
+        let (key_type, value_type) = (K::to_column_type(), V::to_column_type());
+        self.entries().map(move |(key, value)| {
+            Ok((try_decode(&key_type, key)?, try_decode(&value_type, value)?))
+        })
+    }
+}
+
+impl<K: DeserializeCql + ToColumnType, V> MapView<'_, K, V> {
+    /// Iterates over the keys, without decoding the values.
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        let typ = K::to_column_type();
+        self.entries().map(move |(key, _)| decode(&typ, key))
+    }
+}
+
+impl<K, V: DeserializeCql + ToColumnType> MapView<'_, K, V> {
+    /// Iterates over the values, without decoding the keys.
+    pub fn values(&self) -> impl Iterator<Item = V> + '_ {
+        let typ = V::to_column_type();
+        self.entries().map(move |(_, value)| decode(&typ, value))
+    }
+}
+
+impl<K: SerializeCql + ToColumnType, V: DeserializeCql + ToColumnType> MapView<'_, K, V> {
+    /// Decodes the value for the given key, comparing the keys without decoding them.
+    /// Returns `None` if the map doesn't contain the key.
+    /// Panics when the value can't be converted to `V`.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let value = find_sorted(self.entries(), key)?;
+        Some(decode(&V::to_column_type(), value))
+    }
+
+    /// Decodes the value for the given key like [`get`](Self::get), returning an error if it
+    /// can't be converted to `V`.
+    pub fn try_get(&self, key: &K) -> Result<Option<V>, ConversionError> {
+        find_sorted(self.entries(), key)
+            .map(|value| try_decode(&V::to_column_type(), value))
+            .transpose()
+    }
+}
+
+impl<K, V> fmt::Debug for MapView<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapView")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K: ToColumnType, V: ToColumnType> ToColumnType for MapView<'_, K, V> {
+    fn to_column_type() -> ColumnType {
+        ColumnType::Map(Box::new(K::to_column_type()), Box::new(V::to_column_type()))
+    }
+}
+
+impl<'a, K: ToColumnType, V: ToColumnType> WasmConvertible for MapView<'a, K, V> {
+    type WasmType = WasmPtr;
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+        Collection::from_wasm(arg, 2, |collection| MapView {
+            collection,
+            entry: PhantomData,
+        })
+    }
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+        self.collection.to_wasmptr::<Self>()
+    }
+}
+
+// Empty values are converted to None, like for other collections
+impl<'a, K: ToColumnType, V: ToColumnType> WasmConvertible for Option<MapView<'a, K, V>> {
+    type WasmType = WasmPtr;
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+        match arg.as_slice() {
+            None | Some([]) => Ok(None),
+            Some(_) => MapView::from_wasm(arg).map(Some),
+        }
+    }
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+        match self {
+            Some(view) => view.to_wasm(),
+            None => Ok(WasmPtr::null()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    fn serialize<T: SerializeCql>(value: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        value.serialize_cql(&mut buf).unwrap();
+        buf.split_off(4)
+    }
+
+    #[test]
+    fn list_view() {
+        let bytes = serialize(&vec![
+            Some(String::from("a")),
+            None,
+            Some(String::from("c")),
+        ]);
+        let view = ListView::<Option<String>>::from_bytes(&bytes).unwrap();
+        assert_eq!(view.len(), 3);
+        assert_eq!(
+            view.iter().collect::<Vec<_>>(),
+            vec![Some(String::from("a")), None, Some(String::from("c"))]
+        );
+        assert_eq!(view.get(2), Some(Some(String::from("c"))));
+        assert_eq!(view.get(3), None);
+        assert_eq!(view.as_bytes(), bytes);
+        let empty = serialize(&Vec::<i32>::new());
+        assert!(ListView::<i32>::from_bytes(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn set_view() {
+        let set = BTreeSet::from([-3_i64, 1, 100]);
+        let bytes = serialize(&set);
+        let view = SetView::<i64>::from_bytes(&bytes).unwrap();
+        assert_eq!(view.iter().collect::<BTreeSet<_>>(), set);
+        assert!(view.contains(&-3));
+        assert!(view.contains(&100));
+        assert!(!view.contains(&2));
+        assert!(!view.contains(&1000));
+    }
+
+    #[test]
+    fn map_view() {
+        let map = BTreeMap::from([
+            (String::from("a"), 1_i32),
+            (String::from("bb"), 2),
+            (String::from("c"), 3),
+        ]);
+        let bytes = serialize(&map);
+        let view = MapView::<String, i32>::from_bytes(&bytes).unwrap();
+        assert_eq!(view.len(), 3);
+        assert_eq!(view.iter().collect::<BTreeMap<_, _>>(), map);
+        assert_eq!(view.keys().collect::<Vec<_>>(), ["a", "bb", "c"]);
+        assert_eq!(view.values().sum::<i32>(), 6);
+        assert_eq!(view.get(&String::from("bb")), Some(2));
+        assert_eq!(view.get(&String::from("b")), None);
+    }
+
+    #[test]
+    fn malformed_views() {
+        // 2 elements declared, 1 present
+        let bytes = [0, 0, 0, 2, 0, 0, 0, 1, 7];
+        assert_eq!(
+            ListView::<i8>::from_bytes(&bytes).unwrap_err(),
+            FromCqlValError::BadVal
+        );
+        assert_eq!(
+            MapView::<i8, i8>::from_bytes(&bytes[..8]).unwrap_err(),
+            FromCqlValError::BadVal
+        );
+        assert_eq!(
+            SetView::<i8>::from_bytes(&[]).unwrap_err(),
+            FromCqlValError::BadCqlType
+        );
+    }
+
+    #[test]
+    fn malformed_elements() {
+        use crate::conversion_error::ConversionErrorKind;
+        let bad_val = ConversionErrorKind::Deserialize(FromCqlValError::BadVal);
+        // [1, a value of 3 bytes, 3]
+        let bytes = [
+            0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 3,
+        ];
+        let view = ListView::<i32>::from_bytes(&bytes).unwrap();
+        let elements = view.try_iter().collect::<Vec<_>>();
+        assert_eq!(elements[0].as_ref().ok(), Some(&1));
+        assert_eq!(elements[1].as_ref().unwrap_err().kind(), &bad_val);
+        assert_eq!(elements[2].as_ref().ok(), Some(&3));
+        assert_eq!(view.try_get(0).unwrap(), Some(1));
+        assert_eq!(view.try_get(1).unwrap_err().kind(), &bad_val);
+        assert_eq!(view.try_get(3).unwrap(), None);
+        // {1: a value of 3 bytes}
+        let bytes = [0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 2];
+        let view = MapView::<i32, i32>::from_bytes(&bytes).unwrap();
+        assert_eq!(view.keys().collect::<Vec<_>>(), [1]);
+        assert_eq!(
+            view.try_iter().next().unwrap().unwrap_err().kind(),
+            &bad_val
+        );
+        assert_eq!(view.try_get(&1).unwrap_err().kind(), &bad_val);
+        assert_eq!(view.try_get(&2).unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to convert")]
+    fn malformed_element_panics() {
+        let bytes = [0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 2];
+        SetView::<i32>::from_bytes(&bytes).unwrap().iter().count();
+    }
+
+    #[test]
+    fn views_convert() {
+        use crate::to_wasmptr::ToWasmPtr;
+        let list = vec![String::from("x"), String::from("y")];
+        let view = ListView::<String>::from_wasm(list.to_wasmptr().unwrap()).unwrap();
+        assert_eq!(view.iter().collect::<Vec<_>>(), list);
+        assert_eq!(
+            Vec::<String>::from_wasm(view.to_wasm().unwrap()).unwrap(),
+            list
+        );
+        // returned views are limited by the maximum result size like other values
+        {
+            let _max_size = crate::to_wasmptr::MaxResultSize::set(8);
+            match view.to_wasm() {
+                Ok(_) => panic!("the conversion succeeded"),
+                Err(error) => assert_eq!(
+                    error.kind(),
+                    &crate::conversion_error::ConversionErrorKind::ResultTooBig {
+                        size: 14,
+                        max_size: 8
+                    }
+                ),
+            }
+        }
+        assert!(
+            Option::<SetView<i32>>::from_wasm(None::<BTreeSet<i32>>.to_wasmptr().unwrap())
+                .unwrap()
+                .is_none()
+        );
+        let map = BTreeMap::from([(1_i32, 2_i32)]);
+        let view = Option::<MapView<i32, i32>>::from_wasm(Some(map).to_wasmptr().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(view.get(&1), Some(2));
+    }
+}
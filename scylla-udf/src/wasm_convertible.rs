@@ -1,14 +1,29 @@
+use crate::conversion_error::{ConversionError, ConversionErrorKind};
+use crate::cql_value::ColumnType;
+use crate::cql_value::{Counter, CqlDuration, Time, Timestamp};
 use crate::from_wasmptr::FromWasmPtr;
 use crate::to_wasmptr::ToWasmPtr;
 use crate::wasmptr::WasmPtr;
-use scylla_cql::frame::value::{Counter, CqlDuration, Time, Timestamp};
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
-use std::convert::TryFrom;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
 
-pub trait WasmConvertible {
+/// Conversion of a Rust type to and from the Wasm representation of its CQL type, used for
+/// arguments and return values of functions annotated with `#[export_udf]`.
+pub trait WasmConvertible: Sized {
+    /// `i32`, `i64`, `f32` or `f64` for the CQL types that Scylla passes as native Wasm values,
+    /// and `WasmPtr` for all other types.
     type WasmType;
-    fn from_wasm(arg: Self::WasmType) -> Self;
-    fn to_wasm(&self) -> Self::WasmType;
+    /// Converts an argument received from Scylla.
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError>;
+    /// Converts a value to be returned to Scylla.
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError>;
 }
 
 // This macro implements WasmConvertible given a Rust type and the resulting WasmType
@@ -16,11 +31,11 @@ macro_rules! impl_wasm_convertible_native {
     ($rust_type:ty) => {
         impl WasmConvertible for $rust_type {
             type WasmType = $rust_type;
-            fn from_wasm(arg: Self::WasmType) -> Self {
-                arg
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+                Ok(arg)
             }
-            fn to_wasm(&self) -> Self::WasmType {
-                *self
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+                Ok(*self)
             }
         }
     };
@@ -33,31 +48,33 @@ impl_wasm_convertible_native!(f64);
 
 // This macro implements WasmConvertible given a Rust type that can be converted to the given WasmType using TryFrom
 macro_rules! impl_wasm_convertible_scalar {
-    ($rust_type:ty, $scalar_type:ty) => {
+    ($rust_type:ty, $scalar_type:ty, $column_type:ident) => {
         impl WasmConvertible for $rust_type {
             type WasmType = $scalar_type;
-            fn from_wasm(arg: Self::WasmType) -> Self {
-                <$rust_type>::try_from(arg)
-                    .expect("Failed to convert from wasm type to a rust type")
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+                <$rust_type>::try_from(arg).map_err(|_| {
+                    ConversionError::new::<$rust_type>(ConversionErrorKind::OutOfRange)
+                        .with_column_type(ColumnType::$column_type)
+                })
             }
-            fn to_wasm(&self) -> Self::WasmType {
-                *self as Self::WasmType
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+                Ok(*self as Self::WasmType)
             }
         }
     };
 }
 
-impl_wasm_convertible_scalar!(i8, i32);
-impl_wasm_convertible_scalar!(i16, i32);
+impl_wasm_convertible_scalar!(i8, i32, TinyInt);
+impl_wasm_convertible_scalar!(i16, i32, SmallInt);
 
 // Can't convert bool to i32 using TryFrom, so we need a special implementation
 impl WasmConvertible for bool {
     type WasmType = i32;
-    fn from_wasm(arg: Self::WasmType) -> Self {
-        arg != 0
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+        Ok(arg != 0)
     }
-    fn to_wasm(&self) -> Self::WasmType {
-        i32::from(*self)
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+        Ok(i32::from(*self))
     }
 }
 
@@ -66,10 +83,10 @@ macro_rules! impl_wasm_convertible_serialized {
     ($rust_type:ty) => {
         impl WasmConvertible for $rust_type {
             type WasmType = WasmPtr;
-            fn from_wasm(arg: Self::WasmType) -> Self {
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
                 <Self as FromWasmPtr>::from_wasmptr(arg)
             }
-            fn to_wasm(&self) -> Self::WasmType {
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
                 <Self as ToWasmPtr>::to_wasmptr(self)
             }
         }
@@ -81,8 +98,18 @@ impl_wasm_convertible_serialized!(chrono::NaiveDate);
 impl_wasm_convertible_serialized!(bigdecimal::BigDecimal);
 impl_wasm_convertible_serialized!(CqlDuration);
 impl_wasm_convertible_serialized!(String);
+impl_wasm_convertible_serialized!(Box<str>);
+impl_wasm_convertible_serialized!(Arc<str>);
+impl_wasm_convertible_serialized!(Cow<'static, str>);
+impl_wasm_convertible_serialized!(Box<[u8]>);
+impl_wasm_convertible_serialized!(bytes::Bytes);
 impl_wasm_convertible_serialized!(Timestamp);
+#[cfg(feature = "std")]
 impl_wasm_convertible_serialized!(std::net::IpAddr);
+#[cfg(feature = "std")]
+impl_wasm_convertible_serialized!(std::net::Ipv4Addr);
+#[cfg(feature = "std")]
+impl_wasm_convertible_serialized!(std::net::Ipv6Addr);
 impl_wasm_convertible_serialized!(Time);
 impl_wasm_convertible_serialized!(uuid::Uuid);
 impl_wasm_convertible_serialized!(num_bigint::BigInt);
@@ -95,21 +122,66 @@ macro_rules! impl_wasm_convertible_serialized_generic {
             $rust_type: FromWasmPtr + ToWasmPtr,
         {
             type WasmType = WasmPtr;
-            fn from_wasm(arg: Self::WasmType) -> Self {
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
                 <Self as FromWasmPtr>::from_wasmptr(arg)
             }
-            fn to_wasm(&self) -> Self::WasmType {
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
                 <Self as ToWasmPtr>::to_wasmptr(self)
             }
         }
     };
 }
 
+// Converted like the wrapped value, which may be a native Wasm type
+impl<T: WasmConvertible> WasmConvertible for crate::cql_ord::ByCqlOrder<T> {
+    type WasmType = T::WasmType;
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+        T::from_wasm(arg).map(crate::cql_ord::ByCqlOrder)
+    }
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+        self.0.to_wasm()
+    }
+}
+
 impl_wasm_convertible_serialized_generic!(Option<T>);
+impl_wasm_convertible_serialized_generic!(crate::maybe_empty::MaybeEmpty<T>);
+impl_wasm_convertible_serialized_generic!(crate::cql_bytes::CqlBytes<T>);
 // Implements both lists and blobs
 impl_wasm_convertible_serialized_generic!(Vec<T>);
 impl_wasm_convertible_serialized_generic!(BTreeSet<T>);
+#[cfg(feature = "std")]
 impl_wasm_convertible_serialized_generic!(HashSet<T>);
+#[cfg(feature = "indexmap")]
+impl_wasm_convertible_serialized_generic!(indexmap::IndexSet<T>);
+#[cfg(feature = "serde")]
+impl_wasm_convertible_serialized_generic!(crate::json::Json<T>);
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> WasmConvertible for smallvec::SmallVec<A>
+where
+    smallvec::SmallVec<A>: FromWasmPtr + ToWasmPtr,
+{
+    type WasmType = WasmPtr;
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+        <Self as FromWasmPtr>::from_wasmptr(arg)
+    }
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+        <Self as ToWasmPtr>::to_wasmptr(self)
+    }
+}
+
+impl<T, const N: usize> WasmConvertible for [T; N]
+where
+    [T; N]: FromWasmPtr + ToWasmPtr,
+{
+    type WasmType = WasmPtr;
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+        <Self as FromWasmPtr>::from_wasmptr(arg)
+    }
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+        <Self as ToWasmPtr>::to_wasmptr(self)
+    }
+}
 
 // This macro implements WasmConvertible given a Rust type with generic parameters K and V that can be (de)serialized using FromWasmPtr and ToWasmPtr
 macro_rules! impl_wasm_convertible_serialized_double_generic {
@@ -119,10 +191,10 @@ macro_rules! impl_wasm_convertible_serialized_double_generic {
             $rust_type: FromWasmPtr + ToWasmPtr,
         {
             type WasmType = WasmPtr;
-            fn from_wasm(arg: Self::WasmType) -> Self {
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
                 <Self as FromWasmPtr>::from_wasmptr(arg)
             }
-            fn to_wasm(&self) -> Self::WasmType {
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
                 <Self as ToWasmPtr>::to_wasmptr(self)
             }
         }
@@ -130,38 +202,44 @@ macro_rules! impl_wasm_convertible_serialized_double_generic {
 }
 
 impl_wasm_convertible_serialized_double_generic!(BTreeMap<K, V>);
+#[cfg(feature = "std")]
 impl_wasm_convertible_serialized_double_generic!(HashMap<K, V>);
+#[cfg(feature = "indexmap")]
+impl_wasm_convertible_serialized_double_generic!(indexmap::IndexMap<K, V>);
+
+#[cfg(feature = "serde")]
+impl<T, C> WasmConvertible for crate::blob::Blob<T, C>
+where
+    crate::blob::Blob<T, C>: FromWasmPtr + ToWasmPtr,
+{
+    type WasmType = WasmPtr;
+    fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
+        <Self as FromWasmPtr>::from_wasmptr(arg)
+    }
+    fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
+        <Self as ToWasmPtr>::to_wasmptr(self)
+    }
+}
 
 // This macro implements WasmConvertible for tuples of types that can be (de)serialized using FromWasmPtr and ToWasmPtr
 macro_rules! impl_wasm_convertible_serialized_tuple {
-    ( $( $types:ident )* ) => {
+    ( $( $idx:tt $types:ident )* ) => {
         impl<$($types),*> WasmConvertible for ($($types,)*)
         where
             ($($types,)*): FromWasmPtr + ToWasmPtr
         {
             type WasmType = WasmPtr;
-            fn from_wasm(arg: Self::WasmType) -> Self {
+            fn from_wasm(arg: Self::WasmType) -> Result<Self, ConversionError> {
                 <Self as FromWasmPtr>::from_wasmptr(arg)
             }
-            fn to_wasm(&self) -> Self::WasmType {
+            fn to_wasm(&self) -> Result<Self::WasmType, ConversionError> {
                 <Self as ToWasmPtr>::to_wasmptr(self)
             }
         }
     };
 }
 
-impl_wasm_convertible_serialized_tuple! { A }
-impl_wasm_convertible_serialized_tuple! { A B }
-impl_wasm_convertible_serialized_tuple! { A B C }
-impl_wasm_convertible_serialized_tuple! { A B C D }
-impl_wasm_convertible_serialized_tuple! { A B C D E }
-impl_wasm_convertible_serialized_tuple! { A B C D E F }
-impl_wasm_convertible_serialized_tuple! { A B C D E F G }
-impl_wasm_convertible_serialized_tuple! { A B C D E F G H }
-impl_wasm_convertible_serialized_tuple! { A B C D E F G H I }
-impl_wasm_convertible_serialized_tuple! { A B C D E F G H I J }
-impl_wasm_convertible_serialized_tuple! { A B C D E F G H I J K }
-impl_wasm_convertible_serialized_tuple! { A B C D E F G H I J K L }
+for_each_tuple!(impl_wasm_convertible_serialized_tuple);
 
 #[cfg(test)]
 mod tests {
@@ -170,67 +248,83 @@ mod tests {
 
     #[test]
     fn i32_convert() {
-        assert_eq!(i32::from_wasm(42_i32.to_wasm()), 42_i32);
-        assert_eq!(i32::from_wasm(-42_i32), -42_i32);
-        assert_eq!((-42_i32).to_wasm(), -42_i32);
+        assert_eq!(i32::from_wasm(42_i32.to_wasm().unwrap()).unwrap(), 42_i32);
+        assert_eq!(i32::from_wasm(-42_i32).unwrap(), -42_i32);
+        assert_eq!((-42_i32).to_wasm().unwrap(), -42_i32);
     }
     #[test]
     fn i64_convert() {
-        assert_eq!(i64::from_wasm(42_i64.to_wasm()), 42_i64);
-        assert_eq!(i64::from_wasm(-42_i64), -42_i64);
-        assert_eq!((-42_i64).to_wasm(), -42_i64);
+        assert_eq!(i64::from_wasm(42_i64.to_wasm().unwrap()).unwrap(), 42_i64);
+        assert_eq!(i64::from_wasm(-42_i64).unwrap(), -42_i64);
+        assert_eq!((-42_i64).to_wasm().unwrap(), -42_i64);
     }
     #[test]
     fn f32_convert() {
-        assert_eq!(f32::from_wasm(0.42_f32.to_wasm()), 0.42_f32);
-        assert_eq!(f32::from_wasm(-0.42_f32), -0.42_f32);
-        assert_eq!((-0.42_f32).to_wasm(), -0.42_f32);
+        assert_eq!(
+            f32::from_wasm(0.42_f32.to_wasm().unwrap()).unwrap(),
+            0.42_f32
+        );
+        assert_eq!(f32::from_wasm(-0.42_f32).unwrap(), -0.42_f32);
+        assert_eq!((-0.42_f32).to_wasm().unwrap(), -0.42_f32);
     }
     #[test]
     fn f64_convert() {
-        assert_eq!(f64::from_wasm(0.42_f64.to_wasm()), 0.42_f64);
-        assert_eq!(f64::from_wasm(-0.42_f64), -0.42_f64);
-        assert_eq!((-0.42_f64).to_wasm(), -0.42_f64);
+        assert_eq!(
+            f64::from_wasm(0.42_f64.to_wasm().unwrap()).unwrap(),
+            0.42_f64
+        );
+        assert_eq!(f64::from_wasm(-0.42_f64).unwrap(), -0.42_f64);
+        assert_eq!((-0.42_f64).to_wasm().unwrap(), -0.42_f64);
     }
     #[test]
     fn i8_convert() {
-        assert_eq!(i8::from_wasm(42_i8.to_wasm()), 42_i8);
-        assert_eq!(i8::from_wasm(-42_i32), -42_i8);
-        assert_eq!((-42_i8).to_wasm(), -42_i32);
+        assert_eq!(i8::from_wasm(42_i8.to_wasm().unwrap()).unwrap(), 42_i8);
+        assert_eq!(i8::from_wasm(-42_i32).unwrap(), -42_i8);
+        assert_eq!((-42_i8).to_wasm().unwrap(), -42_i32);
     }
     #[test]
     fn i16_convert() {
-        assert_eq!(i64::from_wasm(42_i64.to_wasm()), 42_i64);
-        assert_eq!(i64::from_wasm(-42_i64), -42_i64);
-        assert_eq!((-42_i64).to_wasm(), -42_i64);
+        assert_eq!(i16::from_wasm(42_i16.to_wasm().unwrap()).unwrap(), 42_i16);
+        assert_eq!(i16::from_wasm(-42_i32).unwrap(), -42_i16);
+        assert_eq!((-42_i16).to_wasm().unwrap(), -42_i32);
+        for value in [i16::MIN, i16::MAX] {
+            assert_eq!(i16::from_wasm(value.to_wasm().unwrap()).unwrap(), value);
+        }
+        assert!(i16::from_wasm(i32::from(i16::MAX) + 1).is_err());
     }
     #[test]
     fn bool_convert() {
-        assert!(bool::from_wasm(true.to_wasm()));
-        assert!(bool::from_wasm(1_i32));
-        assert_eq!(bool::to_wasm(&false), 0_i32);
+        assert!(bool::from_wasm(true.to_wasm().unwrap()).unwrap());
+        assert!(bool::from_wasm(1_i32).unwrap());
+        assert_eq!(bool::to_wasm(&false).unwrap(), 0_i32);
     }
     #[test]
     fn blob_convert() {
         let blob: Vec<u8> = vec![1, 2, 3, 4, 5];
-        assert_eq!(Vec::<u8>::from_wasm(blob.to_wasm()), blob);
+        assert_eq!(Vec::<u8>::from_wasm(blob.to_wasm().unwrap()).unwrap(), blob);
     }
     #[test]
     fn counter_convert() {
-        assert_eq!(Counter::from_wasm(Counter(13).to_wasm()), Counter(13));
+        assert_eq!(
+            Counter::from_wasm(Counter(13).to_wasm().unwrap()).unwrap(),
+            Counter(13)
+        );
     }
     #[test]
     fn naive_date_convert() {
         use chrono::NaiveDate;
         let date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
-        assert_eq!(NaiveDate::from_wasm(date.to_wasm()), date);
+        assert_eq!(NaiveDate::from_wasm(date.to_wasm().unwrap()).unwrap(), date);
     }
     #[test]
     fn big_decimal_convert() {
         use bigdecimal::BigDecimal;
-        use std::str::FromStr;
+        use core::str::FromStr;
         let bigd = BigDecimal::from_str("547318970434573134570").unwrap();
-        assert_eq!(BigDecimal::from_wasm(bigd.to_wasm()), bigd);
+        assert_eq!(
+            BigDecimal::from_wasm(bigd.to_wasm().unwrap()).unwrap(),
+            bigd
+        );
     }
     #[test]
     fn cql_duration_convert() {
@@ -239,50 +333,184 @@ mod tests {
             days: 2,
             nanoseconds: 3,
         };
-        assert_eq!(CqlDuration::from_wasm(dur.to_wasm()), dur);
+        assert_eq!(CqlDuration::from_wasm(dur.to_wasm().unwrap()).unwrap(), dur);
     }
     #[test]
     fn timestamp_convert() {
         use chrono::Duration;
         let ts = Timestamp(Duration::weeks(2));
-        assert_eq!(Timestamp::from_wasm(ts.to_wasm()), ts);
+        assert_eq!(Timestamp::from_wasm(ts.to_wasm().unwrap()).unwrap(), ts);
+    }
+    #[test]
+    fn maybe_empty_convert() {
+        let empty = MaybeEmpty::<i32>::Empty;
+        assert_eq!(
+            MaybeEmpty::<i32>::from_wasm(empty.to_wasm().unwrap()).unwrap(),
+            empty
+        );
+        let value = MaybeEmpty::Value(uuid::Uuid::from_u128(42));
+        assert_eq!(
+            MaybeEmpty::<uuid::Uuid>::from_wasm(value.to_wasm().unwrap()).unwrap(),
+            value
+        );
+        let null: Option<MaybeEmpty<Timestamp>> = None;
+        assert_eq!(
+            Option::<MaybeEmpty<Timestamp>>::from_wasm(null.to_wasm().unwrap()).unwrap(),
+            None
+        );
+        assert_eq!(
+            Option::<MaybeEmpty<i32>>::from_wasm(Some(empty).to_wasm().unwrap()).unwrap(),
+            Some(empty)
+        );
+        // without MaybeEmpty, empty values are read as null
+        assert_eq!(
+            Option::<i32>::from_wasm(empty.to_wasm().unwrap()).unwrap(),
+            None
+        );
+    }
+    #[test]
+    fn cql_bytes_convert() {
+        let value = (String::from("abc"), vec![1, 2]);
+        let bytes = CqlBytes::<(String, Vec<i32>)>::from_wasm(value.to_wasm().unwrap()).unwrap();
+        assert_eq!(bytes, CqlBytes::encode(&value).unwrap());
+        assert_eq!(bytes.decode().unwrap(), value);
+        let returned = CqlBytes::<(String, Vec<i32>)>::from_wasm(bytes.to_wasm().unwrap()).unwrap();
+        assert_eq!(returned.as_bytes(), bytes.as_bytes());
+        assert_eq!(
+            Option::<CqlBytes<i32>>::from_wasm(None::<i32>.to_wasm().unwrap()).unwrap(),
+            None
+        );
+        // nested values are serialized again
+        let list = vec![CqlBytes::encode(&5_i64).unwrap()];
+        assert_eq!(
+            Vec::<CqlBytes<i64>>::from_wasm(list.to_wasm().unwrap()).unwrap(),
+            list
+        );
+    }
+    #[test]
+    fn by_cql_order_convert() {
+        use std::collections::BTreeSet;
+        assert_eq!(
+            ByCqlOrder::<i32>::from_wasm(ByCqlOrder(7).to_wasm().unwrap())
+                .unwrap()
+                .0,
+            7
+        );
+        let set: BTreeSet<_> = [f64::NAN, -0.0, 0.0].into_iter().map(ByCqlOrder).collect();
+        let converted = BTreeSet::<ByCqlOrder<f64>>::from_wasm(set.to_wasm().unwrap()).unwrap();
+        assert!(converted.iter().eq(set.iter()));
+        assert_eq!(converted.len(), 3);
+    }
+    #[test]
+    fn set_and_map_cql_order() {
+        use std::collections::{BTreeMap, HashSet};
+        let set: HashSet<i32> = [3, -1, 2, 0].into_iter().collect();
+        assert_eq!(
+            Vec::<i32>::from_wasm(set.to_wasm().unwrap()).unwrap(),
+            vec![-1, 0, 2, 3]
+        );
+        // uuids are ordered by version first, unlike uuid::Uuid
+        let v4 = uuid::Uuid::from_u128(0x00000000_0000_4000_8000_000000000000);
+        let v1 = uuid::Uuid::from_u128(0xffffffff_ffff_1fff_8000_000000000000);
+        let map: BTreeMap<uuid::Uuid, i8> = [(v4, 4), (v1, 1)].into_iter().collect();
+        let bytes = CqlBytes::encode(&map).unwrap();
+        // the entry count, then the size and bytes of the first key
+        assert_eq!(&bytes.as_bytes()[8..24], v1.as_bytes());
+        assert_eq!(bytes.decode().unwrap(), map);
     }
     #[test]
     fn string_convert() {
         let s = String::from("abc");
-        assert_eq!(String::from_wasm(s.to_wasm()), s);
+        assert_eq!(String::from_wasm(s.to_wasm().unwrap()).unwrap(), s);
+    }
+    #[test]
+    fn shared_string_convert() {
+        use alloc::borrow::Cow;
+        use alloc::sync::Arc;
+        let s: Box<str> = Box::from("abc");
+        assert_eq!(Box::<str>::from_wasm(s.to_wasm().unwrap()).unwrap(), s);
+        let s: Arc<str> = Arc::from("abc");
+        assert_eq!(Arc::<str>::from_wasm(s.to_wasm().unwrap()).unwrap(), s);
+        let s: Cow<'static, str> = Cow::Borrowed("abc");
+        assert_eq!(
+            Cow::<'static, str>::from_wasm(s.to_wasm().unwrap()).unwrap(),
+            s
+        );
+    }
+    #[test]
+    fn shared_blob_convert() {
+        let blob: Box<[u8]> = Box::from([1_u8, 2, 3].as_slice());
+        assert_eq!(
+            Box::<[u8]>::from_wasm(blob.to_wasm().unwrap()).unwrap(),
+            blob
+        );
+        let blob = bytes::Bytes::from_static(&[1, 2, 3]);
+        assert_eq!(
+            bytes::Bytes::from_wasm(blob.to_wasm().unwrap()).unwrap(),
+            blob
+        );
     }
     #[test]
     fn inet_convert() {
         use std::net::IpAddr;
         let ip = IpAddr::from([127, 0, 0, 1]);
-        assert_eq!(IpAddr::from_wasm(ip.to_wasm()), ip);
+        assert_eq!(IpAddr::from_wasm(ip.to_wasm().unwrap()).unwrap(), ip);
+    }
+    #[test]
+    fn ipv4_convert() {
+        use std::net::{IpAddr, Ipv4Addr};
+        let ip = Ipv4Addr::new(127, 0, 0, 1);
+        assert_eq!(Ipv4Addr::from_wasm(ip.to_wasm().unwrap()).unwrap(), ip);
+        assert_eq!(
+            IpAddr::from_wasm(ip.to_wasm().unwrap()).unwrap(),
+            IpAddr::V4(ip)
+        );
+    }
+    #[test]
+    fn ipv6_convert() {
+        use std::net::Ipv6Addr;
+        let ip = Ipv6Addr::LOCALHOST;
+        assert_eq!(Ipv6Addr::from_wasm(ip.to_wasm().unwrap()).unwrap(), ip);
+    }
+    #[test]
+    fn inet_family_mismatch() {
+        use std::net::{Ipv4Addr, Ipv6Addr};
+        let error = Ipv6Addr::from_wasm(Ipv4Addr::LOCALHOST.to_wasm().unwrap()).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ConversionErrorKind::Deserialize(FromCqlValError::BadVal)
+        );
+        assert!(error.rust_type().ends_with("Ipv6Addr"));
+        assert_eq!(error.offset(), Some(0));
     }
     #[test]
     fn time_convert() {
         use chrono::Duration;
         let t = Time(Duration::hours(3));
-        assert_eq!(Time::from_wasm(t.to_wasm()), t);
+        assert_eq!(Time::from_wasm(t.to_wasm().unwrap()).unwrap(), t);
     }
     #[test]
     fn uuid_convert() {
         use uuid::Uuid;
         let uuid = Uuid::NAMESPACE_OID;
-        assert_eq!(Uuid::from_wasm(uuid.to_wasm()), uuid);
+        assert_eq!(Uuid::from_wasm(uuid.to_wasm().unwrap()).unwrap(), uuid);
     }
     #[test]
     fn big_int_convert() {
+        use core::str::FromStr;
         use num_bigint::BigInt;
-        use std::str::FromStr;
         let bi = BigInt::from_str("420000000000000000").unwrap();
-        assert_eq!(BigInt::from_wasm(bi.to_wasm()), bi);
+        assert_eq!(BigInt::from_wasm(bi.to_wasm().unwrap()).unwrap(), bi);
     }
 
     #[test]
     fn vec_convert() {
         // convert vec of strings
         let vec = vec![String::from("a"), String::from("b")];
-        assert_eq!(Vec::<String>::from_wasm(vec.to_wasm()), vec);
+        assert_eq!(
+            Vec::<String>::from_wasm(vec.to_wasm().unwrap()).unwrap(),
+            vec
+        );
     }
     #[test]
     fn hashset_convert() {
@@ -290,7 +518,10 @@ mod tests {
         let mut set = HashSet::new();
         set.insert(String::from("a"));
         set.insert(String::from("b"));
-        assert_eq!(HashSet::<String>::from_wasm(set.to_wasm()), set);
+        assert_eq!(
+            HashSet::<String>::from_wasm(set.to_wasm().unwrap()).unwrap(),
+            set
+        );
     }
     #[test]
     fn btreeset_convert() {
@@ -298,7 +529,10 @@ mod tests {
         let mut set = BTreeSet::new();
         set.insert((1, String::from("a")));
         set.insert((3, String::from("b")));
-        assert_eq!(BTreeSet::<(i32, String)>::from_wasm(set.to_wasm()), set);
+        assert_eq!(
+            BTreeSet::<(i32, String)>::from_wasm(set.to_wasm().unwrap()).unwrap(),
+            set
+        );
     }
     #[test]
     fn hashmap_convert() {
@@ -306,7 +540,10 @@ mod tests {
         let mut map = HashMap::new();
         map.insert(String::from("a"), 5_i16);
         map.insert(String::from("b"), 55_i16);
-        assert_eq!(HashMap::<String, i16>::from_wasm(map.to_wasm()), map);
+        assert_eq!(
+            HashMap::<String, i16>::from_wasm(map.to_wasm().unwrap()).unwrap(),
+            map
+        );
     }
     #[test]
     fn btreemap_convert() {
@@ -315,13 +552,269 @@ mod tests {
         map.insert((1, 2), String::from("a"));
         map.insert((3, 4), String::from("b"));
         assert_eq!(
-            BTreeMap::<(i32, i32), String>::from_wasm(map.to_wasm()),
+            BTreeMap::<(i32, i32), String>::from_wasm(map.to_wasm().unwrap()).unwrap(),
             map
         );
     }
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn indexset_convert() {
+        use indexmap::IndexSet;
+        let set: IndexSet<String> = [String::from("b"), String::from("a")].into_iter().collect();
+        let converted = IndexSet::<String>::from_wasm(set.to_wasm().unwrap()).unwrap();
+        assert_eq!(converted, set);
+        assert!(converted.iter().eq(["a", "b"]));
+    }
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn indexmap_convert() {
+        use indexmap::IndexMap;
+        let map: IndexMap<String, i16> = [(String::from("b"), 5_i16), (String::from("a"), 55_i16)]
+            .into_iter()
+            .collect();
+        let converted = IndexMap::<String, i16>::from_wasm(map.to_wasm().unwrap()).unwrap();
+        // the entries are serialized in CQL order, and deserialized in the received order
+        assert_eq!(converted, map);
+        assert!(converted.keys().eq(["a", "b"]));
+    }
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn smallvec_convert() {
+        use smallvec::SmallVec;
+        let vec: SmallVec<[String; 2]> = SmallVec::from_vec(vec![String::from("a")]);
+        assert_eq!(
+            SmallVec::<[String; 2]>::from_wasm(vec.to_wasm().unwrap()).unwrap(),
+            vec
+        );
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_convert() {
+        use std::collections::BTreeMap;
+        let text = String::from(r#"{"a":[1,2],"b":[]}"#);
+        let json = Json::<BTreeMap<String, Vec<i32>>>::from_wasm(text.to_wasm().unwrap()).unwrap();
+        assert_eq!(json["a"], vec![1, 2]);
+        assert_eq!(String::from_wasm(json.to_wasm().unwrap()).unwrap(), text);
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_parse_failure() {
+        let error =
+            Json::<Vec<i32>>::from_wasm(String::from("[1, ").to_wasm().unwrap()).unwrap_err();
+        assert!(matches!(error.column_type(), Some(ColumnType::Text)));
+        // the error of serde_json is kept
+        assert!(error
+            .to_string()
+            .ends_with(": Parse error: EOF while parsing a value at line 1 column 4"));
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_encode_failure() {
+        use std::collections::BTreeMap;
+        // JSON objects can only have string keys
+        let json = Json(BTreeMap::from([((1_i32, 2_i32), 3_i32)]));
+        let error = match json.to_wasm() {
+            Ok(_) => panic!("the conversion succeeded"),
+            Err(error) => error,
+        };
+        assert_eq!(
+            error.kind(),
+            &ConversionErrorKind::Encode(String::from("key must be a string"))
+        );
+    }
+    #[cfg(all(feature = "msgpack", feature = "cbor", feature = "bincode"))]
+    #[test]
+    fn blob_codecs_convert() {
+        use std::collections::BTreeMap;
+        fn check<C: BlobCodec>() {
+            let map: BTreeMap<String, (i32, Vec<f64>)> = [(String::from("a"), (1, vec![1.5, 2.5]))]
+                .into_iter()
+                .collect();
+            let blob = Blob::<_, C>::new(map);
+            assert_eq!(
+                Blob::<_, C>::from_wasm(blob.to_wasm().unwrap()).unwrap(),
+                blob
+            );
+        }
+        check::<MessagePack>();
+        check::<Cbor>();
+        check::<Bincode>();
+        // msgpack fixmap with one entry: "a" => 1
+        let raw = vec![0x81, 0xa1, b'a', 0x01];
+        let blob =
+            Blob::<BTreeMap<String, i32>, MessagePack>::from_wasm(raw.to_wasm().unwrap()).unwrap();
+        assert_eq!(blob["a"], 1);
+    }
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn blob_decode_failure() {
+        let error =
+            Blob::<String, Cbor>::from_wasm(vec![0xff_u8, 0x00].to_wasm().unwrap()).unwrap_err();
+        assert!(matches!(error.column_type(), Some(ColumnType::Blob)));
+        assert!(matches!(
+            error.kind(),
+            ConversionErrorKind::Deserialize(FromCqlValError::Parse(_))
+        ));
+    }
     #[test]
     fn tuple_convert() {
         let tup = (String::from("a"), 5_i8);
-        assert_eq!(<(String, i8)>::from_wasm(tup.to_wasm()), tup);
+        assert_eq!(
+            <(String, i8)>::from_wasm(tup.to_wasm().unwrap()).unwrap(),
+            tup
+        );
+    }
+    #[test]
+    fn wide_tuple_convert() {
+        type Wide = (
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            i32,
+            String,
+        );
+        let tup: Wide = (
+            0,
+            1,
+            2,
+            3,
+            4,
+            5,
+            6,
+            7,
+            8,
+            9,
+            10,
+            11,
+            12,
+            13,
+            14,
+            15,
+            16,
+            17,
+            18,
+            19,
+            20,
+            21,
+            22,
+            23,
+            24,
+            25,
+            26,
+            27,
+            28,
+            29,
+            30,
+            String::from("a"),
+        );
+        // std doesn't implement PartialEq for tuples with more than 12 elements
+        let res = Wide::from_wasm(tup.to_wasm().unwrap()).unwrap();
+        assert_eq!(res.0, tup.0);
+        assert_eq!(res.12, tup.12);
+        assert_eq!(res.30, tup.30);
+        assert_eq!(res.31, tup.31);
+    }
+    #[test]
+    fn size_hint_convert() {
+        use crate::serialize_cql::SerializeCql;
+        let value = (
+            vec![String::from("abc"), String::new()],
+            Some(-1_i64),
+            Option::<i32>::None,
+        );
+        let mut buf = Vec::new();
+        value.serialize_cql(&mut buf).unwrap();
+        assert_eq!(value.size_hint(), buf.len());
+        assert_eq!(
+            <(Vec<String>, Option<i64>, Option<i32>)>::from_wasm(value.to_wasm().unwrap()).unwrap(),
+            value
+        );
+        let empty = String::new();
+        assert_eq!(String::from_wasm(empty.to_wasm().unwrap()).unwrap(), empty);
+    }
+    #[test]
+    fn exact_size_hints() {
+        use crate::serialize_cql::SerializeCql;
+        use std::collections::{BTreeSet, HashSet};
+        fn check<T: SerializeCql>(value: T) {
+            let mut buf = Vec::new();
+            value.serialize_cql(&mut buf).unwrap();
+            assert_eq!(value.size_hint(), buf.len());
+        }
+        for shift in 0..80 {
+            let power = num_bigint::BigInt::from(1) << shift as usize;
+            for varint in [&power - 1, power.clone(), -&power, -&power - 1] {
+                check(bigdecimal::BigDecimal::new(varint.clone(), 3));
+                check(varint);
+            }
+        }
+        for value in [0, 1, -64, 1 << 40, i64::MIN, i64::MAX] {
+            check(CqlDuration {
+                months: value as i32,
+                days: (value >> 32) as i32,
+                nanoseconds: value,
+            });
+        }
+        check(CqlValue::Map(vec![(
+            CqlValue::Text(String::from("a")),
+            CqlValue::Tuple(vec![
+                Some(CqlValue::Decimal("-1.05".parse().unwrap())),
+                None,
+                Some(CqlValue::Empty),
+            ]),
+        )]));
+        // the elements of sets are reordered unless they're already in the CQL order
+        check(BTreeSet::from([String::from("b"), String::from("a")]));
+        check(HashSet::from([1_i32, -2, 3, -4, 5, -6]));
+    }
+    #[test]
+    fn array_convert() {
+        let arr = [String::from("a"), String::from("b"), String::from("c")];
+        assert_eq!(
+            <[String; 3]>::from_wasm(arr.to_wasm().unwrap()).unwrap(),
+            arr
+        );
+        // arrays can be read from lists of the same length
+        let vec = vec![1_i64, 2_i64];
+        assert_eq!(
+            <[i64; 2]>::from_wasm(vec.to_wasm().unwrap()).unwrap(),
+            [1_i64, 2_i64]
+        );
+    }
+    #[test]
+    fn array_length_mismatch() {
+        let vec = vec![1_i64, 2_i64, 3_i64];
+        let error = <[i64; 2]>::from_wasm(vec.to_wasm().unwrap()).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ConversionErrorKind::Deserialize(FromCqlValError::BadVal)
+        );
     }
 }
@@ -0,0 +1,212 @@
+use crate::codec::FromCqlValError;
+use crate::codec::{decode_value, encode_value_with_type, encoded_size};
+use crate::conversion_error::{ConversionError, ConversionErrorKind};
+use crate::cql_value::{ColumnType, CqlValue};
+use crate::to_wasmptr::serialize_to_wasmptr;
+use crate::wasmptr::WasmPtr;
+
+/// Conversion of a dynamically typed value, whose CQL type is only known from the `types`
+/// attribute of the `#[export_udf]` macro, to and from its Wasm representation.
+pub trait WasmConvertibleWithType: Sized {
+    fn from_wasm_with_type(arg: WasmPtr, typ: &ColumnType) -> Result<Self, ConversionError>;
+    fn to_wasm_with_type(&self, typ: &ColumnType) -> Result<WasmPtr, ConversionError>;
+}
+
+// Checks whether a value can be sent to Scylla as a value of the given type
+fn value_matches_type(value: &CqlValue, typ: &ColumnType) -> bool {
+    match (value, typ) {
+        (CqlValue::Empty, _) => true,
+        (_, ColumnType::Custom(_)) => true,
+        (CqlValue::Ascii(_), ColumnType::Ascii | ColumnType::Text) => true,
+        (CqlValue::Text(_), ColumnType::Ascii | ColumnType::Text) => true,
+        (CqlValue::Uuid(_) | CqlValue::Timeuuid(_), ColumnType::Uuid | ColumnType::Timeuuid) => {
+            true
+        }
+        (CqlValue::Boolean(_), ColumnType::Boolean)
+        | (CqlValue::Blob(_), ColumnType::Blob)
+        | (CqlValue::Counter(_), ColumnType::Counter)
+        | (CqlValue::Date(_), ColumnType::Date)
+        | (CqlValue::Decimal(_), ColumnType::Decimal)
+        | (CqlValue::Double(_), ColumnType::Double)
+        | (CqlValue::Duration(_), ColumnType::Duration)
+        | (CqlValue::Float(_), ColumnType::Float)
+        | (CqlValue::Int(_), ColumnType::Int)
+        | (CqlValue::BigInt(_), ColumnType::BigInt)
+        | (CqlValue::Timestamp(_), ColumnType::Timestamp)
+        | (CqlValue::SmallInt(_), ColumnType::SmallInt)
+        | (CqlValue::TinyInt(_), ColumnType::TinyInt)
+        | (CqlValue::Time(_), ColumnType::Time)
+        | (CqlValue::Varint(_), ColumnType::Varint) => true,
+        #[cfg(feature = "std")]
+        (CqlValue::Inet(_), ColumnType::Inet) => true,
+        (CqlValue::List(elems), ColumnType::List(elem_type))
+        | (CqlValue::Set(elems), ColumnType::Set(elem_type)) => {
+            elems.iter().all(|e| value_matches_type(e, elem_type))
+        }
+        (CqlValue::Map(entries), ColumnType::Map(key_type, value_type)) => entries
+            .iter()
+            .all(|(k, v)| value_matches_type(k, key_type) && value_matches_type(v, value_type)),
+        (CqlValue::Tuple(elems), ColumnType::Tuple(elem_types)) => {
+            elems.len() == elem_types.len()
+                && elems.iter().zip(elem_types).all(|(e, t)| match e {
+                    Some(e) => value_matches_type(e, t),
+                    None => true,
+                })
+        }
+        (
+            CqlValue::UserDefinedType { fields, .. },
+            ColumnType::UserDefinedType { field_types, .. },
+        ) => {
+            fields.len() == field_types.len()
+                && fields
+                    .iter()
+                    .zip(field_types)
+                    .all(|((name, value), (type_name, typ))| {
+                        name == type_name
+                            && match value {
+                                Some(value) => value_matches_type(value, typ),
+                                None => true,
+                            }
+                    })
+        }
+        _ => false,
+    }
+}
+
+impl WasmConvertibleWithType for CqlValue {
+    fn from_wasm_with_type(arg: WasmPtr, typ: &ColumnType) -> Result<Self, ConversionError> {
+        let bytes = arg.as_slice().ok_or_else(|| {
+            ConversionError::deserialize::<Self>(FromCqlValError::ValIsNull, typ, None)
+        })?;
+        decode_value(typ, bytes)
+            .map_err(|e| ConversionError::deserialize::<Self>(e, typ, Some(bytes)))
+    }
+
+    fn to_wasm_with_type(&self, typ: &ColumnType) -> Result<WasmPtr, ConversionError> {
+        if !value_matches_type(self, typ) {
+            return Err(
+                ConversionError::new::<Self>(ConversionErrorKind::TypeMismatch)
+                    .with_column_type(typ.clone()),
+            );
+        }
+        serialize_to_wasmptr::<Self>(encoded_size(self), |buf| {
+            encode_value_with_type(self, typ, buf)
+        })
+    }
+}
+
+impl WasmConvertibleWithType for Option<CqlValue> {
+    fn from_wasm_with_type(arg: WasmPtr, typ: &ColumnType) -> Result<Self, ConversionError> {
+        if arg.is_null() {
+            return Ok(None);
+        }
+        CqlValue::from_wasm_with_type(arg, typ).map(Some)
+    }
+
+    fn to_wasm_with_type(&self, typ: &ColumnType) -> Result<WasmPtr, ConversionError> {
+        match self {
+            Some(value) => value.to_wasm_with_type(typ),
+            None => Ok(WasmPtr::null()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WasmConvertibleWithType;
+    use crate::cql_value::ColumnType;
+    use crate::mapping::WasmPtr;
+    use crate::*;
+
+    #[test]
+    fn cql_value_convert() {
+        let typ = ColumnType::Map(
+            Box::new(ColumnType::Text),
+            Box::new(ColumnType::List(Box::new(ColumnType::Int))),
+        );
+        let val = CqlValue::Map(vec![(
+            CqlValue::Text(String::from("a")),
+            CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)]),
+        )]);
+        assert_eq!(
+            CqlValue::from_wasm_with_type(val.to_wasm_with_type(&typ).unwrap(), &typ).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn cql_value_sorted_sets_and_maps() {
+        let round_trip = |val: CqlValue, typ: &ColumnType| {
+            CqlValue::from_wasm_with_type(val.to_wasm_with_type(typ).unwrap(), typ).unwrap()
+        };
+        let int_set = ColumnType::Set(Box::new(ColumnType::Int));
+        let ints = |ints: &[i32]| ints.iter().copied().map(CqlValue::Int).collect::<Vec<_>>();
+        assert_eq!(
+            round_trip(CqlValue::Set(ints(&[3, -1, 3, 2])), &int_set),
+            CqlValue::Set(ints(&[-1, 2, 3]))
+        );
+        // duplicate keys keep the first value, nested sets are sorted too
+        let typ = ColumnType::Map(
+            Box::new(ColumnType::Text),
+            Box::new(ColumnType::Tuple(vec![int_set.clone()])),
+        );
+        let entry = |key: &str, value: &[i32]| {
+            (
+                CqlValue::Text(String::from(key)),
+                CqlValue::Tuple(vec![Some(CqlValue::Set(ints(value)))]),
+            )
+        };
+        assert_eq!(
+            round_trip(
+                CqlValue::Map(vec![entry("b", &[2, 1]), entry("a", &[]), entry("b", &[3])]),
+                &typ
+            ),
+            CqlValue::Map(vec![entry("a", &[]), entry("b", &[1, 2])])
+        );
+    }
+    #[test]
+    fn option_cql_value_convert() {
+        let typ = ColumnType::Int;
+        assert_eq!(
+            Option::<CqlValue>::from_wasm_with_type(None.to_wasm_with_type(&typ).unwrap(), &typ)
+                .unwrap(),
+            None
+        );
+        let val = Some(CqlValue::Int(7));
+        assert_eq!(
+            Option::<CqlValue>::from_wasm_with_type(val.to_wasm_with_type(&typ).unwrap(), &typ)
+                .unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn cql_value_type_mismatch() {
+        let error = CqlValue::Int(7)
+            .to_wasm_with_type(&ColumnType::Text)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), &ConversionErrorKind::TypeMismatch);
+        assert_eq!(
+            error.to_string(),
+            "Failed to convert a value from scylla_udf::cql_value::CqlValue to CQL type text: value doesn't match the declared type"
+        );
+    }
+    #[test]
+    fn cql_value_malformed() {
+        let typ = ColumnType::List(Box::new(ColumnType::Int));
+        // the second element is cut off
+        let bytes = [0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 4, 0];
+        let mut arg = WasmPtr::with_size(bytes.len() as u32).unwrap();
+        arg.as_mut_slice().unwrap().copy_from_slice(&bytes);
+        let error = CqlValue::from_wasm_with_type(arg, &typ).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ConversionErrorKind::Deserialize(FromCqlValError::BadVal)
+        );
+        assert_eq!(error.offset(), Some(12));
+        let error = CqlValue::from_wasm_with_type(WasmPtr::null(), &typ).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ConversionErrorKind::Deserialize(FromCqlValError::ValIsNull)
+        );
+    }
+}
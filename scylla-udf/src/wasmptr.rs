@@ -1,63 +1,153 @@
-use crate::abi_exports::{_scylla_free, _scylla_malloc};
+use crate::abi_exports::{alloc_buffer, buffer_layout, free_buffer, HEADER_SIZE};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::TryInto;
 
-// A unique pointer to an object in the WASM memory.
-// Contains the serialized size of the object in the high 32 bits, and the pointer
-// to the object in the low 32 bits. A null pointer is represented by a size of u32::MAX.
-// The pointer is allocated with _scylla_malloc and freed with _scylla_free.
+/// A unique pointer to a serialized value in the Wasm memory, used to pass values that aren't
+/// native Wasm types between Scylla and the UDF.
+///
+/// Contains the size of the value in the high 32 bits, and the pointer to it in the low 32 bits.
+/// A null value is represented by a size of `u32::MAX`. The memory is allocated with `_scylla_malloc`
+/// and freed with `_scylla_free` when the `WasmPtr` is dropped, so a `WasmPtr` received as an argument
+/// is owned by the UDF, and a `WasmPtr` returned from it is freed by Scylla. Both functions use the
+/// Rust global allocator, and store the size of the buffer in the 4 bytes preceding it.
+///
+/// On other targets than wasm32, where pointers don't fit in 32 bits, a `WasmPtr` holds the size
+/// and a native pointer instead, so that conversions and UDFs can be tested with `cargo test`.
 #[repr(transparent)]
-pub struct WasmPtr(u64);
+pub struct WasmPtr(Repr);
+
+#[cfg(target_arch = "wasm32")]
+type Repr = u64;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[repr(C)]
+struct Repr {
+    size: u32,
+    ptr: *mut u8,
+}
+
+// SAFETY: the WasmPtr uniquely owns its buffer, like the packed pointer on wasm32
+#[cfg(not(target_arch = "wasm32"))]
+unsafe impl Send for Repr {}
+// SAFETY: the buffer can only be modified through a mutable reference to the WasmPtr
+#[cfg(not(target_arch = "wasm32"))]
+unsafe impl Sync for Repr {}
 
 impl WasmPtr {
+    #[cfg(target_arch = "wasm32")]
+    fn from_parts(size: u32, ptr: *mut u8) -> WasmPtr {
+        WasmPtr(((size as u64) << 32) + ptr as u64)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_parts(size: u32, ptr: *mut u8) -> WasmPtr {
+        WasmPtr(Repr { size, ptr })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    const fn raw_size(&self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    const fn raw_size(&self) -> u32 {
+        self.0.size
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn raw(&self) -> *mut u8 {
+        (self.0 & 0xffffffff) as *mut u8
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn raw(&self) -> *mut u8 {
+        self.0.ptr
+    }
+
+    /// Allocates an uninitialized buffer of the given size.
+    /// Returns `None` if the allocation fails or the size is `u32::MAX`.
     pub fn with_size(size: u32) -> Option<WasmPtr> {
         if size == u32::MAX {
             // u32::MAX is reserved for null
             return None;
         }
 
-        // SAFETY: the size fits in a u32, so it's valid to allocate that much memory
-        // and we do not dereference the pointer if the allocation fails
-        let ptr = unsafe { _scylla_malloc(size) };
-        if ptr == 0 {
+        let ptr = alloc_buffer(size);
+        if ptr.is_null() {
             return None;
         }
-        Some(WasmPtr(((size as u64) << 32) + ptr as u64))
+        Some(WasmPtr::from_parts(size, ptr))
     }
 
+    /// Takes over a buffer with a serialized value preceded by its size, as written by `SerializeCql`,
+    /// without copying it: the size becomes the header of the buffer allocated by `alloc_buffer`.
+    ///
+    /// Panics if the size doesn't match the length of the buffer, which `ToWasmPtr` checks beforehand.
+    pub(crate) fn from_serialized(buf: Vec<u8>) -> WasmPtr {
+        let size = u32::from_be_bytes(buf[..HEADER_SIZE].try_into().unwrap());
+        if size == u32::MAX {
+            return WasmPtr::null();
+        }
+        assert_eq!(
+            Some(buf.len()),
+            buffer_layout(size).map(|layout| layout.size()),
+            "Serialized value size doesn't match the number of written bytes"
+        );
+        // into_boxed_slice shrinks the allocation to the exact length if the capacity is bigger,
+        // so that the buffer has the layout expected by free_buffer
+        let ptr = Box::into_raw(buf.into_boxed_slice()) as *mut u8;
+        // SAFETY: the buffer is at least HEADER_SIZE bytes long
+        let ptr = unsafe { ptr.add(HEADER_SIZE) };
+        WasmPtr::from_parts(size, ptr)
+    }
+
+    /// The size of the value, or `None` for null.
     pub const fn size(&self) -> Option<usize> {
-        let size = self.0 >> 32;
-        if size == u32::MAX as u64 {
+        let size = self.raw_size();
+        if size == u32::MAX {
             None
         } else {
             Some(size as usize)
         }
     }
 
+    #[cfg(target_arch = "wasm32")]
     pub const fn null() -> WasmPtr {
         WasmPtr((u32::MAX as u64) << 32)
     }
 
-    pub const fn is_null(&self) -> bool {
-        self.size().is_none()
-    }
-
-    fn raw(&self) -> *mut u8 {
-        (self.0 & 0xffffffff) as *mut u8
+    #[cfg(not(target_arch = "wasm32"))]
+    pub const fn null() -> WasmPtr {
+        WasmPtr(Repr {
+            size: u32::MAX,
+            ptr: core::ptr::null_mut(),
+        })
     }
 
-    fn raw_mut(&self) -> *mut u8 {
-        (self.0 & 0xffffffff) as *mut u8
+    pub const fn is_null(&self) -> bool {
+        self.size().is_none()
     }
 
-    pub fn as_slice<'a>(&self) -> Option<&'a [u8]> {
-        // SAFETY: the `dest` pointer is a succesful result of allocating `size` bytes and it's always aligned to a u8
-        self.size()
-            .map(|size| unsafe { std::slice::from_raw_parts::<'a>(self.raw(), size) })
+    /// The bytes of the value, or `None` for null.
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        // The pointer of an empty value may be null, so it's not used to create an empty slice
+        match self.size() {
+            Some(0) => Some(&[]),
+            // SAFETY: the `dest` pointer is a succesful result of allocating `size` bytes and it's always aligned to a u8
+            Some(size) => Some(unsafe { core::slice::from_raw_parts(self.raw(), size) }),
+            None => None,
+        }
     }
 
-    pub fn as_mut_slice<'a>(&mut self) -> Option<&'a mut [u8]> {
+    /// The buffer of the value, to be filled after `with_size`, or `None` for null.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        if self.size() == Some(0) {
+            return Some(&mut []);
+        }
         if let Some(size) = self.size() {
             // SAFETY: the `dest` pointer is a succesful result of allocating `size` bytes and it's always aligned to a u8
-            Some(unsafe { std::slice::from_raw_parts_mut::<'a>(self.raw_mut(), size) })
+            Some(unsafe { core::slice::from_raw_parts_mut(self.raw(), size) })
         } else {
             None
         }
@@ -67,8 +157,36 @@ impl WasmPtr {
 impl Drop for WasmPtr {
     fn drop(&mut self) {
         if !self.is_null() {
-            // SAFETY: the `dest` pointer is a succesful result of a _scylla_malloc call, so it's valid
-            unsafe { _scylla_free(self.raw() as u32) };
+            // SAFETY: the `dest` pointer is a succesful result of an alloc_buffer call, so it's valid
+            unsafe { free_buffer(self.raw()) };
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_size_boundaries() {
+        let mut empty = WasmPtr::with_size(0).unwrap();
+        assert_eq!(empty.size(), Some(0));
+        assert_eq!(empty.as_mut_slice(), Some(&mut [][..]));
+        let mut one = WasmPtr::with_size(1).unwrap();
+        one.as_mut_slice().unwrap()[0] = 7;
+        assert_eq!(one.as_slice(), Some(&[7][..]));
+        // u32::MAX is reserved for null
+        assert!(WasmPtr::with_size(u32::MAX).is_none());
+        assert!(WasmPtr::null().as_slice().is_none());
+    }
+
+    // A buffer can't be bigger than isize::MAX bytes including its header
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn oversized_with_size() {
+        let max_size = i32::MAX as u32 - HEADER_SIZE as u32;
+        assert!(WasmPtr::with_size(max_size + 1).is_none());
+        assert!(WasmPtr::with_size(u32::MAX - HEADER_SIZE as u32).is_none());
+        assert!(WasmPtr::with_size(u32::MAX - 1).is_none());
+    }
+}
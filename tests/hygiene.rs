@@ -11,20 +11,38 @@ struct TestStruct {
 #[::_scylla_udf::export_newtype(crate = "_scylla_udf")]
 struct TestNewtype(::core::primitive::i32);
 
+// Json doesn't implement CqlOrd, which only leaves out the CqlOrd impl of the struct
+#[::_scylla_udf::export_udt(crate = "_scylla_udf")]
+struct TestJsonStruct {
+    a: ::_scylla_udf::Json<::std::vec::Vec<::core::primitive::i32>>,
+}
+
 // Macro can only be expanded if TestStruct and TestNewtype were
 // properly expanded.
-#[::_scylla_udf::export_udf(crate = "_scylla_udf")]
+#[::_scylla_udf::export_udf(crate = "_scylla_udf", max_result_size = 1024)]
 fn test_fn(arg1: TestNewtype, arg2: TestStruct) -> (TestNewtype, TestStruct) {
     (arg1, arg2)
 }
 
+#[::_scylla_udf::export_udf(crate = "_scylla_udf")]
+fn test_json_fn(arg: TestJsonStruct) -> ::core::primitive::bool {
+    arg.a.is_empty()
+}
+
 #[test]
 fn test_renamed() {
     use ::_scylla_udf::_macro_internal::WasmConvertible;
     let arg1 = TestNewtype(16);
     let arg2 = TestStruct { a: 16 };
-    let rets = _scylla_internal_test_fn(arg1.to_wasm(), arg2.to_wasm());
-    let (ret1, ret2) = <(TestNewtype, TestStruct)>::from_wasm(rets);
+    let rets = _scylla_internal_test_fn(arg1.to_wasm().unwrap(), arg2.to_wasm().unwrap());
+    let (ret1, ret2) = <(TestNewtype, TestStruct)>::from_wasm(rets).unwrap();
     ::std::assert_eq!(arg1, ret1);
     ::std::assert_eq!(arg2, ret2);
 }
+
+#[test]
+fn test_cql_ord() {
+    use ::_scylla_udf::CqlOrd;
+    ::std::assert!(TestNewtype(-1).cql_cmp(&TestNewtype(1)).is_lt());
+    ::std::assert!(TestStruct { a: 2 }.cql_cmp(&TestStruct { a: 1 }).is_gt());
+}
@@ -0,0 +1,61 @@
+use scylla_udf::_macro_internal::WasmConvertible;
+
+#[cfg(feature = "arena")]
+#[global_allocator]
+// SAFETY: the tests don't use values allocated during a call after it returns
+static ALLOCATOR: scylla_udf::Arena = unsafe { scylla_udf::Arena::new() };
+
+scylla_udf::protobuf_field_udf! {
+    fn user_city(user) -> Option<String> {
+        descriptor: include_bytes!("users.bin"),
+        message: "users.User",
+        path: "address.city",
+    }
+}
+
+scylla_udf::protobuf_field_udf! {
+    fn user_scores(user) -> Vec<i32> {
+        descriptor: include_bytes!("users.bin"),
+        message: "users.User",
+        path: "scores",
+    }
+}
+
+// User { name: "Ann", address: Address { city: "Oslo" }, scores: [3, 5] }
+const ANN: &[u8] = b"\x0a\x03Ann\x12\x06\x0a\x04Oslo\x1a\x02\x03\x05";
+// User { name: "Bob" }
+const BOB: &[u8] = b"\x0a\x03Bob";
+
+#[test]
+fn test_nested_field() {
+    let city = _scylla_internal_user_city(ANN.to_vec().to_wasm().unwrap());
+    assert_eq!(
+        <Option<String>>::from_wasm(city).unwrap(),
+        Some("Oslo".to_string())
+    );
+}
+
+#[test]
+fn test_repeated_field() {
+    let scores = _scylla_internal_user_scores(ANN.to_vec().to_wasm().unwrap());
+    assert_eq!(<Vec<i32>>::from_wasm(scores).unwrap(), vec![3, 5]);
+}
+
+// The extractor is created by the first call and reused by the following ones, so it must not be
+// freed with the memory allocated during the call, e.g. when the `arena` feature is enabled
+#[test]
+fn test_repeated_calls() {
+    for _ in 0..3 {
+        let city = _scylla_internal_user_city(ANN.to_vec().to_wasm().unwrap());
+        assert_eq!(
+            <Option<String>>::from_wasm(city).unwrap(),
+            Some("Oslo".to_string())
+        );
+        let city = _scylla_internal_user_city(BOB.to_vec().to_wasm().unwrap());
+        assert_eq!(<Option<String>>::from_wasm(city).unwrap(), None);
+        // another call, which overwrites the memory freed after the previous ones
+        let _call_scope = scylla_udf::_macro_internal::CallScope::enter();
+        let garbage: Vec<Vec<u8>> = (0..256).map(|_| vec![0xff; 1024]).collect();
+        std::hint::black_box(garbage);
+    }
+}